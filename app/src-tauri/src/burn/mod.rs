@@ -0,0 +1,331 @@
+//! Identity Burn
+//!
+//! Retires a sock puppet without leaving forensic residue. Where
+//! `Identity::destroy` only flips the status, a burn tears down the live
+//! browser instance, shreds its profile directory, clears the session blob,
+//! privacy profile and captured pages, scrubs the identity from entity
+//! sources, timeline events and graph edges, and finally deletes the identity
+//! record.
+//!
+//! Intelligence the identity gathered is kept by default and re-attributed to
+//! [`BURNED_ATTRIBUTION`]; with `purge_entities` its sightings are dropped
+//! instead. The audit log is append-only, so earlier records naming the
+//! identity remain (they never carried session contents); the burn itself is
+//! recorded there with the receipt ID.
+
+use crate::cef;
+use crate::core::privacy_engine::PrivacyProfile;
+use crate::investigation::{self, Investigation};
+use crate::storage::audit::{AuditAction, SYSTEM_ACTOR};
+use crate::storage::{self, EntityFilter, StorageError, Store};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Identity ID that burned identities' attributions are rewritten to
+pub const BURNED_ATTRIBUTION: &str = "burned";
+
+/// How a burn treats what the identity collected
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BurnOptions {
+    /// Drop the identity's entity sightings (deleting entities left with
+    /// none) instead of re-attributing them
+    #[serde(default)]
+    pub purge_entities: bool,
+}
+
+/// What a burn removed, returned to the caller as proof of destruction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DestructionReceipt {
+    pub receipt_id: String,
+    pub identity_id: String,
+    pub burned_at: DateTime<Utc>,
+    /// Whether a browser instance was still running
+    pub browser_torn_down: bool,
+    pub history_entries_cleared: usize,
+    pub profile_files_shredded: u64,
+    pub profile_bytes_shredded: u64,
+    /// Whether a session blob existed
+    pub session_cleared: bool,
+    /// Whether stored privacy settings and site assessments existed
+    pub privacy_profile_deleted: bool,
+    pub pages_deleted: usize,
+    pub sources_reattributed: usize,
+    pub sources_removed: usize,
+    pub entities_deleted: usize,
+    pub timeline_events_reattributed: usize,
+    pub graph_edges_reattributed: usize,
+    /// Whether the burned identity was active (Prime is active afterwards)
+    pub switched_to_prime: bool,
+    /// Sequence number of the audit record for the burn
+    pub audit_seq: u64,
+}
+
+/// Entity sources touched by [`scrub_entities`]
+#[derive(Debug, Clone, Copy, Default)]
+struct EntityScrub {
+    reattributed: usize,
+    removed: usize,
+    deleted: usize,
+}
+
+/// Burn an identity in the open vault.
+///
+/// Prime cannot be burned. If the burned identity is active, Prime becomes
+/// the active identity first.
+pub fn burn_identity(
+    identity_id: &str,
+    options: &BurnOptions,
+) -> Result<DestructionReceipt, String> {
+    if identity_id == "prime" {
+        return Err("Cannot burn Prime identity".to_string());
+    }
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    store
+        .get_identity(identity_id)
+        .map_err(|e| format!("Storage error: {}", e))?
+        .ok_or_else(|| format!("Identity {} not found", identity_id))?;
+
+    let switched_to_prime = store
+        .get_active_identity()
+        .map_err(|e| format!("Storage error: {}", e))?
+        .id
+        == identity_id;
+    if switched_to_prime {
+        store
+            .set_active_identity("prime")
+            .map_err(|e| format!("Failed to switch to Prime: {}", e))?;
+    }
+
+    let context = cef::with_manager_mut(|mgr| mgr.burn_context(identity_id))?;
+
+    let session_cleared = store
+        .get_session(identity_id)
+        .map_err(|e| format!("Storage error: {}", e))?
+        .is_some();
+    store
+        .clear_session(identity_id)
+        .map_err(|e| format!("Failed to clear session: {}", e))?;
+
+    // Unsaved counters must not write the profile back after it is deleted
+    crate::commands::privacy::forget(identity_id)?;
+    let privacy_key = PrivacyProfile::setting_key(identity_id);
+    let privacy_profile_deleted = store
+        .get_setting(&privacy_key)
+        .map_err(|e| format!("Storage error: {}", e))?
+        .is_some();
+    store
+        .delete_setting(&privacy_key)
+        .map_err(|e| format!("Failed to delete privacy profile: {}", e))?;
+
+    let pages_deleted = store
+        .delete_pages_by_identity(identity_id)
+        .map_err(|e| format!("Failed to delete pages: {}", e))?;
+
+    let entities = scrub_entities(store.as_ref(), identity_id, options.purge_entities)
+        .map_err(|e| format!("Failed to scrub entities: {}", e))?;
+
+    let (timeline_events_reattributed, graph_edges_reattributed) =
+        investigation::with_investigations_mut(|investigations| {
+            let mut totals = (0, 0);
+            for inv in investigations.values_mut() {
+                let (events, edges) = scrub_investigation(inv, identity_id);
+                totals.0 += events;
+                totals.1 += edges;
+            }
+            Ok(totals)
+        })?;
+
+    store
+        .delete_identity(identity_id)
+        .map_err(|e| format!("Failed to delete identity: {}", e))?;
+
+    let receipt_id = uuid::Uuid::new_v4().to_string();
+    let record = store
+        .append_audit(
+            SYSTEM_ACTOR,
+            AuditAction::IdentityBurned {
+                identity_id: identity_id.to_string(),
+                receipt_id: receipt_id.clone(),
+            },
+        )
+        .map_err(|e| format!("Audit error: {}", e))?;
+    store.flush().map_err(|e| format!("Storage error: {}", e))?;
+
+    tracing::info!(
+        "Burned identity {} (receipt {}, {} pages, {} files shredded)",
+        identity_id,
+        receipt_id,
+        pages_deleted,
+        context.files_shredded
+    );
+
+    Ok(DestructionReceipt {
+        receipt_id,
+        identity_id: identity_id.to_string(),
+        burned_at: record.timestamp,
+        browser_torn_down: context.was_live,
+        history_entries_cleared: context.history_entries,
+        profile_files_shredded: context.files_shredded,
+        profile_bytes_shredded: context.bytes_shredded,
+        session_cleared,
+        privacy_profile_deleted,
+        pages_deleted,
+        sources_reattributed: entities.reattributed,
+        sources_removed: entities.removed,
+        entities_deleted: entities.deleted,
+        timeline_events_reattributed,
+        graph_edges_reattributed,
+        switched_to_prime,
+        audit_seq: record.seq,
+    })
+}
+
+/// Re-attribute (or with `purge`, remove) every entity source discovered by
+/// `identity_id`
+fn scrub_entities(
+    store: &dyn Store,
+    identity_id: &str,
+    purge: bool,
+) -> Result<EntityScrub, StorageError> {
+    // Collect first: rewriting sources moves entities out of the index
+    // being paged through.
+    let filter = EntityFilter::Identity(identity_id.to_string());
+    let mut hashes = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = store.query_entities(&filter, cursor.as_deref(), 500)?;
+        hashes.extend(page.entities.into_iter().map(|e| e.hash));
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    let mut scrub = EntityScrub::default();
+    for hash in hashes {
+        let Some(mut entity) = store.get_entity(&hash)? else {
+            continue;
+        };
+
+        if purge {
+            let before = entity.sources.len();
+            entity.sources.retain(|s| s.identity_id != identity_id);
+            let removed = before - entity.sources.len();
+            scrub.removed += removed;
+
+            if entity.sources.is_empty() {
+                store.delete_entity(&hash)?;
+                scrub.deleted += 1;
+                continue;
+            }
+            entity.occurrence_count = entity
+                .occurrence_count
+                .saturating_sub(removed as u32)
+                .max(1);
+            if let Some(first) = entity.sources.iter().map(|s| s.timestamp).min() {
+                entity.first_seen = first;
+            }
+            if let Some(last) = entity.sources.iter().map(|s| s.timestamp).max() {
+                entity.last_seen = last;
+            }
+        } else {
+            for source in entity
+                .sources
+                .iter_mut()
+                .filter(|s| s.identity_id == identity_id)
+            {
+                source.identity_id = BURNED_ATTRIBUTION.to_string();
+                scrub.reattributed += 1;
+            }
+        }
+        store.save_entity(&entity)?;
+    }
+    Ok(scrub)
+}
+
+/// Re-attribute a case's timeline events and graph edges, returning how many
+/// of each changed
+fn scrub_investigation(inv: &mut Investigation, identity_id: &str) -> (usize, usize) {
+    let mut events = 0;
+    for event in inv
+        .timeline
+        .iter_mut()
+        .filter(|e| e.identity_id == identity_id)
+    {
+        event.identity_id = BURNED_ATTRIBUTION.to_string();
+        events += 1;
+    }
+
+    let mut edges = 0;
+    for edge in inv
+        .graph
+        .edges
+        .iter_mut()
+        .filter(|e| e.discovered_by == identity_id)
+    {
+        edge.discovered_by = BURNED_ATTRIBUTION.to_string();
+        edges += 1;
+    }
+
+    if events + edges > 0 {
+        inv.updated_at = Utc::now();
+    }
+    (events, edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entity::{Entity, EntitySource, EntityType};
+    use crate::storage::MemoryStore;
+
+    fn shared_and_solo(store: &MemoryStore) -> (Entity, Entity) {
+        let mut shared = Entity::new(
+            EntityType::Email,
+            "a@example.com".to_string(),
+            EntitySource::new("dupe"),
+        );
+        shared.sources.push(EntitySource::new("prime"));
+        let solo = Entity::new(
+            EntityType::Domain,
+            "example.org".to_string(),
+            EntitySource::new("dupe"),
+        );
+        store.save_entity(&shared).unwrap();
+        store.save_entity(&solo).unwrap();
+        (shared, solo)
+    }
+
+    #[test]
+    fn test_sources_are_reattributed() {
+        let store = MemoryStore::new();
+        let (shared, solo) = shared_and_solo(&store);
+
+        let scrub = scrub_entities(&store, "dupe", false).unwrap();
+        assert_eq!(scrub.reattributed, 2);
+
+        let filter = EntityFilter::Identity("dupe".to_string());
+        assert!(store
+            .query_entities(&filter, None, 10)
+            .unwrap()
+            .entities
+            .is_empty());
+        let solo = store.get_entity(&solo.hash).unwrap().unwrap();
+        assert_eq!(solo.unique_sources(), vec![BURNED_ATTRIBUTION.to_string()]);
+        assert!(store.get_entity(&shared.hash).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_purge_drops_sightings() {
+        let store = MemoryStore::new();
+        let (shared, solo) = shared_and_solo(&store);
+
+        let scrub = scrub_entities(&store, "dupe", true).unwrap();
+        assert_eq!((scrub.removed, scrub.deleted), (2, 1));
+
+        assert!(store.get_entity(&solo.hash).unwrap().is_none());
+        let shared = store.get_entity(&shared.hash).unwrap().unwrap();
+        assert_eq!(shared.unique_sources(), vec!["prime".to_string()]);
+    }
+}
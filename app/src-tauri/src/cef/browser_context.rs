@@ -15,7 +15,7 @@ use crate::core::fingerprint::Fingerprint;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// An isolated browser context for a single identity
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -186,6 +186,61 @@ impl BrowserContext {
     }
 }
 
+/// Files and bytes destroyed by [`shred_dir`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShredStats {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Overwrite every file under `path` with random bytes, sync it to disk,
+/// then delete the whole tree.
+///
+/// Journaling and copy-on-write filesystems or SSD wear levelling may still
+/// keep old blocks; the overwrite defeats casual recovery from the profile
+/// directory itself.
+pub fn shred_dir(path: &Path) -> std::io::Result<ShredStats> {
+    let mut stats = ShredStats::default();
+    if !path.exists() {
+        return Ok(stats);
+    }
+    overwrite_tree(path, &mut stats)?;
+    std::fs::remove_dir_all(path)?;
+    Ok(stats)
+}
+
+fn overwrite_tree(path: &Path, stats: &mut ShredStats) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            overwrite_tree(&entry.path(), stats)?;
+        } else if file_type.is_file() {
+            stats.bytes += overwrite_file(&entry.path())?;
+            stats.files += 1;
+        }
+    }
+    Ok(())
+}
+
+fn overwrite_file(path: &Path) -> std::io::Result<u64> {
+    use rand::RngCore;
+    use std::io::Write;
+
+    let len = std::fs::metadata(path)?.len();
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let mut chunk = vec![0u8; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(chunk.len() as u64) as usize;
+        rand::thread_rng().fill_bytes(&mut chunk[..n]);
+        file.write_all(&chunk[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    Ok(len)
+}
+
 /// Calculate directory size recursively
 fn dir_size(path: &PathBuf) -> u64 {
     if !path.exists() {
@@ -117,6 +117,17 @@ pub struct NavigationEntry {
     pub identity_id: String,
 }
 
+/// What [`CefManager::burn_context`] removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextBurn {
+    /// Whether a browser instance was still running
+    pub was_live: bool,
+    /// Navigation history entries discarded
+    pub history_entries: usize,
+    pub files_shredded: u64,
+    pub bytes_shredded: u64,
+}
+
 /// CEF Manager - orchestrates all browser instances
 #[derive(Debug)]
 pub struct CefManager {
@@ -323,6 +334,33 @@ impl CefManager {
         Ok(())
     }
 
+    /// Tear down an identity's browser context for good: drop the instance
+    /// with its navigation history and shred the profile directory.
+    pub fn burn_context(&mut self, identity_id: &str) -> Result<ContextBurn, String> {
+        let instance = self.instances.remove(identity_id);
+        let was_live = instance
+            .as_ref()
+            .is_some_and(|i| i.status != BrowserStatus::Destroyed);
+        let history_entries = instance.map_or(0, |i| i.history.len());
+
+        let profile_dir = self.base_data_dir.join("contexts").join(identity_id);
+        let shredded = browser_context::shred_dir(&profile_dir)
+            .map_err(|e| format!("Failed to shred context dir: {}", e))?;
+
+        tracing::info!(
+            "Burned CEF context for identity '{}' ({} files shredded)",
+            identity_id,
+            shredded.files
+        );
+
+        Ok(ContextBurn {
+            was_live,
+            history_entries,
+            files_shredded: shredded.files,
+            bytes_shredded: shredded.bytes,
+        })
+    }
+
     /// Get browser instance for an identity
     pub fn get_instance(&self, identity_id: &str) -> Option<&CefBrowserInstance> {
         self.instances.get(identity_id)
@@ -366,6 +404,24 @@ pub fn init(data_dir: &std::path::Path) -> Result<(), Box<dyn std::error::Error>
     Ok(())
 }
 
+/// Tear down the global CEF manager, destroying every browser context.
+///
+/// Used when the open vault is closed; [`init`] must run again before use.
+pub fn shutdown() -> Result<(), String> {
+    let mut global = CEF_MANAGER
+        .write()
+        .map_err(|e| format!("CEF lock poisoned: {}", e))?;
+
+    if let Some(mut manager) = global.take() {
+        let identity_ids: Vec<String> = manager.instances.keys().cloned().collect();
+        for identity_id in identity_ids {
+            manager.destroy_context(&identity_id)?;
+        }
+        tracing::info!("CEF module shut down");
+    }
+    Ok(())
+}
+
 /// Get the CEF manager (read access)
 pub fn with_manager<F, R>(f: F) -> Result<R, String>
 where
@@ -0,0 +1,45 @@
+//! Audit Commands
+//!
+//! Read, verify and export the hash-chained audit log.
+
+use crate::storage;
+use crate::storage::audit::{self, AuditRecord, AuditVerification};
+use std::path::PathBuf;
+use tracing::info;
+
+/// Result type for audit operations
+pub type AuditResult<T> = Result<T, String>;
+
+/// Get every audit record, oldest first
+pub async fn get_audit_log() -> AuditResult<Vec<AuditRecord>> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    store
+        .audit_log()
+        .map_err(|e| format!("Failed to read audit log: {}", e))
+}
+
+/// Check the audit chain for edits, deletions and truncation
+pub async fn verify_audit_log() -> AuditResult<AuditVerification> {
+    let export = audit::export().map_err(|e| format!("Failed to read audit log: {}", e))?;
+    if !export.verification.valid {
+        tracing::warn!(
+            "Audit log verification failed at record {:?}: {:?}",
+            export.verification.failed_seq,
+            export.verification.failure
+        );
+    }
+    Ok(export.verification)
+}
+
+/// Write the audit log and its verification result to `path` as JSON
+pub async fn export_audit_log(path: String) -> AuditResult<AuditVerification> {
+    info!("Exporting audit log to {}", path);
+
+    let export = audit::export().map_err(|e| format!("Failed to read audit log: {}", e))?;
+    let json = serde_json::to_vec_pretty(&export)
+        .map_err(|e| format!("Failed to serialize audit log: {}", e))?;
+    std::fs::write(PathBuf::from(&path), json)
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    Ok(export.verification)
+}
@@ -0,0 +1,91 @@
+//! Brand Watch List Commands
+//!
+//! Handlers for the open vault's watch list of protected brands. Domains,
+//! emails and URLs imitating a listed brand are tagged `looks-like:<brand>`
+//! as they enter the Hivemind.
+
+use crate::core::idn::{self, BrandWatchList, DomainAnalysis, WATCH_LIST_SETTING};
+use crate::storage::{self, StorageError, Store};
+use tracing::info;
+
+/// Result type for watch list operations
+pub type BrandResult<T> = Result<T, String>;
+
+fn get_list(store: &dyn Store) -> Result<BrandWatchList, StorageError> {
+    match store.get_setting(WATCH_LIST_SETTING)? {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(BrandWatchList::default()),
+    }
+}
+
+/// Normalise, persist and install `list`
+fn set_list(store: &dyn Store, list: &BrandWatchList) -> BrandResult<BrandWatchList> {
+    let list = BrandWatchList {
+        brands: list.normalized()?,
+    };
+    let value = serde_json::to_value(&list).map_err(|e| format!("Serialize error: {}", e))?;
+    store
+        .set_setting(WATCH_LIST_SETTING, &value)
+        .map_err(|e| format!("Failed to save watch list: {}", e))?;
+    idn::install(&list)?;
+    Ok(list)
+}
+
+/// Install the open vault's watch list (called when a vault opens)
+pub fn load() -> BrandResult<()> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let list = get_list(store.as_ref()).map_err(|e| format!("Failed to read watch list: {}", e))?;
+    idn::install(&list)
+}
+
+/// Get the open vault's watch list
+pub async fn get_brand_watch_list() -> BrandResult<BrandWatchList> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    get_list(store.as_ref()).map_err(|e| format!("Failed to read watch list: {}", e))
+}
+
+/// Replace the watch list. Entities already in the Hivemind keep their tags.
+pub async fn save_brand_watch_list(list: BrandWatchList) -> BrandResult<BrandWatchList> {
+    info!("Saving brand watch list ({} brands)", list.brands.len());
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    set_list(store.as_ref(), &list)
+}
+
+/// Watch a brand domain
+pub async fn add_watched_brand(domain: String) -> BrandResult<BrandWatchList> {
+    info!("Watching brand: {}", domain);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut list =
+        get_list(store.as_ref()).map_err(|e| format!("Failed to read watch list: {}", e))?;
+    list.brands.push(domain);
+    set_list(store.as_ref(), &list)
+}
+
+/// Stop watching a brand domain
+pub async fn remove_watched_brand(domain: String) -> BrandResult<BrandWatchList> {
+    info!("Unwatching brand: {}", domain);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let list = get_list(store.as_ref()).map_err(|e| format!("Failed to read watch list: {}", e))?;
+    let removed = BrandWatchList {
+        brands: vec![domain.clone()],
+    }
+    .normalized()?;
+    let brands: Vec<String> = list
+        .normalized()?
+        .into_iter()
+        .filter(|brand| !removed.contains(brand))
+        .collect();
+    if brands.len() == list.brands.len() {
+        return Err(format!("Brand '{}' is not watched", domain));
+    }
+    set_list(store.as_ref(), &BrandWatchList { brands })
+}
+
+/// Unicode form, scripts, confusable characters and brand look-alikes of a
+/// domain, checked against the open vault's watch list
+pub async fn analyze_domain(domain: String) -> BrandResult<DomainAnalysis> {
+    Ok(idn::analyze(&domain))
+}
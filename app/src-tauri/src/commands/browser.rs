@@ -2,6 +2,7 @@
 //!
 //! Core browser functionality for navigation and page interaction.
 
+use crate::storage::{self, CapturedPage};
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
@@ -99,10 +100,25 @@ pub async fn get_page_content(identity_id: Option<String>) -> BrowserResult<Page
     // TODO: Extract real content from the webview once multi-webview support is wired up.
     // Returns a blank page placeholder so callers can distinguish "no content yet"
     // from an actual error.
-    Ok(PageContent {
+    let content = PageContent {
         url: "about:blank".to_string(),
         title: format!("New Tab ({})", id_label),
         html: String::new(),
         text: String::new(),
-    })
+    };
+
+    // Keep the text so it turns up in case-wide search
+    if !content.text.trim().is_empty() {
+        let page = CapturedPage::new(
+            id_label.to_string(),
+            content.url.clone(),
+            content.title.clone(),
+            content.text.clone(),
+        );
+        storage::get_store()
+            .and_then(|store| store.save_page(&page))
+            .map_err(|e| format!("Failed to capture page text: {}", e))?;
+    }
+
+    Ok(content)
 }
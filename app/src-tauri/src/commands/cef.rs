@@ -3,6 +3,7 @@
 //! Command handlers for the embedded Chromium browser.
 
 use crate::cef::{self, CefBrowserInstance, NavigationEntry};
+use crate::core::entity::EntityType;
 use crate::storage;
 use tracing::info;
 
@@ -28,6 +29,42 @@ pub async fn cef_navigate(identity_id: String, url: String) -> CefResult<Navigat
     cef::with_manager_mut(|mgr| mgr.navigate(&identity_id, &url))
 }
 
+/// Open a URL or domain entity in an identity's browser.
+///
+/// Entities that were written defanged are refused unless `confirmed`, so
+/// an indicator pasted from a threat report is only visited when the user
+/// explicitly asks for it.
+pub async fn open_entity(
+    identity_id: String,
+    entity_hash: String,
+    confirmed: bool,
+) -> CefResult<NavigationEntry> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let entity = store
+        .get_entity(&entity_hash)
+        .map_err(|e| format!("Failed to get entity: {}", e))?
+        .ok_or_else(|| format!("Entity '{}' not found", entity_hash))?;
+
+    let url = match entity.entity_type {
+        EntityType::Url => entity.value.clone(),
+        EntityType::Domain => format!("https://{}", entity.value),
+        other => {
+            return Err(format!(
+                "{} entities cannot be opened",
+                other.display_name()
+            ))
+        }
+    };
+    if entity.is_defanged() && !confirmed {
+        return Err(format!(
+            "'{}' was found defanged; confirm before opening it",
+            entity.value
+        ));
+    }
+
+    cef_navigate(identity_id, url).await
+}
+
 /// Go back in CEF browser
 pub async fn cef_go_back(identity_id: String) -> CefResult<Option<String>> {
     info!("CEF go back: identity='{}'", identity_id);
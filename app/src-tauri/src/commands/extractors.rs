@@ -0,0 +1,118 @@
+//! Custom Extractor Commands
+//!
+//! Handlers for the open vault's user-defined extractors. The rule set is
+//! kept in the vault's settings and installed into the extraction pipeline
+//! when the vault opens and on every change.
+
+use crate::core::confidence;
+use crate::core::custom_extractors::{
+    self, CustomExtractor, CustomRuleSet, CUSTOM_EXTRACTORS_SETTING,
+};
+use crate::core::entity_extractor::Extraction;
+use crate::storage::{self, StorageError, Store};
+use tracing::info;
+
+/// Result type for extractor operations
+pub type ExtractorResult<T> = Result<T, String>;
+
+fn get_rules(store: &dyn Store) -> Result<CustomRuleSet, StorageError> {
+    match store.get_setting(CUSTOM_EXTRACTORS_SETTING)? {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(CustomRuleSet::default()),
+    }
+}
+
+/// Validate, persist and install `rules`
+fn set_rules(store: &dyn Store, rules: &CustomRuleSet) -> ExtractorResult<()> {
+    rules.compile()?;
+    let value = serde_json::to_value(rules).map_err(|e| format!("Serialize error: {}", e))?;
+    store
+        .set_setting(CUSTOM_EXTRACTORS_SETTING, &value)
+        .map_err(|e| format!("Failed to save extractors: {}", e))?;
+    custom_extractors::install(rules)
+}
+
+/// Install the open vault's rule set (called when a vault opens)
+pub fn load() -> ExtractorResult<()> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let rules =
+        get_rules(store.as_ref()).map_err(|e| format!("Failed to read extractors: {}", e))?;
+    custom_extractors::install(&rules)
+}
+
+/// Get the open vault's custom extractors
+pub async fn get_custom_extractors() -> ExtractorResult<CustomRuleSet> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    get_rules(store.as_ref()).map_err(|e| format!("Failed to read extractors: {}", e))
+}
+
+/// Add an extractor, or replace the one with the same name. Existing entities
+/// keep the values and hashes the old rule gave them.
+pub async fn save_custom_extractor(extractor: CustomExtractor) -> ExtractorResult<CustomRuleSet> {
+    info!("Saving custom extractor '{}'", extractor.name);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut rules =
+        get_rules(store.as_ref()).map_err(|e| format!("Failed to read extractors: {}", e))?;
+    match rules
+        .extractors
+        .iter_mut()
+        .find(|e| e.name == extractor.name)
+    {
+        Some(existing) => *existing = extractor,
+        None => rules.extractors.push(extractor),
+    }
+    set_rules(store.as_ref(), &rules)?;
+    Ok(rules)
+}
+
+/// Remove an extractor. Entities it already produced stay in the Hivemind
+/// under their existing values and hashes; values entered by hand for its
+/// type afterwards are only lowercased.
+pub async fn delete_custom_extractor(name: String) -> ExtractorResult<CustomRuleSet> {
+    info!("Deleting custom extractor '{}'", name);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut rules =
+        get_rules(store.as_ref()).map_err(|e| format!("Failed to read extractors: {}", e))?;
+    let before = rules.extractors.len();
+    rules.extractors.retain(|e| e.name != name);
+    if rules.extractors.len() == before {
+        return Err(format!("Extractor '{}' not found", name));
+    }
+    set_rules(store.as_ref(), &rules)?;
+    Ok(rules)
+}
+
+/// Run an unsaved extractor over sample text, including matches its
+/// validator would drop
+pub async fn test_custom_extractor(
+    extractor: CustomExtractor,
+    text: String,
+) -> ExtractorResult<Vec<Extraction>> {
+    let compiled = extractor.compile()?;
+    Ok(compiled
+        .find(&text)
+        .into_iter()
+        .map(|span| {
+            let raw = &text[span.start..span.end];
+            let value = compiled.canonicalize(raw);
+            let validation = compiled.validate(raw);
+            Extraction {
+                entity_type: extractor.entity_type(),
+                confidence: confidence::score(
+                    &extractor.entity_type(),
+                    &value,
+                    validation,
+                    &text[..span.start],
+                    false,
+                ),
+                value,
+                raw: raw.to_string(),
+                validation,
+                spans: vec![span],
+                defanged: false,
+            }
+        })
+        .collect())
+}
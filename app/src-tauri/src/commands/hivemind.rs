@@ -3,15 +3,31 @@
 //! The Hivemind is Spin's collective intelligence system.
 //! All discovered entities are shared across all identities in real-time.
 
-use crate::core::entity::{Entity, EntitySource, EntityType};
+use crate::core::document_extractor::{read_document, DocumentFormat, DocumentMetadata};
+use crate::core::entity::{Entity, EntitySource, EntityType, SourcePosition};
+use crate::core::entity_extractor::{
+    context_snippet, extract_with_progress, locate, TextLocator, DEFAULT_CONTEXT_WINDOW,
+};
+use crate::core::html_extractor::{extract_html, StructuredRecord};
+use crate::core::image_metadata::{read_image, to_geojson, ImageMetadata};
+use crate::core::normalize::canonicalize;
+use crate::core::validators::validate;
+use crate::core::{confidence, refang, suppression};
 use crate::hivemind::{CrossReference, HivemindEvent};
-use crate::storage;
+use crate::investigation::{self, GraphEdge, GraphNode};
+use crate::storage::audit::{self, AuditAction};
+use crate::storage::{self, EntityFilter, EntityPage};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use tracing::{debug, info};
 
 /// Result type for hivemind operations
 pub type HivemindResult<T> = Result<T, String>;
 
+/// Upper bound on a requested context window, in characters either side
+const MAX_CONTEXT_WINDOW: usize = 1000;
+
 /// Request to add a new entity
 #[derive(Debug, Deserialize)]
 pub struct AddEntityRequest {
@@ -22,6 +38,49 @@ pub struct AddEntityRequest {
     pub context: Option<String>,
 }
 
+/// Entities found in page markup, with the structured records that group
+/// them
+#[derive(Debug, Serialize)]
+pub struct HtmlEntities {
+    pub entities: Vec<Entity>,
+    pub records: Vec<StructuredRecord>,
+    pub links: Vec<RecordLink>,
+}
+
+/// Entities found in a local file, with what the file says about itself
+#[derive(Debug, Serialize)]
+pub struct FileEntities {
+    pub file_name: String,
+    pub sha256: String,
+    pub format: DocumentFormat,
+    pub size: u64,
+    pub metadata: DocumentMetadata,
+    /// Link targets embedded in the file
+    pub links: Vec<String>,
+    /// The file itself (as a `Sha256Hash`), then everything found in it
+    pub entities: Vec<Entity>,
+}
+
+/// What a photo's metadata gave away
+#[derive(Debug, Serialize)]
+pub struct ImageEntities {
+    pub file_name: String,
+    pub metadata: ImageMetadata,
+    /// The file itself (as a `Sha256Hash`), then its location, capture time
+    /// and camera serial
+    pub entities: Vec<Entity>,
+}
+
+/// An entity's membership of a record
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordLink {
+    /// Index into [`HtmlEntities::records`]
+    pub record: usize,
+    pub entity_hash: String,
+    /// The record field it came from (`email`, `sameAs`, ...)
+    pub field: Option<String>,
+}
+
 /// Get all entities in the Hivemind
 pub async fn get_all_entities() -> HivemindResult<Vec<Entity>> {
     debug!("Fetching all Hivemind entities");
@@ -31,6 +90,29 @@ pub async fn get_all_entities() -> HivemindResult<Vec<Entity>> {
         .map_err(|e| format!("Failed to get entities: {}", e))
 }
 
+/// Page through entities matching a type, tag or identity filter
+pub async fn query_entities(
+    filter: EntityFilter,
+    cursor: Option<String>,
+    limit: usize,
+) -> HivemindResult<EntityPage> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    store
+        .query_entities(&filter, cursor.as_deref(), limit)
+        .map_err(|e| format!("Failed to query entities: {}", e))
+}
+
+/// Page through entities, most recently seen first
+pub async fn get_recent_entities(
+    cursor: Option<String>,
+    limit: usize,
+) -> HivemindResult<EntityPage> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    store
+        .entities_by_last_seen(None, cursor.as_deref(), limit)
+        .map_err(|e| format!("Failed to query entities: {}", e))
+}
+
 /// Add a new entity to the Hivemind
 pub async fn add_entity(request: AddEntityRequest) -> HivemindResult<Entity> {
     info!(
@@ -38,24 +120,44 @@ pub async fn add_entity(request: AddEntityRequest) -> HivemindResult<Entity> {
         request.entity_type, request.value
     );
 
+    let canonical = canonicalize(&request.entity_type, &request.value);
+    if suppression::is_suppressed(&request.entity_type, &canonical) {
+        return Err(format!("'{}' is on the suppression list", request.value));
+    }
+
     let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
 
     let source = EntitySource {
         identity_id: request.source_identity,
         url: request.source_url,
         context: request.context,
+        position: None,
+        defanged: refang::applies_to(&request.entity_type) && refang::is_defanged(&request.value),
+        confidence: None,
+        file_sha256: None,
         timestamp: chrono::Utc::now(),
     };
 
-    let entity_hash = Entity::compute_hash(&request.entity_type, &request.value);
+    // Hash the canonical form, so formatting variants find the same entity
+    let entity_hash = Entity::compute_hash(&request.entity_type, &canonical);
+    let actor = source.identity_id.clone();
 
     let entity = if let Some(mut existing) = store.get_entity(&entity_hash).ok().flatten() {
+        existing.add_alias(&request.value);
         existing.sources.push(source);
         existing.last_seen = chrono::Utc::now();
         existing.occurrence_count += 1;
         store
             .save_entity(&existing)
             .map_err(|e| format!("Failed to update entity: {}", e))?;
+        audit::record_as(
+            &actor,
+            AuditAction::EntitySighted {
+                entity_hash: existing.hash.clone(),
+                source_count: existing.sources.len(),
+            },
+        )
+        .map_err(|e| format!("Audit error: {}", e))?;
 
         if existing.sources.len() > 1 {
             crate::hivemind::broadcast(HivemindEvent::CrossReference {
@@ -70,6 +172,14 @@ pub async fn add_entity(request: AddEntityRequest) -> HivemindResult<Entity> {
         store
             .save_entity(&entity)
             .map_err(|e| format!("Failed to save entity: {}", e))?;
+        audit::record_as(
+            &actor,
+            AuditAction::EntityAdded {
+                entity_hash: entity.hash.clone(),
+                entity_type: entity.entity_type.clone(),
+            },
+        )
+        .map_err(|e| format!("Audit error: {}", e))?;
 
         crate::hivemind::broadcast(HivemindEvent::NewEntity {
             entity_hash: entity.hash.clone(),
@@ -95,17 +205,22 @@ pub async fn get_entity_sources(entity_hash: String) -> HivemindResult<Vec<Entit
 /// Get cross-references (entities found by multiple identities)
 pub async fn get_cross_references() -> HivemindResult<Vec<CrossReference>> {
     let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
-    let entities = store
-        .get_all_entities()
-        .map_err(|e| format!("Failed to get entities: {}", e))?;
+    let hashes = store
+        .cross_reference_hashes()
+        .map_err(|e| format!("Failed to read identity index: {}", e))?;
+
+    let mut entities = Vec::with_capacity(hashes.len());
+    for hash in hashes {
+        if let Some(entity) = store
+            .get_entity(&hash)
+            .map_err(|e| format!("Failed to get entity: {}", e))?
+        {
+            entities.push(entity);
+        }
+    }
 
     let cross_refs: Vec<CrossReference> = entities
         .into_iter()
-        .filter(|e| {
-            let unique: std::collections::HashSet<_> =
-                e.sources.iter().map(|s| &s.identity_id).collect();
-            unique.len() > 1
-        })
         .map(|e| {
             let identity_ids: Vec<String> = e
                 .sources
@@ -130,30 +245,482 @@ pub async fn get_cross_references() -> HivemindResult<Vec<CrossReference>> {
     Ok(cross_refs)
 }
 
-/// Extract entities from text
+/// Extract entities from text of any size, broadcasting progress as
+/// [`HivemindEvent::ExtractionProgress`]
+///
+/// Each source keeps `context_window` characters (default
+/// [`DEFAULT_CONTEXT_WINDOW`]) either side of the first occurrence. When the
+/// text was serialised from a page, `locators` map its ranges back to DOM
+/// elements so the source can also record an XPath.
 pub async fn extract_entities_from_text(
     text: String,
     source_identity: String,
     source_url: Option<String>,
+    context_window: Option<usize>,
+    locators: Option<Vec<TextLocator>>,
 ) -> HivemindResult<Vec<Entity>> {
     info!("Extracting entities from text ({} chars)", text.len());
-    let extracted = crate::core::entity_extractor::extract_all(&text);
+    let window = context_window
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+        .min(MAX_CONTEXT_WINDOW);
+    Ok(text_entities(
+        &text,
+        &source_identity,
+        source_url.as_deref(),
+        None,
+        window,
+        &locators.unwrap_or_default(),
+    ))
+}
+
+/// Run the extractor over `text` and turn what the suppression list lets
+/// through into entities
+fn text_entities(
+    text: &str,
+    source_identity: &str,
+    source_url: Option<&str>,
+    file_sha256: Option<&str>,
+    window: usize,
+    locators: &[TextLocator],
+) -> Vec<Entity> {
+    let extracted = extract_with_progress(text, |progress| {
+        crate::hivemind::broadcast(HivemindEvent::ExtractionProgress {
+            identity_id: source_identity.to_string(),
+            source_url: source_url.map(str::to_string),
+            progress: progress.clone(),
+        })
+    });
     info!("Extracted {} potential entities", extracted.len());
-    Ok(extracted
+    let found = extracted.len();
+    let extracted: Vec<_> = extracted
+        .into_iter()
+        .filter(|e| suppression::allows(&e.entity_type, &e.value, e.confidence))
+        .collect();
+    if extracted.len() < found {
+        debug!(
+            "Suppressed {} extractions (suppression list or low confidence)",
+            found - extracted.len()
+        );
+    }
+
+    extracted
         .into_iter()
-        .map(|(entity_type, value)| {
+        .map(|extraction| {
+            let (context, position) = match extraction.spans.first() {
+                Some(&span) => {
+                    let (snippet, highlight) = context_snippet(text, span, window);
+                    let position = SourcePosition {
+                        span,
+                        highlight,
+                        locator: locate(locators, span),
+                    };
+                    (Some(snippet), Some(position))
+                }
+                None => (None, None),
+            };
             Entity::new(
-                entity_type,
-                value,
+                extraction.entity_type,
+                extraction.raw,
                 EntitySource {
-                    identity_id: source_identity.clone(),
-                    url: source_url.clone(),
-                    context: None,
+                    identity_id: source_identity.to_string(),
+                    url: source_url.map(str::to_string),
+                    context,
+                    position,
+                    defanged: extraction.defanged,
+                    confidence: Some(extraction.confidence),
+                    file_sha256: file_sha256.map(str::to_string),
                     timestamp: chrono::Utc::now(),
                 },
             )
         })
-        .collect())
+        .collect()
+}
+
+/// Extract entities from a local file: PDF, Office Open XML, email, CSV or
+/// plain text. The extractor runs over the document's metadata, text and
+/// embedded links, and every source records the file's SHA-256.
+///
+/// The file itself comes back first, as a `Sha256Hash` entity. With
+/// `investigation_id`, it is added to that investigation's graph linked to
+/// everything found in it.
+pub async fn extract_entities_from_file(
+    path: String,
+    source_identity: String,
+    context_window: Option<usize>,
+    investigation_id: Option<String>,
+) -> HivemindResult<FileEntities> {
+    info!("Extracting entities from file {}", path);
+    let path = std::path::PathBuf::from(path);
+    let document = read_document(&path)?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    info!(
+        "Read {:?} document {} ({} bytes, {} chars of text)",
+        document.format,
+        document.sha256,
+        document.size,
+        document.text.len()
+    );
+
+    let window = context_window
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+        .min(MAX_CONTEXT_WINDOW);
+    let mut entities = vec![file_entity(&document.sha256, &file_name, &source_identity)];
+    for entity in text_entities(
+        &document.extraction_text(),
+        &source_identity,
+        None,
+        Some(&document.sha256),
+        window,
+        &[],
+    ) {
+        // A document quoting its own hash is not a second entity
+        if entity.hash != entities[0].hash {
+            entities.push(entity);
+        }
+    }
+
+    if let Some(investigation_id) = investigation_id {
+        link_file(&investigation_id, &entities, &source_identity)?;
+    }
+    Ok(FileEntities {
+        file_name,
+        sha256: document.sha256,
+        format: document.format,
+        size: document.size,
+        metadata: document.metadata,
+        links: document.links,
+        entities,
+    })
+}
+
+/// Extract a photo's GPS position, capture time and camera serial number
+/// from its EXIF and XMP metadata, as `Coordinate`, `Date` and
+/// `CameraSerial` entities whose sources record the file's SHA-256.
+///
+/// As with [`extract_entities_from_file`], the file itself comes back first,
+/// and `investigation_id` links it to what was found.
+pub async fn extract_entities_from_image(
+    path: String,
+    source_identity: String,
+    investigation_id: Option<String>,
+) -> HivemindResult<ImageEntities> {
+    info!("Extracting image metadata from {}", path);
+    let path = std::path::PathBuf::from(path);
+    let metadata = read_image(&path)?;
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut entities = vec![file_entity(&metadata.sha256, &file_name, &source_identity)];
+    for found in metadata.entities() {
+        let value = canonicalize(&found.entity_type, &found.value);
+        let confidence = confidence::score(
+            &found.entity_type,
+            &value,
+            validate(&found.entity_type, &found.value),
+            "",
+            false,
+        );
+        if !suppression::allows(&found.entity_type, &value, confidence) {
+            debug!(
+                "Suppressed {:?} {} from image metadata",
+                found.entity_type, value
+            );
+            continue;
+        }
+        entities.push(Entity::new(
+            found.entity_type,
+            found.value,
+            EntitySource {
+                identity_id: source_identity.clone(),
+                url: None,
+                context: Some(format!("{} of {}", found.origin, file_name)),
+                position: None,
+                defanged: false,
+                confidence: Some(confidence),
+                file_sha256: Some(metadata.sha256.clone()),
+                timestamp: chrono::Utc::now(),
+            },
+        ));
+    }
+    info!(
+        "Found {} entities in {:?} image metadata",
+        entities.len() - 1,
+        metadata.format
+    );
+
+    if let Some(investigation_id) = investigation_id {
+        link_file(&investigation_id, &entities, &source_identity)?;
+    }
+    Ok(ImageEntities {
+        file_name,
+        metadata,
+        entities,
+    })
+}
+
+/// Write the GPS positions of photos to `output` as a GeoJSON
+/// `FeatureCollection`. Returns how many of the photos had one; unreadable
+/// files are skipped.
+pub async fn export_images_geojson(paths: Vec<String>, output: String) -> HivemindResult<usize> {
+    info!("Exporting GeoJSON for {} images to {}", paths.len(), output);
+
+    let mut images = Vec::new();
+    for path in paths {
+        let path = std::path::PathBuf::from(path);
+        match read_image(&path) {
+            Ok(metadata) => {
+                let file_name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                images.push((file_name, metadata));
+            }
+            Err(e) => tracing::warn!("Skipping {:?}: {}", path, e),
+        }
+    }
+    let geojson = to_geojson(&images);
+    let count = geojson["features"].as_array().map_or(0, Vec::len);
+    let json = serde_json::to_vec_pretty(&geojson)
+        .map_err(|e| format!("Failed to serialize GeoJSON: {}", e))?;
+    std::fs::write(&output, json).map_err(|e| format!("Failed to write {}: {}", output, e))?;
+    Ok(count)
+}
+
+/// An ingested file as a `Sha256Hash` entity, named in its context
+fn file_entity(sha256: &str, file_name: &str, identity_id: &str) -> Entity {
+    Entity::new(
+        EntityType::Sha256Hash,
+        sha256.to_string(),
+        EntitySource {
+            identity_id: identity_id.to_string(),
+            url: None,
+            context: Some(file_name.to_string()),
+            position: None,
+            defanged: false,
+            confidence: None,
+            file_sha256: Some(sha256.to_string()),
+            timestamp: chrono::Utc::now(),
+        },
+    )
+}
+
+/// Add a file (the first entity) and what was found in it to an
+/// investigation graph
+fn link_file(investigation_id: &str, entities: &[Entity], identity_id: &str) -> HivemindResult<()> {
+    let Some((file, found)) = entities.split_first() else {
+        return Ok(());
+    };
+    let node = |entity: &Entity| GraphNode {
+        id: entity.hash.clone(),
+        node_type: "entity".to_string(),
+        label: entity.value.clone(),
+        value: entity.value.clone(),
+        entity_type: Some(entity.entity_type.display_name()),
+        color: None,
+        metadata: None,
+    };
+
+    investigation::with_investigations_mut(|store| {
+        let inv = store
+            .get_mut(investigation_id)
+            .ok_or_else(|| format!("Investigation '{}' not found", investigation_id))?;
+        inv.add_node(node(file));
+        for entity in found {
+            inv.add_node(node(entity));
+            inv.add_edge(GraphEdge {
+                id: format!("edge-{}", uuid::Uuid::new_v4()),
+                source: file.hash.clone(),
+                target: entity.hash.clone(),
+                relationship: "contains".to_string(),
+                label: "contains".to_string(),
+                weight: 1.0,
+                discovered_by: identity_id.to_string(),
+                context: entity.sources.first().and_then(|s| s.context.clone()),
+            });
+        }
+        Ok(())
+    })
+}
+
+/// Extract entities from a page's HTML (`PageContent.html`): link targets,
+/// `<meta>` tags and JSON-LD objects that text extraction cannot see.
+///
+/// With `investigation_id`, each structured record is added to that
+/// investigation's graph as a node linked to its entities (and to the record
+/// it was nested in), so related selectors arrive already connected.
+pub async fn extract_entities_from_html(
+    html: String,
+    source_identity: String,
+    source_url: Option<String>,
+    investigation_id: Option<String>,
+) -> HivemindResult<HtmlEntities> {
+    info!("Extracting entities from HTML ({} bytes)", html.len());
+    let extraction = extract_html(&html, source_url.as_deref());
+    info!(
+        "Extracted {} entities in {} records from markup",
+        extraction.entities.len(),
+        extraction.records.len()
+    );
+
+    let mut entities: Vec<Entity> = Vec::new();
+    let mut index_by_hash: HashMap<String, usize> = HashMap::new();
+    let mut links = Vec::new();
+    for found in extraction.entities {
+        // Markup states what each value is, so only validation and noise
+        // count towards confidence
+        let confidence = confidence::score(
+            &found.entity_type,
+            &found.value,
+            found.validation,
+            "",
+            false,
+        );
+        if !suppression::allows(&found.entity_type, &found.value, confidence) {
+            debug!(
+                "Suppressed {:?} {} from markup",
+                found.entity_type, found.value
+            );
+            continue;
+        }
+        let entity = Entity::new(
+            found.entity_type,
+            found.raw,
+            EntitySource {
+                identity_id: source_identity.clone(),
+                url: source_url.clone(),
+                context: Some(found.origin),
+                position: None,
+                defanged: false,
+                confidence: Some(confidence),
+                file_sha256: None,
+                timestamp: chrono::Utc::now(),
+            },
+        );
+        if let Some(record) = found.record {
+            links.push(RecordLink {
+                record,
+                entity_hash: entity.hash.clone(),
+                field: found.field,
+            });
+        }
+        if !index_by_hash.contains_key(&entity.hash) {
+            index_by_hash.insert(entity.hash.clone(), entities.len());
+            entities.push(entity);
+        }
+    }
+
+    let result = HtmlEntities {
+        entities,
+        records: extraction.records,
+        links,
+    };
+    if let Some(investigation_id) = investigation_id {
+        link_records(
+            &investigation_id,
+            &result,
+            &source_identity,
+            source_url.as_deref(),
+        )?;
+    }
+    Ok(result)
+}
+
+/// Add records and their entities to an investigation graph. Nodes and edges
+/// that already exist are left alone, so re-extracting a page is harmless.
+fn link_records(
+    investigation_id: &str,
+    found: &HtmlEntities,
+    identity_id: &str,
+    source_url: Option<&str>,
+) -> HivemindResult<()> {
+    let record_ids: Vec<String> = found
+        .records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let key = format!(
+                "{}|{}|{}|{}",
+                source_url.unwrap_or_default(),
+                index,
+                record.kind,
+                record.name.as_deref().unwrap_or_default()
+            );
+            let digest = Sha256::digest(key.as_bytes());
+            format!(
+                "record-{}",
+                base64::Engine::encode(
+                    &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+                    &digest[..12]
+                )
+            )
+        })
+        .collect();
+    let edge =
+        |source: &str, target: &str, relationship: &str, context: Option<String>| GraphEdge {
+            id: format!("edge-{}", uuid::Uuid::new_v4()),
+            source: source.to_string(),
+            target: target.to_string(),
+            relationship: relationship.to_string(),
+            label: relationship.to_string(),
+            weight: 1.0,
+            discovered_by: identity_id.to_string(),
+            context,
+        };
+
+    investigation::with_investigations_mut(|store| {
+        let inv = store
+            .get_mut(investigation_id)
+            .ok_or_else(|| format!("Investigation '{}' not found", investigation_id))?;
+
+        for (record, id) in found.records.iter().zip(&record_ids) {
+            inv.add_node(GraphNode {
+                id: id.clone(),
+                node_type: record.kind.to_lowercase(),
+                label: record.name.clone().unwrap_or_else(|| record.kind.clone()),
+                value: record.name.clone().unwrap_or_default(),
+                entity_type: None,
+                color: None,
+                metadata: None,
+            });
+        }
+        for (index, record) in found.records.iter().enumerate() {
+            if let Some(parent) = record.parent {
+                inv.add_edge(edge(
+                    &record_ids[index],
+                    &record_ids[parent],
+                    "affiliated_with",
+                    Some(record.origin.clone()),
+                ));
+            }
+        }
+
+        for link in &found.links {
+            let Some(entity) = found.entities.iter().find(|e| e.hash == link.entity_hash) else {
+                continue;
+            };
+            inv.add_node(GraphNode {
+                id: entity.hash.clone(),
+                node_type: "entity".to_string(),
+                label: entity.value.clone(),
+                value: entity.value.clone(),
+                entity_type: Some(entity.entity_type.display_name()),
+                color: None,
+                metadata: None,
+            });
+            inv.add_edge(edge(
+                &record_ids[link.record],
+                &entity.hash,
+                link.field.as_deref().unwrap_or("has"),
+                source_url.map(str::to_string),
+            ));
+        }
+        Ok(())
+    })
 }
 
 /// Clear all entities (with confirmation)
@@ -167,5 +734,7 @@ pub async fn clear_entities(confirm: bool) -> HivemindResult<()> {
     let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
     store
         .clear_entities()
-        .map_err(|e| format!("Failed to clear entities: {}", e))
+        .map_err(|e| format!("Failed to clear entities: {}", e))?;
+    audit::record(AuditAction::EntitiesCleared).map_err(|e| format!("Audit error: {}", e))?;
+    Ok(())
 }
@@ -3,8 +3,10 @@
 //! Spin can spawn multiple identities (dupes),
 //! each with unique fingerprints and isolated sessions.
 
+use crate::burn::{self, BurnOptions, DestructionReceipt};
 use crate::core::identity::{Identity, IdentityStatus, ProxyConfig};
 use crate::storage;
+use crate::storage::audit::{self, AuditAction};
 use serde::Deserialize;
 use tracing::info;
 use uuid::Uuid;
@@ -52,6 +54,11 @@ pub async fn create_identity(request: CreateIdentityRequest) -> IdentityResult<I
     store
         .save_identity(&identity)
         .map_err(|e| format!("Failed to save identity: {}", e))?;
+    audit::record(AuditAction::IdentityCreated {
+        identity_id: identity.id.clone(),
+        name: identity.name.clone(),
+    })
+    .map_err(|e| format!("Audit error: {}", e))?;
 
     info!(
         "Created identity {} with fingerprint {}",
@@ -72,11 +79,25 @@ pub async fn delete_identity(identity_id: String) -> IdentityResult<()> {
     store
         .delete_identity(&identity_id)
         .map_err(|e| format!("Failed to delete identity: {}", e))?;
+    audit::record(AuditAction::IdentityDestroyed {
+        identity_id: identity_id.clone(),
+    })
+    .map_err(|e| format!("Audit error: {}", e))?;
 
     info!("Identity {} deleted and absorbed", identity_id);
     Ok(())
 }
 
+/// Burn an identity: tear down its browser, shred its profile and scrub it
+/// from sessions, pages, entities and cases
+pub async fn burn_identity(
+    identity_id: String,
+    options: BurnOptions,
+) -> IdentityResult<DestructionReceipt> {
+    info!("Burning identity: {}", identity_id);
+    burn::burn_identity(&identity_id, &options)
+}
+
 /// Switch to a different identity
 pub async fn switch_identity(identity_id: String) -> IdentityResult<Identity> {
     info!("Switching to identity: {}", identity_id);
@@ -91,6 +112,7 @@ pub async fn switch_identity(identity_id: String) -> IdentityResult<Identity> {
     store
         .set_active_identity(&identity_id)
         .map_err(|e| format!("Failed to switch identity: {}", e))?;
+    crate::commands::privacy::load_identity(&identity_id)?;
 
     info!(
         "Now operating as: {} ({})",
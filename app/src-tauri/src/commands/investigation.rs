@@ -3,10 +3,14 @@
 //! IPC handlers for investigation management, timeline tracking,
 //! and entity relationship graph operations.
 
+use crate::core::dates::parse_date;
+use crate::core::entity::EntityType;
 use crate::investigation::{
     self, GraphEdge, GraphNode, InvestigationExport, InvestigationGraph, InvestigationSummary,
-    TimelineEvent,
+    TimelineEvent, TimelineEventType,
 };
+use crate::storage;
+use crate::storage::audit::{self, AuditAction};
 use chrono::Utc;
 use std::collections::HashMap;
 use tracing::info;
@@ -80,19 +84,80 @@ pub async fn add_timeline_event(
         entity_hash,
         importance: importance.unwrap_or(1).min(5),
         metadata,
+        occurred_at: None,
         created_at: Utc::now(),
     };
 
+    insert_event(event)
+}
+
+/// Drop a Date entity onto an investigation's timeline.
+///
+/// The event is placed at the entity's normalised date (with its precision),
+/// independent of when it was added.
+pub async fn add_date_event(
+    investigation_id: String,
+    entity_hash: String,
+    identity_id: String,
+    title: Option<String>,
+    description: Option<String>,
+    importance: Option<u8>,
+) -> InvestigationResult<TimelineEvent> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let entity = store
+        .get_entity(&entity_hash)
+        .map_err(|e| format!("Storage error: {}", e))?
+        .ok_or_else(|| format!("Entity {} not found", entity_hash))?;
+    if entity.entity_type != EntityType::Date {
+        return Err(format!("Entity {} is not a date", entity_hash));
+    }
+    let occurred_at = parse_date(&entity.value)
+        .ok_or_else(|| format!("Cannot parse '{}' as a date", entity.value))?;
+
+    info!(
+        "Adding date event '{}' to investigation '{}'",
+        occurred_at.value, investigation_id
+    );
+
+    let event = TimelineEvent {
+        id: format!("evt-{}", uuid::Uuid::new_v4()),
+        investigation_id,
+        event_type: TimelineEventType::DateReference,
+        title: title.unwrap_or_else(|| occurred_at.value.clone()),
+        description: description.unwrap_or_default(),
+        identity_id,
+        url: entity.sources.iter().rev().find_map(|s| s.url.clone()),
+        entity_hash: Some(entity_hash),
+        importance: importance.unwrap_or(1).min(5),
+        metadata: None,
+        occurred_at: Some(occurred_at),
+        created_at: Utc::now(),
+    };
+
+    insert_event(event)
+}
+
+/// Append an event to its investigation and audit it
+fn insert_event(event: TimelineEvent) -> InvestigationResult<TimelineEvent> {
     let event_clone = event.clone();
 
     investigation::with_investigations_mut(|store| {
         let inv = store
-            .get_mut(&investigation_id)
-            .ok_or_else(|| format!("Investigation '{}' not found", investigation_id))?;
+            .get_mut(&event.investigation_id)
+            .ok_or_else(|| format!("Investigation '{}' not found", event.investigation_id))?;
         inv.add_event(event_clone);
         Ok(())
     })?;
 
+    audit::record_as(
+        &event.identity_id,
+        AuditAction::InvestigationEventAdded {
+            investigation_id: event.investigation_id.clone(),
+            event_id: event.id.clone(),
+        },
+    )
+    .map_err(|e| format!("Audit error: {}", e))?;
+
     Ok(event)
 }
 
@@ -204,6 +269,18 @@ pub async fn get_investigation_timeline(
     })
 }
 
+/// Get an investigation's dated events in real-world order
+pub async fn get_investigation_chronology(
+    investigation_id: String,
+) -> InvestigationResult<Vec<TimelineEvent>> {
+    investigation::with_investigations(|store| {
+        let inv = store
+            .get(&investigation_id)
+            .ok_or_else(|| format!("Investigation '{}' not found", investigation_id))?;
+        Ok(inv.chronology().into_iter().cloned().collect())
+    })
+}
+
 /// Update investigation status
 pub async fn update_investigation_status(
     investigation_id: String,
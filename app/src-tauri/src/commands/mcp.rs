@@ -4,6 +4,7 @@
 //! The MCP server provides Claude-powered sub-agents with specialized skills.
 
 use crate::mcp::{self, Agent, AgentSkill, AgentInvocation, AgentResponse};
+use crate::storage::audit::{self, AuditAction};
 use serde::{Deserialize, Serialize};
 use tracing::{info, debug};
 
@@ -504,8 +505,14 @@ pub async fn set_claude_api_key(api_key: String) -> McpResult<bool> {
 
     mcp::with_claude_client(|client| {
         client.set_api_key(api_key);
-        Ok(true)
+        Ok(())
+    })?;
+
+    audit::record(AuditAction::ApiKeyChanged {
+        provider: "claude".to_string(),
     })
+    .map_err(|e| format!("Audit error: {}", e))?;
+    Ok(true)
 }
 
 /// Get Claude API connection status
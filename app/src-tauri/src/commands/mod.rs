@@ -4,12 +4,20 @@
 //! Previously exposed over Tauri IPC to the React frontend.
 //! Spin v12 - Jessica Jones
 
+pub mod audit;
+pub mod brands;
 pub mod browser;
 pub mod cef;
+pub mod extractors;
 pub mod hivemind;
 pub mod identity;
 pub mod investigation;
 pub mod mcp;
 pub mod osint;
 pub mod privacy;
+pub mod retention;
+pub mod search;
 pub mod session;
+pub mod storage;
+pub mod suppression;
+pub mod vault;
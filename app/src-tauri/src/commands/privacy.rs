@@ -1,94 +1,205 @@
 //! Privacy Commands
 //!
 //! IPC handlers for the Dynamic Privacy Engine.
+//!
+//! Settings, daily statistics and site assessments are kept per identity in
+//! the open vault's `config` tree and follow the active identity.
+//!
+//! Settings changes are written through at once. Counters bumped on every
+//! blocked request stay in memory and are written back at most every
+//! [`COUNTER_FLUSH_INTERVAL`], on identity switch and when the vault closes.
 
 use crate::core::privacy_engine::{
-    assess_domain_risk, OpsecLevel, PrivacySettings, PrivacyStats, RiskAssessment,
+    assess_domain_risk, DailyPrivacyStats, OpsecLevel, PrivacyProfile, PrivacySettings,
+    PrivacyStats, RiskAssessment,
 };
-use lazy_static::lazy_static;
-use serde::{Deserialize, Serialize};
+use crate::storage::{self, Store};
+use chrono::{NaiveDate, Utc};
 use std::sync::RwLock;
+use std::time::{Duration, Instant};
 use tracing::{info, debug};
 
-/// Privacy state container
+/// Longest time counter changes stay unsaved while they keep coming
+const COUNTER_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Privacy profile of the identity it was loaded for
 struct PrivacyState {
-    settings: PrivacySettings,
-    stats: PrivacyStats,
-    site_assessments: std::collections::HashMap<String, RiskAssessment>,
-}
-
-impl PrivacyState {
-    fn new() -> Self {
-        Self {
-            settings: PrivacySettings {
-                opsec_level: OpsecLevel::Standard,
-                auto_adjust: true,
-                block_trackers: true,
-                block_fingerprinting: false,
-                spoof_canvas: false,
-                spoof_webgl: false,
-                spoof_audio: false,
-                block_webrtc: true,
-                spoof_timezone: false,
-                spoof_screen: false,
-                spoof_user_agent: false,
-                spoof_fonts: false,
-                block_third_party_cookies: true,
-                clear_cookies_on_close: false,
-                use_tor: false,
-                dns_over_https: true,
-                block_javascript: false,
-            },
-            stats: PrivacyStats {
-                trackers_blocked: 0,
-                fingerprint_attempts_blocked: 0,
-                cookies_blocked: 0,
-                webrtc_leaks_prevented: 0,
-                dns_queries_protected: 0,
-                scripts_blocked: 0,
-                sites_assessed: 0,
-                high_risk_sites_visited: 0,
-                auto_escalations: 0,
-            },
-            site_assessments: std::collections::HashMap::new(),
-        }
-    }
+    identity_id: String,
+    profile: PrivacyProfile,
+    /// When the profile first changed without being saved
+    unsaved_since: Option<Instant>,
 }
 
-/// Global privacy state
-lazy_static! {
-    static ref PRIVACY_STATE: RwLock<PrivacyState> = RwLock::new(PrivacyState::new());
-}
+/// Cached profile of the active identity
+static PRIVACY_STATE: RwLock<Option<PrivacyState>> = RwLock::new(None);
 
 /// Result type for privacy operations
 pub type PrivacyResult<T> = Result<T, String>;
 
+/// Read an identity's profile from the `config` tree, or the defaults
+fn load_profile(store: &dyn Store, identity_id: &str) -> PrivacyResult<PrivacyProfile> {
+    let value = store
+        .get_setting(&PrivacyProfile::setting_key(identity_id))
+        .map_err(|e| format!("Storage error: {}", e))?;
+    match value {
+        Some(value) => serde_json::from_value(value)
+            .map_err(|e| format!("Corrupt privacy profile for {}: {}", identity_id, e)),
+        None => Ok(PrivacyProfile::default()),
+    }
+}
+
+/// Write an identity's profile to the `config` tree
+fn save_state(store: &dyn Store, state: &mut PrivacyState) -> PrivacyResult<()> {
+    let value = serde_json::to_value(&state.profile)
+        .map_err(|e| format!("Failed to encode privacy profile: {}", e))?;
+    store
+        .set_setting(&PrivacyProfile::setting_key(&state.identity_id), &value)
+        .map_err(|e| format!("Failed to save privacy profile: {}", e))?;
+    state.unsaved_since = None;
+    Ok(())
+}
+
+/// Write back the cached profile if it has unsaved changes
+fn flush(store: &dyn Store, state: &mut Option<PrivacyState>) -> PrivacyResult<()> {
+    match state.as_mut() {
+        Some(cached) if cached.unsaved_since.is_some() => save_state(store, cached),
+        _ => Ok(()),
+    }
+}
+
+/// Load an identity's profile into the cache, e.g. after a switch
+pub fn load_identity(identity_id: &str) -> PrivacyResult<PrivacySettings> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let profile = load_profile(store.as_ref(), identity_id)?;
+    let settings = profile.settings.clone();
+
+    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
+    flush(store.as_ref(), &mut state)?;
+    *state = Some(PrivacyState {
+        identity_id: identity_id.to_string(),
+        profile,
+        unsaved_since: None,
+    });
+
+    info!(
+        "Loaded privacy profile for {} (OPSEC level: {:?})",
+        identity_id, settings.opsec_level
+    );
+    Ok(settings)
+}
+
+/// Save and drop the cached profile, e.g. when the open vault is closed
+pub fn unload() -> PrivacyResult<()> {
+    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
+    if let Ok(store) = storage::get_store() {
+        flush(store.as_ref(), &mut state)?;
+    }
+    *state = None;
+    Ok(())
+}
+
+/// Drop an identity's cached profile without saving it, e.g. when it is
+/// burned
+pub fn forget(identity_id: &str) -> PrivacyResult<()> {
+    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
+    if state.as_ref().is_some_and(|cached| cached.identity_id == identity_id) {
+        *state = None;
+    }
+    Ok(())
+}
+
+/// Read the active identity's profile, loading it if the cache is stale
+fn with_profile<F, R>(f: F) -> PrivacyResult<R>
+where
+    F: FnOnce(&PrivacyProfile) -> R,
+{
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
+    let active = cached_state(&mut state, store.as_ref())?;
+    Ok(f(&active.profile))
+}
+
+/// Change the active identity's profile and write it back to the store
+fn with_profile_mut<F, R>(f: F) -> PrivacyResult<R>
+where
+    F: FnOnce(&mut PrivacyProfile) -> R,
+{
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
+    let active = cached_state(&mut state, store.as_ref())?;
+    let result = f(&mut active.profile);
+    save_state(store.as_ref(), active)?;
+    Ok(result)
+}
+
+/// Change the active identity's counters in memory, saving the profile once
+/// changes have been pending for [`COUNTER_FLUSH_INTERVAL`]
+fn with_counters<F, R>(f: F) -> PrivacyResult<R>
+where
+    F: FnOnce(&mut PrivacyProfile) -> R,
+{
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
+    let active = cached_state(&mut state, store.as_ref())?;
+    let result = f(&mut active.profile);
+    let unsaved_since = *active.unsaved_since.get_or_insert_with(Instant::now);
+    if unsaved_since.elapsed() >= COUNTER_FLUSH_INTERVAL {
+        save_state(store.as_ref(), active)?;
+    }
+    Ok(result)
+}
+
+/// The cached state, reloaded if it belongs to another identity
+fn cached_state<'a>(
+    state: &'a mut Option<PrivacyState>,
+    store: &dyn Store,
+) -> PrivacyResult<&'a mut PrivacyState> {
+    let identity_id = store
+        .get_active_identity()
+        .map_err(|e| format!("Storage error: {}", e))?
+        .id;
+    if state.as_ref().is_none_or(|cached| cached.identity_id != identity_id) {
+        flush(store, state)?;
+        let profile = load_profile(store, &identity_id)?;
+        return Ok(state.insert(PrivacyState {
+            identity_id,
+            profile,
+            unsaved_since: None,
+        }));
+    }
+    state
+        .as_mut()
+        .ok_or_else(|| "Privacy state not loaded".to_string())
+}
+
+/// Today's counters in the active identity's profile
+fn today(profile: &mut PrivacyProfile) -> &mut PrivacyStats {
+    profile.stats_for(Utc::now().date_naive())
+}
+
 /// Get current privacy settings
 pub async fn get_privacy_settings() -> PrivacyResult<PrivacySettings> {
-    let state = PRIVACY_STATE.read().map_err(|e| format!("Lock poisoned: {}", e))?;
-    Ok(state.settings.clone())
+    with_profile(|profile| profile.settings.clone())
 }
 
 /// Update privacy settings
 pub async fn set_privacy_settings(settings: PrivacySettings) -> PrivacyResult<()> {
     info!("Updating privacy settings to OPSEC level: {:?}", settings.opsec_level);
-    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-    state.settings = settings;
-    Ok(())
+    with_profile_mut(|profile| profile.settings = settings)
 }
 
 /// Set OPSEC level (quick toggle)
 pub async fn set_opsec_level(level: OpsecLevel) -> PrivacyResult<PrivacySettings> {
     info!("Setting OPSEC level to: {:?}", level);
-    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-    state.settings = PrivacySettings::for_level(level);
-    Ok(state.settings.clone())
+    with_profile_mut(|profile| {
+        profile.settings = PrivacySettings::for_level(level);
+        profile.settings.clone()
+    })
 }
 
 /// Get current OPSEC level
 pub async fn get_opsec_level() -> PrivacyResult<OpsecLevel> {
-    let state = PRIVACY_STATE.read().map_err(|e| format!("Lock poisoned: {}", e))?;
-    Ok(state.settings.opsec_level)
+    with_profile(|profile| profile.settings.opsec_level)
 }
 
 /// Assess risk for a URL/domain
@@ -101,14 +212,14 @@ pub async fn assess_site_risk(url: String) -> PrivacyResult<RiskAssessment> {
     let assessment = assess_domain_risk(&domain);
 
     // Cache the assessment
-    {
-        let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-        state.stats.sites_assessed += 1;
+    with_counters(|profile| {
+        let stats = today(profile);
+        stats.sites_assessed += 1;
         if assessment.risk_score >= 60 {
-            state.stats.high_risk_sites_visited += 1;
+            stats.high_risk_sites_visited += 1;
         }
-        state.site_assessments.insert(domain.clone(), assessment.clone());
-    }
+        profile.record_assessment(domain.clone(), assessment.clone());
+    })?;
 
     info!(
         "Domain {} assessed: risk={}, category={:?}, recommended_opsec={:?}",
@@ -123,59 +234,61 @@ pub async fn auto_adjust_privacy(url: String) -> PrivacyResult<PrivacySettings>
     let domain = extract_domain(&url).unwrap_or_else(|| url.clone());
     let assessment = assess_domain_risk(&domain);
 
-    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-
-    if !state.settings.auto_adjust {
-        return Ok(state.settings.clone());
-    }
+    with_profile_mut(|profile| {
+        if !profile.settings.auto_adjust {
+            return profile.settings.clone();
+        }
 
-    // Only escalate, never downgrade automatically
-    if assessment.recommended_opsec > state.settings.opsec_level {
-        info!(
-            "Auto-escalating OPSEC from {:?} to {:?} for {}",
-            state.settings.opsec_level, assessment.recommended_opsec, domain
-        );
-        state.settings = PrivacySettings::for_level(assessment.recommended_opsec);
-        state.stats.auto_escalations += 1;
-    }
+        // Only escalate, never downgrade automatically
+        if assessment.recommended_opsec > profile.settings.opsec_level {
+            info!(
+                "Auto-escalating OPSEC from {:?} to {:?} for {}",
+                profile.settings.opsec_level, assessment.recommended_opsec, domain
+            );
+            profile.settings = PrivacySettings::for_level(assessment.recommended_opsec);
+            today(profile).auto_escalations += 1;
+        }
 
-    Ok(state.settings.clone())
+        profile.settings.clone()
+    })
 }
 
-/// Get privacy statistics
+/// Get privacy statistics, totalled over every recorded day
 pub async fn get_privacy_stats() -> PrivacyResult<PrivacyStats> {
-    let state = PRIVACY_STATE.read().map_err(|e| format!("Lock poisoned: {}", e))?;
-    Ok(state.stats.clone())
+    with_profile(|profile| profile.total_stats())
+}
+
+/// Get daily privacy statistics between two dates (inclusive), oldest first
+pub async fn get_privacy_stats_history(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> PrivacyResult<Vec<DailyPrivacyStats>> {
+    with_profile(|profile| profile.stats_history(from, to))
 }
 
 /// Record a blocked tracker
 pub async fn record_blocked_tracker(domain: String) -> PrivacyResult<()> {
     debug!("Blocked tracker: {}", domain);
-    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-    state.stats.trackers_blocked += 1;
-    Ok(())
+    with_counters(|profile| today(profile).trackers_blocked += 1)
 }
 
 /// Record a blocked fingerprint attempt
 pub async fn record_blocked_fingerprint(technique: String) -> PrivacyResult<()> {
     debug!("Blocked fingerprint attempt: {}", technique);
-    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-    state.stats.fingerprint_attempts_blocked += 1;
-    Ok(())
+    with_counters(|profile| today(profile).fingerprint_attempts_blocked += 1)
 }
 
 /// Get cached site assessments
 pub async fn get_site_assessments() -> PrivacyResult<Vec<RiskAssessment>> {
-    let state = PRIVACY_STATE.read().map_err(|e| format!("Lock poisoned: {}", e))?;
-    Ok(state.site_assessments.values().cloned().collect())
+    with_profile(|profile| profile.site_assessments.values().cloned().collect())
 }
 
 /// Clear privacy statistics
 pub async fn clear_privacy_stats() -> PrivacyResult<()> {
-    let mut state = PRIVACY_STATE.write().map_err(|e| format!("Lock poisoned: {}", e))?;
-    state.stats = PrivacyStats::default();
-    state.site_assessments.clear();
-    Ok(())
+    with_profile_mut(|profile| {
+        profile.daily_stats.clear();
+        profile.site_assessments.clear();
+    })
 }
 
 /// Extract domain from URL
@@ -0,0 +1,50 @@
+//! Retention Commands
+//!
+//! Handlers for the open vault's data retention policy and purge sweeps.
+
+use crate::retention::{self, RetentionPolicy, SweepReport};
+use crate::storage;
+use tracing::info;
+
+/// Result type for retention operations
+pub type RetentionResult<T> = Result<T, String>;
+
+/// Get the open vault's retention policy
+pub async fn get_retention_policy() -> RetentionResult<RetentionPolicy> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    retention::get_policy(store.as_ref())
+        .map_err(|e| format!("Failed to read retention policy: {}", e))
+}
+
+/// Replace the open vault's retention policy
+pub async fn set_retention_policy(policy: RetentionPolicy) -> RetentionResult<()> {
+    if policy.rules.iter().any(|rule| rule.id.trim().is_empty()) {
+        return Err("Every retention rule needs an id".to_string());
+    }
+
+    info!(
+        "Setting retention policy ({} rules, enabled: {})",
+        policy.rules.len(),
+        policy.enabled
+    );
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    retention::set_policy(store.as_ref(), &policy)
+        .map_err(|e| format!("Failed to save retention policy: {}", e))
+}
+
+/// Apply the policy now; with `dry_run`, only report what would be purged
+pub async fn run_retention_sweep(dry_run: bool) -> RetentionResult<SweepReport> {
+    info!("Running retention sweep (dry run: {})", dry_run);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let policy = retention::get_policy(store.as_ref())
+        .map_err(|e| format!("Failed to read retention policy: {}", e))?;
+    retention::run(store.as_ref(), &policy, dry_run)
+        .map_err(|e| format!("Retention sweep failed: {}", e))
+}
+
+/// Get the report of the most recent sweep, if one has run
+pub async fn get_last_retention_sweep() -> RetentionResult<Option<SweepReport>> {
+    Ok(retention::last_sweep())
+}
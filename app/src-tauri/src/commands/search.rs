@@ -0,0 +1,22 @@
+//! Search Commands
+//!
+//! One search box over everything in a case: entities, timeline events and
+//! captured page text.
+
+use crate::storage::{self, SearchHit};
+use tracing::debug;
+
+/// Result type for search operations
+pub type SearchResult<T> = Result<T, String>;
+
+/// Default number of hits returned when the caller does not ask for a limit
+const DEFAULT_LIMIT: usize = 100;
+
+/// Run a full-text query, e.g. `type:email notes:"wire transfer"`
+pub async fn search(query: String, limit: Option<usize>) -> SearchResult<Vec<SearchHit>> {
+    debug!("Searching: {}", query);
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    store
+        .search(&query, limit.unwrap_or(DEFAULT_LIMIT))
+        .map_err(|e| format!("Search failed: {}", e))
+}
@@ -6,6 +6,7 @@ use crate::session::{
     self, CloneOptions, CloneResult, SessionData, SessionExport, HistoryEntry, TabState,
 };
 use crate::storage;
+use crate::storage::audit::{self, AuditAction};
 use chrono::Utc;
 use std::collections::HashMap;
 use tracing::info;
@@ -47,6 +48,11 @@ pub async fn clone_session(
     store
         .save_session(&target_identity_id, &session_json)
         .map_err(|e| format!("Failed to save cloned session: {}", e))?;
+    audit::record(AuditAction::SessionCloned {
+        source_identity_id,
+        target_identity_id,
+    })
+    .map_err(|e| format!("Audit error: {}", e))?;
 
     info!(
         "Session cloned: {} cookies, {} localStorage entries, {} history entries",
@@ -90,6 +96,8 @@ pub async fn import_session(
     store
         .save_session(&target_identity_id, &session_json)
         .map_err(|e| format!("Failed to save imported session: {}", e))?;
+    audit::record(AuditAction::SessionImported { target_identity_id })
+    .map_err(|e| format!("Audit error: {}", e))?;
 
     Ok(imported)
 }
@@ -100,12 +108,15 @@ pub async fn clear_session(identity_id: String) -> SessionResult<()> {
     let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
     store
         .clear_session(&identity_id)
-        .map_err(|e| format!("Failed to clear session: {}", e))
+        .map_err(|e| format!("Failed to clear session: {}", e))?;
+    audit::record(AuditAction::SessionCleared { identity_id })
+        .map_err(|e| format!("Audit error: {}", e))?;
+    Ok(())
 }
 
 /// Get or create a default empty session for an identity
 fn get_or_create_session(
-    store: &std::sync::Arc<crate::storage::SledStore>,
+    store: &std::sync::Arc<dyn crate::storage::Store>,
     identity_id: &str,
 ) -> Result<SessionData, String> {
     match store
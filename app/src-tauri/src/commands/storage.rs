@@ -0,0 +1,97 @@
+//! Storage Commands
+//!
+//! Handlers for managing the encrypted sled store.
+
+use crate::storage;
+use crate::storage::audit::{self, AuditAction};
+use crate::storage::backup::{BackupManifest, BackupOptions, RestoreReport, RestoreScope};
+use std::path::PathBuf;
+use tracing::info;
+
+/// Result type for storage operations
+pub type StorageResult<T> = Result<T, String>;
+
+/// Change the store passphrase, re-sealing every record under the new key
+pub async fn change_passphrase(current: String, new: String) -> StorageResult<()> {
+    if new.is_empty() {
+        return Err("New passphrase cannot be empty".to_string());
+    }
+
+    info!("Changing store passphrase");
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    store
+        .change_passphrase(&current, &new)
+        .map_err(|e| format!("Failed to change passphrase: {}", e))
+}
+
+/// Get the store's schema version and the version this build writes
+pub async fn get_schema_version() -> StorageResult<(Option<u32>, u32)> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let current = store
+        .schema_version()
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+    Ok((current, storage::migrations::SCHEMA_VERSION))
+}
+
+/// Write an encrypted backup archive of the store and identity profiles
+pub async fn create_backup(
+    path: String,
+    passphrase: String,
+    options: BackupOptions,
+) -> StorageResult<BackupManifest> {
+    if passphrase.is_empty() {
+        return Err("Backup passphrase cannot be empty".to_string());
+    }
+
+    info!("Creating backup at {}", path);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let data_dir = storage::data_dir().map_err(|e| format!("Storage error: {}", e))?;
+    storage::backup::create_backup(
+        store.as_ref(),
+        &data_dir,
+        &PathBuf::from(path),
+        &passphrase,
+        &options,
+    )
+    .map_err(|e| format!("Backup failed: {}", e))
+}
+
+/// Decrypt and verify a backup archive, returning its manifest
+pub async fn inspect_backup(path: String, passphrase: String) -> StorageResult<BackupManifest> {
+    storage::backup::inspect_backup(&PathBuf::from(path), &passphrase)
+        .map_err(|e| format!("Failed to read backup: {}", e))
+}
+
+/// Restore everything, one identity or one investigation from a backup
+pub async fn restore_backup(
+    path: String,
+    passphrase: String,
+    scope: RestoreScope,
+) -> StorageResult<RestoreReport> {
+    info!("Restoring {:?} from {}", scope, path);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let data_dir = storage::data_dir().map_err(|e| format!("Storage error: {}", e))?;
+    let report = storage::backup::restore_backup(
+        store.as_ref(),
+        &data_dir,
+        &PathBuf::from(&path),
+        &passphrase,
+        &scope,
+        // A running browser must not keep using a profile being swapped out
+        |identity_id| crate::cef::with_manager_mut(|mgr| mgr.destroy_context(identity_id)),
+    )
+    .map_err(|e| format!("Restore failed: {}", e))?;
+    audit::record(AuditAction::BackupRestored {
+        archive: path,
+        scope,
+    })
+    .map_err(|e| format!("Audit error: {}", e))?;
+
+    // Cases are cached in memory; pick up the restored ones
+    crate::investigation::reload()?;
+
+    Ok(report)
+}
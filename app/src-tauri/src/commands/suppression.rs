@@ -0,0 +1,98 @@
+//! Suppression List Commands
+//!
+//! Handlers for the open vault's suppression list. The list is kept in the
+//! vault's settings and installed into the extraction pipeline when the
+//! vault opens and on every change.
+
+use crate::core::entity::EntityType;
+use crate::core::normalize::canonicalize;
+use crate::core::suppression::{self, SuppressionList, SuppressionRule, SUPPRESSION_SETTING};
+use crate::storage::{self, StorageError, Store};
+use tracing::info;
+
+/// Result type for suppression operations
+pub type SuppressionResult<T> = Result<T, String>;
+
+fn get_list(store: &dyn Store) -> Result<SuppressionList, StorageError> {
+    match store.get_setting(SUPPRESSION_SETTING)? {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(SuppressionList::default()),
+    }
+}
+
+/// Validate, persist and install `list`
+fn set_list(store: &dyn Store, list: &SuppressionList) -> SuppressionResult<()> {
+    list.compile()?;
+    let value = serde_json::to_value(list).map_err(|e| format!("Serialize error: {}", e))?;
+    store
+        .set_setting(SUPPRESSION_SETTING, &value)
+        .map_err(|e| format!("Failed to save suppression list: {}", e))?;
+    suppression::install(list)
+}
+
+/// Install the open vault's list (called when a vault opens)
+pub fn load() -> SuppressionResult<()> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let list =
+        get_list(store.as_ref()).map_err(|e| format!("Failed to read suppression list: {}", e))?;
+    suppression::install(&list)
+}
+
+/// Get the open vault's suppression list
+pub async fn get_suppression_list() -> SuppressionResult<SuppressionList> {
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    get_list(store.as_ref()).map_err(|e| format!("Failed to read suppression list: {}", e))
+}
+
+/// Replace the suppression list. Entities already in the Hivemind are kept.
+pub async fn save_suppression_list(list: SuppressionList) -> SuppressionResult<SuppressionList> {
+    info!(
+        "Saving suppression list ({} rules, min confidence {})",
+        list.rules.len(),
+        list.min_confidence
+    );
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    set_list(store.as_ref(), &list)?;
+    Ok(list)
+}
+
+/// Add a rule, unless the same rule is already listed
+pub async fn add_suppression_rule(rule: SuppressionRule) -> SuppressionResult<SuppressionList> {
+    info!("Adding suppression rule: {:?}", rule);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut list =
+        get_list(store.as_ref()).map_err(|e| format!("Failed to read suppression list: {}", e))?;
+    if !list.rules.contains(&rule) {
+        list.rules.push(rule);
+        set_list(store.as_ref(), &list)?;
+    }
+    Ok(list)
+}
+
+/// Remove the rule at `index`
+pub async fn remove_suppression_rule(index: usize) -> SuppressionResult<SuppressionList> {
+    info!("Removing suppression rule {}", index);
+
+    let store = storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    let mut list =
+        get_list(store.as_ref()).map_err(|e| format!("Failed to read suppression list: {}", e))?;
+    if index >= list.rules.len() {
+        return Err(format!("Suppression rule {} not found", index));
+    }
+    list.rules.remove(index);
+    set_list(store.as_ref(), &list)?;
+    Ok(list)
+}
+
+/// Whether the open vault's list covers a value
+pub async fn is_value_suppressed(
+    entity_type: EntityType,
+    value: String,
+) -> SuppressionResult<bool> {
+    Ok(suppression::is_suppressed(
+        &entity_type,
+        &canonicalize(&entity_type, &value),
+    ))
+}
@@ -0,0 +1,54 @@
+//! Vault Commands
+//!
+//! Handlers for listing, creating, opening and closing case vaults.
+
+use crate::storage::{MigrationOptions, MigrationReport};
+use crate::vault::{self, VaultInfo};
+use tracing::info;
+use zeroize::Zeroizing;
+
+/// Result type for vault operations
+pub type VaultResult<T> = Result<T, String>;
+
+/// List every registered vault
+pub async fn list_vaults() -> VaultResult<Vec<VaultInfo>> {
+    vault::list()
+}
+
+/// Get the name of the open vault, if any
+pub async fn get_active_vault() -> VaultResult<Option<String>> {
+    vault::active()
+}
+
+/// Register a new vault; it is created on disk and sealed on first open
+pub async fn create_vault(name: String) -> VaultResult<VaultInfo> {
+    info!("Creating vault: {}", name);
+    vault::create(&name)
+}
+
+/// Close the current vault (if any) and open `name` with its passphrase.
+///
+/// Migrations run with the default options unless `options` is given.
+pub async fn open_vault(
+    name: String,
+    passphrase: String,
+    options: Option<MigrationOptions>,
+) -> VaultResult<MigrationReport> {
+    let passphrase = Zeroizing::new(passphrase);
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+    info!("Switching to vault: {}", name);
+    vault::open(&name, &passphrase, &options.unwrap_or_default())
+}
+
+/// Close the current vault (if any) and open a burn-after-use in-memory vault
+pub async fn open_ephemeral_vault() -> VaultResult<()> {
+    info!("Switching to ephemeral vault");
+    vault::open_ephemeral()
+}
+
+/// Close the open vault, releasing its store and browser contexts
+pub async fn close_vault() -> VaultResult<()> {
+    vault::close()
+}
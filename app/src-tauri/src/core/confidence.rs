@@ -0,0 +1,244 @@
+//! Extraction Confidence
+//!
+//! How likely a match is to be a real selector rather than noise, scored
+//! 0-100. The score starts from the validator's verdict, loses points for
+//! values that are rarely of interest (private addresses, reserved and CDN
+//! hostnames, version numbers that parse as IPs) and gains some when the
+//! text labels the value ("email:", "wallet", "C2") or wrote it defanged.
+
+use crate::core::entity::EntityType;
+use crate::core::validators::ValidationStatus;
+use std::net::IpAddr;
+use url::Url;
+
+/// Characters of preceding text searched for labels and version cues
+pub const CUE_WINDOW: usize = 32;
+
+/// Hosts reserved for documentation and testing (RFC 2606, RFC 6761)
+const RESERVED_DOMAINS: &[&str] = &[
+    "example",
+    "example.com",
+    "example.net",
+    "example.org",
+    "invalid",
+    "local",
+    "localhost",
+    "test",
+];
+
+/// Content delivery and shared hosting domains, seen on nearly every page
+const CDN_DOMAINS: &[&str] = &[
+    "akamai.net",
+    "akamaiedge.net",
+    "akamaihd.net",
+    "azureedge.net",
+    "b-cdn.net",
+    "cdn.jsdelivr.net",
+    "cdnjs.cloudflare.com",
+    "cloudflare.com",
+    "cloudfront.net",
+    "edgekey.net",
+    "fastly.net",
+    "fastlylb.net",
+    "googleapis.com",
+    "googleusercontent.com",
+    "gstatic.com",
+    "unpkg.com",
+];
+
+/// Words before an IPv4-shaped match that make it a version number
+const VERSION_CUES: &[&str] = &["build", "release", "ver", "version", "v"];
+
+/// Score a match. `value` is canonical; `before` is the text preceding the
+/// occurrence (only its last [`CUE_WINDOW`] characters are read).
+pub fn score(
+    entity_type: &EntityType,
+    value: &str,
+    validation: ValidationStatus,
+    before: &str,
+    defanged: bool,
+) -> u8 {
+    let mut score: i32 = match validation {
+        ValidationStatus::Valid => 90,
+        ValidationStatus::Unchecked => 60,
+        ValidationStatus::Invalid => 10,
+    };
+
+    let cue: String = {
+        let start = before
+            .char_indices()
+            .rev()
+            .take(CUE_WINDOW)
+            .last()
+            .map_or(before.len(), |(i, _)| i);
+        before[start..].to_lowercase()
+    };
+    let words: Vec<&str> = cue
+        .split(|c: char| !c.is_alphanumeric() && c != '&')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    score -= noise_penalty(entity_type, value);
+    if *entity_type == EntityType::IpV4 && words.last().is_some_and(|w| VERSION_CUES.contains(w)) {
+        score -= 40;
+    }
+    if words
+        .iter()
+        .rev()
+        .take(4)
+        .any(|w| labels(entity_type).contains(w))
+    {
+        score += 10;
+    }
+    if defanged {
+        score += 10;
+    }
+    score.clamp(0, 100) as u8
+}
+
+/// Points lost for values that are valid but rarely of interest
+fn noise_penalty(entity_type: &EntityType, value: &str) -> i32 {
+    match entity_type {
+        EntityType::IpV4 | EntityType::IpV6 => match value.parse::<IpAddr>() {
+            Ok(ip) if is_non_public(ip) => 40,
+            _ => 0,
+        },
+        EntityType::Cidr => match value.split_once('/').map(|(ip, _)| ip.parse::<IpAddr>()) {
+            Some(Ok(ip)) if is_non_public(ip) => 30,
+            _ => 0,
+        },
+        EntityType::Domain | EntityType::Email | EntityType::Url => {
+            match host_of(entity_type, value) {
+                Some(host) if in_domains(&host, RESERVED_DOMAINS) => 50,
+                Some(host) if in_domains(&host, CDN_DOMAINS) => 30,
+                _ => 0,
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Words that, just before a match, say what it is
+fn labels(entity_type: &EntityType) -> &'static [&'static str] {
+    match entity_type {
+        EntityType::Email => &["contact", "email", "mail", "reach"],
+        EntityType::Phone => &["call", "cell", "fax", "mobile", "phone", "tel", "whatsapp"],
+        EntityType::IpV4
+        | EntityType::IpV6
+        | EntityType::Cidr
+        | EntityType::Domain
+        | EntityType::Url
+        | EntityType::OnionAddress => &[
+            "beacon", "c&c", "c2", "callback", "domain", "host", "ioc", "ip", "server",
+        ],
+        EntityType::BitcoinAddress
+        | EntityType::EthereumAddress
+        | EntityType::LitecoinAddress
+        | EntityType::MoneroAddress
+        | EntityType::TronAddress
+        | EntityType::SolanaAddress => &["address", "btc", "pay", "send", "wallet", "xmr"],
+        EntityType::Iban => &["account", "bank", "iban", "transfer"],
+        EntityType::Md5Hash | EntityType::Sha1Hash | EntityType::Sha256Hash => {
+            &["hash", "md5", "sha1", "sha256", "sample"]
+        }
+        _ => &[],
+    }
+}
+
+/// Loopback, private, link-local, unspecified, broadcast and documentation
+/// addresses
+pub fn is_non_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+        }
+        IpAddr::V6(ip) => {
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || (ip.segments()[0] == 0x2001 && ip.segments()[1] == 0x0db8)
+                || ip
+                    .to_ipv4_mapped()
+                    .is_some_and(|v4| is_non_public(IpAddr::V4(v4)))
+        }
+    }
+}
+
+/// Host of a canonical domain, email or URL
+pub fn host_of(entity_type: &EntityType, value: &str) -> Option<String> {
+    match entity_type {
+        EntityType::Domain => Some(value.to_string()),
+        EntityType::Email => value.rsplit_once('@').map(|(_, host)| host.to_string()),
+        EntityType::Url => Url::parse(value).ok()?.host_str().map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Whether `host` is one of `domains` or a subdomain of one
+pub fn in_domains(host: &str, domains: &[&str]) -> bool {
+    domains.iter().any(|d| is_within(host, d))
+}
+
+/// Whether `host` is `domain` or a subdomain of it
+pub fn is_within(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str, before: &str) -> u8 {
+        score(
+            &EntityType::IpV4,
+            value,
+            ValidationStatus::Valid,
+            before,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_noise_scores_lower() {
+        assert!(ip("10.1.2.3", "seen at ") < ip("8.8.8.8", "seen at "));
+        assert!(ip("1.2.3.4", "running version ") < ip("1.2.3.4", "seen at "));
+
+        let domain = |value: &str| {
+            score(
+                &EntityType::Domain,
+                value,
+                ValidationStatus::Unchecked,
+                "",
+                false,
+            )
+        };
+        assert!(domain("example.com") < domain("evil.com"));
+        assert!(domain("d1234.cloudfront.net") < domain("evil.com"));
+        assert!(domain("notexample.com") == domain("evil.com"));
+    }
+
+    #[test]
+    fn test_labels_and_defanging_raise_score() {
+        let email = |before: &str, defanged: bool| {
+            score(
+                &EntityType::Email,
+                "bob@corp.io",
+                ValidationStatus::Unchecked,
+                before,
+                defanged,
+            )
+        };
+        assert!(email("Email: ", false) > email("lorem ipsum ", false));
+        assert!(email("", true) > email("", false));
+        assert!(email("contact ", true) <= 100);
+    }
+}
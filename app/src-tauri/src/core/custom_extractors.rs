@@ -0,0 +1,352 @@
+//! Custom Extractors
+//!
+//! User-defined extractors for case-specific identifiers such as case
+//! numbers, licence plates or employee IDs. Each is a named regex with an
+//! optional validator and normalisation template; its matches become
+//! `EntityType::Custom(name)` entities.
+//!
+//! The rule set is stored in the open vault's settings and compiled into a
+//! process-wide registry when the vault opens, so extraction, normalisation
+//! and validation all see the same rules.
+
+use crate::core::entity::{EntityType, TextSpan};
+use crate::core::validators::{luhn, ValidationStatus};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+/// Store setting holding the [`CustomRuleSet`]
+pub const CUSTOM_EXTRACTORS_SETTING: &str = "custom_extractors";
+
+/// Compiled size limit per pattern, to keep pathological rules cheap
+const MAX_PATTERN_SIZE: usize = 1 << 20;
+
+/// Compiled rules of the open vault
+static REGISTRY: RwLock<Vec<CompiledExtractor>> = RwLock::new(Vec::new());
+
+/// A user-defined extractor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomExtractor {
+    /// Entity type name (`[a-z0-9_]`), e.g. `case_number`
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(default)]
+    pub validator: Option<CustomValidator>,
+    /// Replacement template applied to each match, in regex syntax
+    /// (`CASE-${year}-$2`). Without one, matches are only trimmed (and
+    /// lowercased unless `case_sensitive`).
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Check a custom match must pass to be kept
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CustomValidator {
+    /// Luhn check digit over the digits of the match
+    Luhn,
+    /// Character count of the match
+    Length { min: usize, max: usize },
+    /// Match starts with one of these (case-insensitive)
+    Prefix { prefixes: Vec<String> },
+}
+
+/// The persisted set of custom extractors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomRuleSet {
+    #[serde(default)]
+    pub extractors: Vec<CustomExtractor>,
+}
+
+impl CustomRuleSet {
+    /// Compile every rule, rejecting the set if any fails or names repeat
+    pub fn compile(&self) -> Result<Vec<CompiledExtractor>, String> {
+        let mut compiled: Vec<CompiledExtractor> = Vec::with_capacity(self.extractors.len());
+        for rule in &self.extractors {
+            if compiled.iter().any(|c| c.rule.name == rule.name) {
+                return Err(format!("Duplicate extractor name '{}'", rule.name));
+            }
+            compiled.push(rule.compile()?);
+        }
+        Ok(compiled)
+    }
+}
+
+/// A rule with its regexes built
+#[derive(Debug, Clone)]
+pub struct CompiledExtractor {
+    rule: CustomExtractor,
+    regex: Regex,
+    /// The pattern anchored to a whole value, for re-normalising
+    anchored: Regex,
+}
+
+impl CustomExtractor {
+    pub fn entity_type(&self) -> EntityType {
+        EntityType::Custom(self.name.clone())
+    }
+
+    pub fn compile(&self) -> Result<CompiledExtractor, String> {
+        if self.name.is_empty()
+            || self.name.len() > 48
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(format!(
+                "Extractor name '{}' must be 1-48 characters of a-z, 0-9 and '_'",
+                self.name
+            ));
+        }
+        if let Some(CustomValidator::Length { min, max }) = &self.validator {
+            if min > max {
+                return Err(format!("Extractor '{}': length min exceeds max", self.name));
+            }
+        }
+
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(!self.case_sensitive)
+                .size_limit(MAX_PATTERN_SIZE)
+                .build()
+                .map_err(|e| format!("Extractor '{}': invalid pattern: {}", self.name, e))
+        };
+        let regex = build(&self.pattern)?;
+        if regex.is_match("") {
+            return Err(format!(
+                "Extractor '{}': pattern matches the empty string",
+                self.name
+            ));
+        }
+        let anchored = build(&format!("^(?:{})$", self.pattern))?;
+
+        Ok(CompiledExtractor {
+            rule: self.clone(),
+            regex,
+            anchored,
+        })
+    }
+}
+
+impl CompiledExtractor {
+    pub fn rule(&self) -> &CustomExtractor {
+        &self.rule
+    }
+
+    /// Byte offsets of the matches in `text`
+    pub fn find(&self, text: &str) -> Vec<TextSpan> {
+        self.regex
+            .find_iter(text)
+            .map(|m| TextSpan {
+                start: m.start(),
+                end: m.end(),
+            })
+            .collect()
+    }
+
+    pub fn validate(&self, value: &str) -> ValidationStatus {
+        let Some(validator) = &self.rule.validator else {
+            return ValidationStatus::Unchecked;
+        };
+        let valid = match validator {
+            CustomValidator::Luhn => {
+                let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+                luhn(&digits)
+            }
+            CustomValidator::Length { min, max } => {
+                (*min..=*max).contains(&value.trim().chars().count())
+            }
+            CustomValidator::Prefix { prefixes } => {
+                let value = value.trim().to_lowercase();
+                prefixes
+                    .iter()
+                    .any(|prefix| value.starts_with(&prefix.to_lowercase()))
+            }
+        };
+        if valid {
+            ValidationStatus::Valid
+        } else {
+            ValidationStatus::Invalid
+        }
+    }
+
+    pub fn canonicalize(&self, value: &str) -> String {
+        let value = value.trim();
+        if let (Some(template), Some(caps)) = (&self.rule.template, self.anchored.captures(value)) {
+            let mut expanded = String::new();
+            caps.expand(template, &mut expanded);
+            return expanded.trim().to_string();
+        }
+        if self.rule.case_sensitive {
+            value.to_string()
+        } else {
+            value.to_lowercase()
+        }
+    }
+}
+
+/// Replace the registry with `rules` (all-or-nothing)
+pub fn install(rules: &CustomRuleSet) -> Result<(), String> {
+    let compiled = rules.compile()?;
+    let mut registry = REGISTRY
+        .write()
+        .map_err(|e| format!("Extractor registry lock poisoned: {}", e))?;
+    *registry = compiled;
+    Ok(())
+}
+
+/// Drop every installed rule
+pub fn clear() {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.clear();
+    }
+}
+
+fn with_extractor<R>(name: &str, f: impl FnOnce(&CompiledExtractor) -> R) -> Option<R> {
+    let registry = REGISTRY.read().ok()?;
+    registry.iter().find(|c| c.rule.name == name).map(f)
+}
+
+/// Entity types of the enabled rules
+pub fn active_types() -> Vec<EntityType> {
+    REGISTRY
+        .read()
+        .map(|registry| {
+            registry
+                .iter()
+                .filter(|c| c.rule.enabled)
+                .map(|c| c.rule.entity_type())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Matches of the named rule (none if it is not installed or disabled)
+pub fn find(name: &str, text: &str) -> Vec<TextSpan> {
+    with_extractor(name, |c| if c.rule.enabled { c.find(text) } else { vec![] }).unwrap_or_default()
+}
+
+pub fn validate(name: &str, value: &str) -> ValidationStatus {
+    with_extractor(name, |c| c.validate(value)).unwrap_or(ValidationStatus::Unchecked)
+}
+
+/// Canonical form under the named rule, if it is installed. Disabled rules
+/// still apply, so new sightings keep merging into the entities they produced.
+pub fn canonicalize(name: &str, value: &str) -> Option<String> {
+    with_extractor(name, |c| c.canonicalize(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, pattern: &str) -> CustomExtractor {
+        CustomExtractor {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            case_sensitive: false,
+            validator: None,
+            template: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_template_normalises_variants() {
+        let mut case = rule(
+            "case_number",
+            r"case\s*#?\s*(?P<year>\d{4})[-/ ](?P<seq>\d{1,6})",
+        );
+        case.template = Some("CASE-${year}-${seq}".to_string());
+        let compiled = case.compile().unwrap();
+
+        let text = "See Case #2024-118 and case 2024/118.";
+        let found = compiled.find(text);
+        assert_eq!(found.len(), 2);
+        for span in found {
+            assert_eq!(
+                compiled.canonicalize(&text[span.start..span.end]),
+                "CASE-2024-118"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validators() {
+        let mut employee = rule("employee_id", r"\b[A-Z]{2}\d{6}\b");
+        employee.validator = Some(CustomValidator::Prefix {
+            prefixes: vec!["HR".to_string(), "IT".to_string()],
+        });
+        let compiled = employee.compile().unwrap();
+        assert_eq!(compiled.validate("hr123456"), ValidationStatus::Valid);
+        assert_eq!(compiled.validate("QX123456"), ValidationStatus::Invalid);
+
+        let mut imei = rule("imei", r"\b\d{15}\b");
+        imei.validator = Some(CustomValidator::Luhn);
+        let compiled = imei.compile().unwrap();
+        assert_eq!(
+            compiled.validate("490154203237518"),
+            ValidationStatus::Valid
+        );
+        assert_eq!(
+            compiled.validate("490154203237519"),
+            ValidationStatus::Invalid
+        );
+
+        let mut plate = rule("plate", r"\b[A-Z0-9]{2,8}\b");
+        plate.validator = Some(CustomValidator::Length { min: 5, max: 7 });
+        let compiled = plate.compile().unwrap();
+        assert_eq!(compiled.validate("AB12CDE"), ValidationStatus::Valid);
+        assert_eq!(compiled.validate("AB1"), ValidationStatus::Invalid);
+    }
+
+    #[test]
+    fn test_editing_a_rule_keeps_entity_hashes() {
+        use crate::core::entity::{Entity, EntitySource};
+
+        let mut ticket = rule("ticket_ref", r"tkt-\d+");
+        ticket.template = Some("TKT-$0".to_string());
+        let set = |ticket: &CustomExtractor| CustomRuleSet {
+            extractors: vec![ticket.clone()],
+        };
+        install(&set(&ticket)).unwrap();
+        let entity = Entity::new(
+            ticket.entity_type(),
+            "tkt-42".to_string(),
+            EntitySource::new("prime"),
+        );
+
+        ticket.template = None;
+        install(&set(&ticket)).unwrap();
+        assert_eq!(
+            Entity::compute_hash(&entity.entity_type, &entity.value),
+            entity.hash
+        );
+        clear();
+        assert_eq!(
+            Entity::compute_hash(&entity.entity_type, &entity.value),
+            entity.hash
+        );
+    }
+
+    #[test]
+    fn test_bad_rules_are_rejected() {
+        assert!(rule("Case Number", r"\d+").compile().is_err());
+        assert!(rule("case", r"(").compile().is_err());
+        assert!(rule("case", r"\d*").compile().is_err());
+
+        let set = CustomRuleSet {
+            extractors: vec![rule("case", r"\d+"), rule("case", r"[a-z]+")],
+        };
+        assert!(set.compile().is_err());
+    }
+}
@@ -0,0 +1,516 @@
+//! Date Extraction
+//!
+//! Finds absolute dates and timestamps in page text and normalises them to a
+//! canonical string plus precision: `2024` (year), `2024-03` (month),
+//! `2024-03-05` (day) or `2024-03-05T14:30:00` (time; suffixed `Z` when the
+//! text gave a zone, which is converted to UTC). Relative dates ("yesterday")
+//! are ignored.
+//!
+//! Recognised forms: ISO 8601, `YYYY/MM/DD`, numeric `D.M.YYYY`,
+//! `D-M-YYYY` and `M/D/YYYY`, and written months in English, French, German,
+//! Spanish, Italian, Portuguese and Dutch (`5 March 2024`, `March 5th, 2024`,
+//! `5. März 2024`, `5 de marzo de 2024`, `March 2024`), each optionally
+//! followed by a time of day.
+//!
+//! Numeric dates are read day-first when separated by `.` or `-` and
+//! month-first when separated by `/`, unless a component over 12 settles it.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+
+/// How much of a date the text gave
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePrecision {
+    Year,
+    Month,
+    Day,
+    Time,
+}
+
+/// A date in canonical form
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NormalizedDate {
+    /// `YYYY`, `YYYY-MM`, `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM:SS[Z]`
+    pub value: String,
+    pub precision: DatePrecision,
+}
+
+impl NormalizedDate {
+    fn day(date: NaiveDate) -> Self {
+        Self {
+            value: date.format("%Y-%m-%d").to_string(),
+            precision: DatePrecision::Day,
+        }
+    }
+
+    fn time(datetime: NaiveDateTime, utc: bool) -> Self {
+        let mut value = datetime.format("%Y-%m-%dT%H:%M:%S").to_string();
+        if utc {
+            value.push('Z');
+        }
+        Self {
+            value,
+            precision: DatePrecision::Time,
+        }
+    }
+
+    /// First instant the date covers, reading zone-less times as UTC
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        let value = self.value.trim_end_matches('Z');
+        let datetime = match self.precision {
+            DatePrecision::Year => {
+                NaiveDate::from_ymd_opt(value.parse().ok()?, 1, 1)?.and_time(NaiveTime::MIN)
+            }
+            DatePrecision::Month => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+                .ok()?
+                .and_time(NaiveTime::MIN),
+            DatePrecision::Day => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()?
+                .and_time(NaiveTime::MIN),
+            DatePrecision::Time => {
+                NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").ok()?
+            }
+        };
+        Some(Utc.from_utc_datetime(&datetime))
+    }
+}
+
+/// A date found in text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DateMatch {
+    /// Byte offsets of the match
+    pub start: usize,
+    pub end: usize,
+    /// The match as it appeared in the text
+    pub raw: String,
+    pub date: NormalizedDate,
+}
+
+/// Month names by language; `false` marks abbreviations (and short names
+/// that double as common words), which only count next to a day
+const MONTH_NAMES: &[(&str, u32, bool)] = &[
+    // English
+    ("january", 1, true),
+    ("jan", 1, false),
+    ("february", 2, true),
+    ("feb", 2, false),
+    ("march", 3, true),
+    ("mar", 3, false),
+    ("april", 4, true),
+    ("apr", 4, false),
+    ("may", 5, false),
+    ("june", 6, true),
+    ("jun", 6, false),
+    ("july", 7, true),
+    ("jul", 7, false),
+    ("august", 8, true),
+    ("aug", 8, false),
+    ("september", 9, true),
+    ("sept", 9, false),
+    ("sep", 9, false),
+    ("october", 10, true),
+    ("oct", 10, false),
+    ("november", 11, true),
+    ("nov", 11, false),
+    ("december", 12, true),
+    ("dec", 12, false),
+    // French
+    ("janvier", 1, true),
+    ("janv", 1, false),
+    ("février", 2, true),
+    ("fevrier", 2, true),
+    ("févr", 2, false),
+    ("mars", 3, true),
+    ("avril", 4, true),
+    ("avr", 4, false),
+    ("mai", 5, true),
+    ("juin", 6, true),
+    ("juillet", 7, true),
+    ("juil", 7, false),
+    ("août", 8, true),
+    ("aout", 8, true),
+    ("septembre", 9, true),
+    ("octobre", 10, true),
+    ("novembre", 11, true),
+    ("décembre", 12, true),
+    ("decembre", 12, true),
+    ("déc", 12, false),
+    // German
+    ("januar", 1, true),
+    ("jänner", 1, true),
+    ("februar", 2, true),
+    ("märz", 3, true),
+    ("maerz", 3, true),
+    ("juni", 6, true),
+    ("juli", 7, true),
+    ("oktober", 10, true),
+    ("okt", 10, false),
+    ("dezember", 12, true),
+    ("dez", 12, false),
+    // Spanish
+    ("enero", 1, true),
+    ("ene", 1, false),
+    ("febrero", 2, true),
+    ("marzo", 3, true),
+    ("abril", 4, true),
+    ("abr", 4, false),
+    ("mayo", 5, true),
+    ("junio", 6, true),
+    ("julio", 7, true),
+    ("agosto", 8, true),
+    ("ago", 8, false),
+    ("septiembre", 9, true),
+    ("setiembre", 9, true),
+    ("octubre", 10, true),
+    ("noviembre", 11, true),
+    ("diciembre", 12, true),
+    ("dic", 12, false),
+    // Italian
+    ("gennaio", 1, true),
+    ("gen", 1, false),
+    ("febbraio", 2, true),
+    ("aprile", 4, true),
+    ("maggio", 5, true),
+    ("giugno", 6, true),
+    ("luglio", 7, true),
+    ("settembre", 9, true),
+    ("set", 9, false),
+    ("ottobre", 10, true),
+    ("ott", 10, false),
+    ("dicembre", 12, true),
+    // Portuguese
+    ("janeiro", 1, true),
+    ("fevereiro", 2, true),
+    ("fev", 2, false),
+    ("março", 3, true),
+    ("marco", 3, true),
+    ("maio", 5, true),
+    ("junho", 6, true),
+    ("julho", 7, true),
+    ("setembro", 9, true),
+    ("outubro", 10, true),
+    ("out", 10, false),
+    ("novembro", 11, true),
+    ("dezembro", 12, true),
+    // Dutch
+    ("januari", 1, true),
+    ("februari", 2, true),
+    ("maart", 3, true),
+    ("mei", 5, true),
+    ("augustus", 8, true),
+];
+
+/// Optional time of day after a date
+const TIME: &str = r"(?:,?\s+(?:at\s+|à\s+|um\s+|a las\s+)?(?P<H>\d{1,2}):(?P<M>\d{2})(?::(?P<S>\d{2}))?(?:\s*(?P<ampm>[aApP]\.?[mM]\.?))?(?:\s*(?P<tz>Z|UTC|GMT|[+-]\d{2}:?\d{2}))?)?";
+
+/// Alternation of month names, longest first so `sept` beats `sep`
+fn month_alternation(full_only: bool) -> String {
+    let mut names: Vec<&str> = MONTH_NAMES
+        .iter()
+        .filter(|(_, _, full)| *full || !full_only)
+        .map(|(name, _, _)| *name)
+        .collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.chars().count()));
+    names.dedup();
+    names
+        .iter()
+        .map(|name| regex::escape(name))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+lazy_static! {
+    static ref ISO_REGEX: Regex = Regex::new(
+        r"\b(?P<y>\d{4})-(?P<m>\d{2})-(?P<d>\d{2})(?:[T ](?P<H>\d{2}):(?P<M>\d{2})(?::(?P<S>\d{2})(?:\.\d+)?)?(?P<tz>Z|[+-]\d{2}:?\d{2})?)?\b"
+    ).unwrap();
+
+    static ref WRITTEN_DMY_REGEX: Regex = Regex::new(&format!(
+        r"(?i)\b(?P<d>\d{{1,2}})(?:st|nd|rd|th|er|e|\.|º)?\s+(?:de\s+|of\s+)?(?P<mon>{})\.?,?\s+(?:de\s+)?(?P<y>\d{{4}})\b{}",
+        month_alternation(false),
+        TIME
+    )).unwrap();
+
+    static ref WRITTEN_MDY_REGEX: Regex = Regex::new(&format!(
+        r"(?i)\b(?P<mon>{})\.?\s+(?P<d>\d{{1,2}})(?:st|nd|rd|th)?,?\s+(?P<y>\d{{4}})\b{}",
+        month_alternation(false),
+        TIME
+    )).unwrap();
+
+    static ref NUMERIC_YMD_REGEX: Regex = Regex::new(&format!(
+        r"\b(?P<y>\d{{4}})/(?P<m>\d{{1,2}})/(?P<d>\d{{1,2}})\b{}",
+        TIME
+    )).unwrap();
+
+    static ref NUMERIC_REGEX: Regex = Regex::new(&format!(
+        r"\b(?P<a>\d{{1,2}})(?P<sep1>[./-])(?P<b>\d{{1,2}})(?P<sep2>[./-])(?P<y>\d{{4}})\b{}",
+        TIME
+    )).unwrap();
+
+    static ref MONTH_YEAR_REGEX: Regex = Regex::new(&format!(
+        r"(?i)\b(?P<mon>{}),?\s+(?:de\s+)?(?P<y>\d{{4}})\b",
+        month_alternation(true)
+    )).unwrap();
+
+    static ref YEAR_REGEX: Regex = Regex::new(r"^(?P<y>\d{4})$").unwrap();
+
+    static ref YEAR_MONTH_REGEX: Regex = Regex::new(r"^(?P<y>\d{4})-(?P<m>\d{2})$").unwrap();
+}
+
+/// Reads a date out of one pattern's captures
+type DateParser = fn(&Captures) -> Option<NormalizedDate>;
+
+/// Find every date in `text`, in order of appearance.
+///
+/// Where forms overlap (`5 March 2024` also contains `March 2024`) the most
+/// precise reading wins.
+pub fn find_dates(text: &str) -> Vec<DateMatch> {
+    let patterns: [(&Regex, DateParser); 6] = [
+        (&ISO_REGEX, iso),
+        (&WRITTEN_DMY_REGEX, written),
+        (&WRITTEN_MDY_REGEX, written),
+        (&NUMERIC_YMD_REGEX, numeric_ymd),
+        (&NUMERIC_REGEX, numeric),
+        (&MONTH_YEAR_REGEX, month_year),
+    ];
+
+    let mut matches: Vec<DateMatch> = Vec::new();
+    for (regex, parse) in patterns {
+        for caps in regex.captures_iter(text) {
+            let whole = caps.get(0).expect("group 0 always matches");
+            let overlaps = matches
+                .iter()
+                .any(|m| whole.start() < m.end && m.start < whole.end());
+            if overlaps {
+                continue;
+            }
+            if let Some(date) = parse(&caps) {
+                matches.push(DateMatch {
+                    start: whole.start(),
+                    end: whole.end(),
+                    raw: whole.as_str().to_string(),
+                    date,
+                });
+            }
+        }
+    }
+
+    matches.sort_by_key(|m| m.start);
+    matches
+}
+
+/// Parse a value that is entirely one date, including canonical forms
+pub fn parse_date(value: &str) -> Option<NormalizedDate> {
+    let value = value.trim();
+    if let Some(caps) = YEAR_REGEX.captures(value) {
+        let year: i32 = caps["y"].parse().ok()?;
+        NaiveDate::from_ymd_opt(year, 1, 1)?;
+        return Some(NormalizedDate {
+            value: format!("{:04}", year),
+            precision: DatePrecision::Year,
+        });
+    }
+    if let Some(caps) = YEAR_MONTH_REGEX.captures(value) {
+        return month_of(caps["y"].parse().ok()?, caps["m"].parse().ok()?);
+    }
+    find_dates(value)
+        .into_iter()
+        .find(|m| m.start == 0 && m.end == value.len())
+        .map(|m| m.date)
+}
+
+fn iso(caps: &Captures) -> Option<NormalizedDate> {
+    let date = NaiveDate::from_ymd_opt(
+        caps["y"].parse().ok()?,
+        caps["m"].parse().ok()?,
+        caps["d"].parse().ok()?,
+    )?;
+    Some(with_time(date, caps))
+}
+
+fn written(caps: &Captures) -> Option<NormalizedDate> {
+    let date = NaiveDate::from_ymd_opt(
+        caps["y"].parse().ok()?,
+        month_number(&caps["mon"])?,
+        caps["d"].parse().ok()?,
+    )?;
+    Some(with_time(date, caps))
+}
+
+fn numeric_ymd(caps: &Captures) -> Option<NormalizedDate> {
+    let year: i32 = caps["y"].parse().ok()?;
+    if !(1900..=2099).contains(&year) {
+        return None;
+    }
+    let date = NaiveDate::from_ymd_opt(year, caps["m"].parse().ok()?, caps["d"].parse().ok()?)?;
+    Some(with_time(date, caps))
+}
+
+fn numeric(caps: &Captures) -> Option<NormalizedDate> {
+    let separator = &caps["sep1"];
+    if separator != &caps["sep2"] {
+        return None;
+    }
+    let year: i32 = caps["y"].parse().ok()?;
+    if !(1900..=2099).contains(&year) {
+        return None;
+    }
+    let a: u32 = caps["a"].parse().ok()?;
+    let b: u32 = caps["b"].parse().ok()?;
+    let (month, day) = if a > 12 {
+        (b, a)
+    } else if b > 12 || separator == "/" {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    Some(with_time(NaiveDate::from_ymd_opt(year, month, day)?, caps))
+}
+
+fn month_year(caps: &Captures) -> Option<NormalizedDate> {
+    month_of(caps["y"].parse().ok()?, month_number(&caps["mon"])?)
+}
+
+fn month_of(year: i32, month: u32) -> Option<NormalizedDate> {
+    NaiveDate::from_ymd_opt(year, month, 1)?;
+    Some(NormalizedDate {
+        value: format!("{:04}-{:02}", year, month),
+        precision: DatePrecision::Month,
+    })
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    MONTH_NAMES
+        .iter()
+        .find(|(candidate, _, _)| *candidate == name)
+        .map(|(_, month, _)| *month)
+}
+
+/// Attach the optional time groups. A time that is absent or invalid
+/// (`13:00 PM`) leaves the date at day precision.
+fn with_time(date: NaiveDate, caps: &Captures) -> NormalizedDate {
+    time_of(date, caps).unwrap_or_else(|| NormalizedDate::day(date))
+}
+
+fn time_of(date: NaiveDate, caps: &Captures) -> Option<NormalizedDate> {
+    let hour = caps.name("H")?;
+    let mut hour: u32 = hour.as_str().parse().ok()?;
+    let minute: u32 = caps["M"].parse().ok()?;
+    let second: u32 = caps
+        .name("S")
+        .map_or(Some(0), |s| s.as_str().parse().ok())?;
+
+    if let Some(ampm) = caps.name("ampm") {
+        let pm = ampm.as_str().to_lowercase().starts_with('p');
+        if hour == 0 || hour > 12 {
+            return None;
+        }
+        hour = match (pm, hour) {
+            (true, 12) => 12,
+            (true, h) => h + 12,
+            (false, 12) => 0,
+            (false, h) => h,
+        };
+    }
+
+    let local = date.and_time(NaiveTime::from_hms_opt(hour, minute, second)?);
+    match caps.name("tz") {
+        Some(tz) => {
+            let offset = parse_offset(tz.as_str())?;
+            let utc = offset.from_local_datetime(&local).single()?.naive_utc();
+            Some(NormalizedDate::time(utc, true))
+        }
+        None => Some(NormalizedDate::time(local, false)),
+    }
+}
+
+/// `Z`, `UTC`, `GMT` (in any case), `+05:30` or `-0800`
+fn parse_offset(tz: &str) -> Option<FixedOffset> {
+    if ["Z", "UTC", "GMT"]
+        .iter()
+        .any(|zone| tz.eq_ignore_ascii_case(zone))
+    {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = if tz.starts_with('-') { -1 } else { 1 };
+    let digits: String = tz.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Vec<String> {
+        find_dates(text).into_iter().map(|m| m.date.value).collect()
+    }
+
+    #[test]
+    fn test_iso_and_numeric_forms() {
+        assert_eq!(
+            values("Posted 2024-03-05T14:30:00+02:00, edited 2024-03-06"),
+            vec!["2024-03-05T12:30:00Z", "2024-03-06"]
+        );
+        assert_eq!(values("on 03/05/2024"), vec!["2024-03-05"]);
+        assert_eq!(values("am 05.03.2024 um 9:15"), vec!["2024-03-05T09:15:00"]);
+        assert_eq!(values("due 25/12/2024"), vec!["2024-12-25"]);
+        assert!(values("version 1.2.3 and 31/31/2024").is_empty());
+    }
+
+    #[test]
+    fn test_lowercase_zones_and_invalid_times() {
+        assert_eq!(
+            values("5 March 2024 10:00 utc"),
+            vec!["2024-03-05T10:00:00Z"]
+        );
+        assert_eq!(
+            values("march 5, 2024 at 9:30 am gmt"),
+            vec!["2024-03-05T09:30:00Z"]
+        );
+
+        // An impossible time keeps the day instead of losing the match
+        let found = find_dates("March 5, 2024 at 13:00 PM");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].date.value, "2024-03-05");
+        assert_eq!(found[0].date.precision, DatePrecision::Day);
+        assert_eq!(values("5 March 2024 25:61"), vec!["2024-03-05"]);
+    }
+
+    #[test]
+    fn test_written_months_in_several_languages() {
+        assert_eq!(
+            values("On March 5th, 2024 at 3:45 PM and 5 March 2024"),
+            vec!["2024-03-05T15:45:00", "2024-03-05"]
+        );
+        assert_eq!(values("le 14 juillet 2023"), vec!["2023-07-14"]);
+        assert_eq!(values("am 3. März 2021"), vec!["2021-03-03"]);
+        assert_eq!(values("el 5 de marzo de 2024"), vec!["2024-03-05"]);
+        assert_eq!(
+            values("Tue, 05 Mar 2024 14:30:00 GMT"),
+            vec!["2024-03-05T14:30:00Z"]
+        );
+
+        let month = find_dates("joined in September 2019");
+        assert_eq!(month[0].date.value, "2019-09");
+        assert_eq!(month[0].date.precision, DatePrecision::Month);
+        assert!(values("sold out 2024").is_empty());
+    }
+
+    #[test]
+    fn test_canonical_values_round_trip() {
+        for canonical in ["2024", "2024-03", "2024-03-05", "2024-03-05T14:30:00Z"] {
+            assert_eq!(parse_date(canonical).unwrap().value, canonical);
+        }
+        let start = parse_date("2024-03").unwrap().start().unwrap();
+        assert_eq!(start.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+        assert!(parse_date("not a date").is_none());
+    }
+}
@@ -0,0 +1,790 @@
+//! Document Extraction
+//!
+//! Reads the text and metadata of local files so they can go through the
+//! entity extractor like page text: PDF, Office Open XML (`.docx`, `.xlsx`,
+//! `.pptx`), RFC 822 email (`.eml`) and CSV or plain text.
+//!
+//! Metadata is what leaked documents give away about who made them: author
+//! and last editor, the tool that wrote the file and when it was created and
+//! modified, and for email the addressing and `Received` headers. Links
+//! embedded behind text (PDF link annotations, Office hyperlinks, HTML email
+//! anchors) are collected too, since they never appear in the text itself.
+//!
+//! Every document is identified by the SHA-256 of its bytes.
+
+use crate::core::dates::{parse_date, NormalizedDate};
+use mail_parser::{MessageParser, MimeHeaders};
+use roxmltree::Node;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// Largest file read
+pub const MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Most decompressed bytes read from one Office document part, so a zip
+/// bomb cannot exhaust memory
+const MAX_PART_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Email headers kept as metadata fields, besides those read into
+/// [`DocumentMetadata`] directly
+const EMAIL_FIELDS: &[&str] = &[
+    "To",
+    "Cc",
+    "Bcc",
+    "Reply-To",
+    "Return-Path",
+    "Sender",
+    "Message-ID",
+    "In-Reply-To",
+    "X-Originating-IP",
+    "X-Sender-IP",
+    "Received",
+];
+
+/// Formats that can be read
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentFormat {
+    Pdf,
+    Docx,
+    Xlsx,
+    Pptx,
+    Eml,
+    Csv,
+    Text,
+}
+
+/// What a document says about its origin
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    /// Author, or for email the sender
+    pub author: Option<String>,
+    pub last_modified_by: Option<String>,
+    /// Application that wrote the document (PDF `Creator`, Office
+    /// `Application`, email `X-Mailer`)
+    pub creator_tool: Option<String>,
+    /// PDF `Producer`: the library that rendered it
+    pub producer: Option<String>,
+    pub created: Option<NormalizedDate>,
+    pub modified: Option<NormalizedDate>,
+    /// Other format-specific fields, e.g. email headers or an Office
+    /// document's `Company`
+    pub fields: Vec<(String, String)>,
+}
+
+/// A file's text and metadata
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Document {
+    pub format: DocumentFormat,
+    /// Hex SHA-256 of the file
+    pub sha256: String,
+    pub size: u64,
+    pub text: String,
+    pub metadata: DocumentMetadata,
+    /// Link targets embedded in the document
+    pub links: Vec<String>,
+}
+
+impl Document {
+    /// The text to run the entity extractor over: the metadata as
+    /// `Field: value` lines, the document text, then the embedded links
+    pub fn extraction_text(&self) -> String {
+        let m = &self.metadata;
+        let mut lines: Vec<String> = Vec::new();
+        let mut field = |name: &str, value: Option<&str>| {
+            if let Some(value) = value.filter(|v| !v.trim().is_empty()) {
+                lines.push(format!("{}: {}", name, value.trim()));
+            }
+        };
+        field("Title", m.title.as_deref());
+        field("Author", m.author.as_deref());
+        field("Last modified by", m.last_modified_by.as_deref());
+        field("Creator tool", m.creator_tool.as_deref());
+        field("Producer", m.producer.as_deref());
+        field("Created", m.created.as_ref().map(|d| d.value.as_str()));
+        field("Modified", m.modified.as_ref().map(|d| d.value.as_str()));
+        for (name, value) in &m.fields {
+            field(name, Some(value));
+        }
+
+        let mut text = lines.join("\n");
+        if !self.text.trim().is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&self.text);
+        }
+        if !self.links.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&self.links.join("\n"));
+        }
+        text
+    }
+}
+
+/// Read and parse a file
+pub fn read_document(path: &Path) -> Result<Document, String> {
+    let file =
+        std::fs::File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut bytes = Vec::new();
+    file.take(MAX_FILE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    if bytes.len() as u64 > MAX_FILE_BYTES {
+        return Err(format!(
+            "{:?} is larger than {} MiB",
+            path,
+            MAX_FILE_BYTES / (1024 * 1024)
+        ));
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    parse_document(&bytes, &name)
+}
+
+/// Parse a file's bytes. The format is sniffed from the content, falling
+/// back to the extension of `file_name`.
+pub fn parse_document(bytes: &[u8], file_name: &str) -> Result<Document, String> {
+    let format = detect_format(bytes, file_name)?;
+    let (text, metadata, links) = match format {
+        DocumentFormat::Pdf => read_pdf(bytes)?,
+        DocumentFormat::Docx | DocumentFormat::Xlsx | DocumentFormat::Pptx => {
+            read_office(bytes, format)?
+        }
+        DocumentFormat::Eml => read_email(bytes)?,
+        DocumentFormat::Csv | DocumentFormat::Text => {
+            (decode_text(bytes), DocumentMetadata::default(), Vec::new())
+        }
+    };
+
+    let mut links: Vec<String> = links
+        .into_iter()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    links.sort();
+    links.dedup();
+
+    Ok(Document {
+        format,
+        sha256: format!("{:x}", Sha256::digest(bytes)),
+        size: bytes.len() as u64,
+        text,
+        metadata,
+        links,
+    })
+}
+
+fn detect_format(bytes: &[u8], file_name: &str) -> Result<DocumentFormat, String> {
+    if bytes.starts_with(b"%PDF-") {
+        return Ok(DocumentFormat::Pdf);
+    }
+    if bytes.starts_with(b"PK\x03\x04") {
+        let archive = zip::ZipArchive::new(Cursor::new(bytes))
+            .map_err(|e| format!("Unreadable archive: {}", e))?;
+        let has = |name: &str| archive.index_for_name(name).is_some();
+        return if has("word/document.xml") {
+            Ok(DocumentFormat::Docx)
+        } else if has("xl/workbook.xml") {
+            Ok(DocumentFormat::Xlsx)
+        } else if has("ppt/presentation.xml") {
+            Ok(DocumentFormat::Pptx)
+        } else {
+            Err("Zip archive is not an Office Open XML document".to_string())
+        };
+    }
+
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "eml" => Ok(DocumentFormat::Eml),
+        "csv" | "tsv" => Ok(DocumentFormat::Csv),
+        _ if looks_like_email(bytes) => Ok(DocumentFormat::Eml),
+        "txt" | "log" | "md" | "" => Ok(DocumentFormat::Text),
+        // UTF-8, allowing a character cut off by the sample's end
+        _ if std::str::from_utf8(&bytes[..bytes.len().min(4096)])
+            .map_or_else(|e| e.error_len().is_none(), |_| true) =>
+        {
+            Ok(DocumentFormat::Text)
+        }
+        _ => Err(format!("Unsupported file type: '{}'", file_name)),
+    }
+}
+
+/// Whether the bytes open with an RFC 822 header block naming a sender
+fn looks_like_email(bytes: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(8192)]);
+    let block = head.split("\n\n").next().unwrap_or_default();
+    let header = |name: &str| {
+        block.lines().any(|l| {
+            l.get(..name.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+        })
+    };
+    header("From:") && (header("Date:") || header("Received:") || header("Message-ID:"))
+}
+
+/// UTF-8, or UTF-16 with a byte-order mark
+fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| decode([c[0], c[1]]))
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        utf16(rest, u16::from_le_bytes)
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        utf16(rest, u16::from_be_bytes)
+    } else {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+
+type Parsed = (String, DocumentMetadata, Vec<String>);
+
+fn read_pdf(bytes: &[u8]) -> Result<Parsed, String> {
+    let mut doc = lopdf::Document::load_mem(bytes).map_err(|e| format!("Unreadable PDF: {}", e))?;
+    if doc.is_encrypted() {
+        // Many PDFs are encrypted only to restrict printing or copying
+        doc.decrypt("")
+            .map_err(|_| "PDF is password protected".to_string())?;
+    }
+
+    let pages: Vec<u32> = doc.get_pages().keys().copied().collect();
+    let text = pages
+        .iter()
+        .filter_map(|&page| doc.extract_text(&[page]).ok())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut metadata = DocumentMetadata::default();
+    let info = doc
+        .trailer
+        .get(b"Info")
+        .and_then(|info| doc.dereference(info))
+        .and_then(|(_, info)| info.as_dict());
+    if let Ok(info) = info {
+        let get = |key: &[u8]| {
+            info.get(key)
+                .and_then(|value| doc.dereference(value))
+                .and_then(|(_, value)| lopdf::decode_text_string(value))
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        metadata.title = get(b"Title");
+        metadata.author = get(b"Author");
+        metadata.creator_tool = get(b"Creator");
+        metadata.producer = get(b"Producer");
+        metadata.created = get(b"CreationDate").and_then(|d| parse_pdf_date(&d));
+        metadata.modified = get(b"ModDate").and_then(|d| parse_pdf_date(&d));
+        for (key, name) in [
+            (b"Subject".as_slice(), "Subject"),
+            (b"Keywords", "Keywords"),
+        ] {
+            if let Some(value) = get(key) {
+                metadata.fields.push((name.to_string(), value));
+            }
+        }
+    }
+
+    // URI actions of link annotations
+    let links = doc
+        .objects
+        .values()
+        .filter_map(|object| object.as_dict().ok())
+        .filter_map(|dict| dict.get(b"URI").ok())
+        .filter_map(|uri| doc.dereference(uri).ok())
+        .filter_map(|(_, uri)| lopdf::decode_text_string(uri).ok())
+        .collect();
+
+    Ok((text, metadata, links))
+}
+
+/// PDF dates: `D:YYYYMMDDHHmmSSOHH'mm'`, every part after the year optional
+fn parse_pdf_date(value: &str) -> Option<NormalizedDate> {
+    let value = value.trim().trim_start_matches("D:");
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    let part = |range: std::ops::Range<usize>| digits.get(range).unwrap_or_default();
+    let iso = match digits.len() {
+        4 => part(0..4).to_string(),
+        6 => format!("{}-{}", part(0..4), part(4..6)),
+        8..=11 => format!("{}-{}-{}", part(0..4), part(4..6), part(6..8)),
+        12..=14 => {
+            let seconds = if digits.len() == 14 {
+                part(12..14)
+            } else {
+                "00"
+            };
+            let zone: String = value[digits.len()..]
+                .chars()
+                .filter(|c| *c != '\'')
+                .collect();
+            let zone = match zone.as_bytes().first() {
+                Some(b'Z') => "Z".to_string(),
+                Some(b'+' | b'-') if zone.len() >= 3 => format!(
+                    "{}:{}",
+                    &zone[..3],
+                    zone.get(3..5).filter(|m| m.len() == 2).unwrap_or("00")
+                ),
+                _ => String::new(),
+            };
+            format!(
+                "{}-{}-{}T{}:{}:{}{}",
+                part(0..4),
+                part(4..6),
+                part(6..8),
+                part(8..10),
+                part(10..12),
+                seconds,
+                zone
+            )
+        }
+        _ => return None,
+    };
+    parse_date(&iso)
+}
+
+fn read_office(bytes: &[u8], format: DocumentFormat) -> Result<Parsed, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|e| format!("Unreadable Office document: {}", e))?;
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let mut read = |name: &str| -> Option<String> {
+        let entry = archive.by_name(name).ok()?;
+        let mut xml = String::new();
+        entry.take(MAX_PART_BYTES).read_to_string(&mut xml).ok()?;
+        Some(xml)
+    };
+
+    let mut metadata = DocumentMetadata::default();
+    if let Some(xml) = read("docProps/core.xml") {
+        read_core_properties(&xml, &mut metadata);
+    }
+    if let Some(xml) = read("docProps/app.xml") {
+        read_app_properties(&xml, &mut metadata);
+    }
+
+    let is_part = |name: &str, dir: &str| {
+        name.strip_prefix(dir)
+            .is_some_and(|rest| rest.ends_with(".xml") && !rest.contains('/'))
+    };
+    let mut parts: Vec<&String> = names
+        .iter()
+        .filter(|name| match format {
+            DocumentFormat::Docx => {
+                is_part(name, "word/")
+                    && [
+                        "document",
+                        "header",
+                        "footer",
+                        "footnotes",
+                        "endnotes",
+                        "comments",
+                    ]
+                    .iter()
+                    .any(|p| name["word/".len()..].starts_with(p))
+            }
+            DocumentFormat::Xlsx => {
+                is_part(name, "xl/worksheets/") || is_part(name, "xl/") && name.contains("comments")
+            }
+            _ => {
+                is_part(name, "ppt/slides/")
+                    || is_part(name, "ppt/notesSlides/")
+                    || is_part(name, "ppt/comments/")
+            }
+        })
+        .collect();
+    // Body before headers and footers; slides and sheets in number order
+    parts.sort_by_key(|name| {
+        let number: String = name.chars().filter(char::is_ascii_digit).collect();
+        (
+            !name.contains("document"),
+            number.parse::<u32>().unwrap_or(0),
+            name.to_string(),
+        )
+    });
+
+    let shared_strings = match format {
+        DocumentFormat::Xlsx => read("xl/sharedStrings.xml")
+            .map(|xml| shared_strings(&xml))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let mut sections = Vec::new();
+    let mut comment_authors = Vec::new();
+    for name in parts {
+        let Some(xml) = read(name) else {
+            continue;
+        };
+        let Ok(doc) = roxmltree::Document::parse(&xml) else {
+            continue;
+        };
+        let text = if name.starts_with("xl/worksheets/") {
+            sheet_text(&doc, &shared_strings)
+        } else {
+            ooxml_text(doc.root())
+        };
+        if !text.trim().is_empty() {
+            sections.push(text);
+        }
+        comment_authors.extend(
+            doc.descendants()
+                .filter(|n| matches!(n.tag_name().name(), "comment" | "author" | "cmAuthor"))
+                .filter_map(|n| attribute(n, "author").or_else(|| attribute(n, "name")))
+                .chain(
+                    doc.descendants()
+                        .filter(|n| n.tag_name().name() == "author")
+                        .filter_map(|n| n.text().map(str::to_string)),
+                ),
+        );
+    }
+    comment_authors.sort();
+    comment_authors.dedup();
+    for author in comment_authors {
+        metadata.fields.push(("Comment author".to_string(), author));
+    }
+
+    // External relationships are hyperlinks (and linked images or templates)
+    let links = names
+        .iter()
+        .filter(|name| name.ends_with(".rels"))
+        .filter_map(|name| read(name))
+        .flat_map(|xml| {
+            roxmltree::Document::parse(&xml)
+                .map(|doc| {
+                    doc.descendants()
+                        .filter(|n| n.attribute("TargetMode") == Some("External"))
+                        .filter_map(|n| n.attribute("Target").map(str::to_string))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        })
+        .collect();
+
+    Ok((sections.join("\n\n"), metadata, links))
+}
+
+/// `docProps/core.xml`: Dublin Core title, creator and dates
+fn read_core_properties(xml: &str, metadata: &mut DocumentMetadata) {
+    let Ok(doc) = roxmltree::Document::parse(xml) else {
+        return;
+    };
+    for node in doc.root_element().children().filter(Node::is_element) {
+        let Some(value) = node.text().map(str::trim).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let value = value.to_string();
+        match node.tag_name().name() {
+            "title" => metadata.title = Some(value),
+            "creator" => metadata.author = Some(value),
+            "lastModifiedBy" => metadata.last_modified_by = Some(value),
+            "created" => metadata.created = parse_date(&value),
+            "modified" => metadata.modified = parse_date(&value),
+            "subject" => metadata.fields.push(("Subject".to_string(), value)),
+            "keywords" => metadata.fields.push(("Keywords".to_string(), value)),
+            "description" => metadata.fields.push(("Description".to_string(), value)),
+            "lastPrinted" => metadata.fields.push(("Last printed".to_string(), value)),
+            _ => {}
+        }
+    }
+}
+
+/// `docProps/app.xml`: the writing application and organisation
+fn read_app_properties(xml: &str, metadata: &mut DocumentMetadata) {
+    let Ok(doc) = roxmltree::Document::parse(xml) else {
+        return;
+    };
+    let get = |name: &str| {
+        doc.root_element()
+            .children()
+            .find(|n| n.tag_name().name() == name)
+            .and_then(|n| n.text())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    metadata.creator_tool = match (get("Application"), get("AppVersion")) {
+        (Some(app), Some(version)) => Some(format!("{} {}", app, version)),
+        (app, _) => app,
+    };
+    for name in ["Company", "Manager", "Template"] {
+        if let Some(value) = get(name) {
+            metadata.fields.push((name.to_string(), value));
+        }
+    }
+}
+
+/// Text runs (`w:t`, `a:t`), with paragraphs and table cells separated
+fn ooxml_text(node: Node) -> String {
+    let mut text = String::new();
+    for descendant in node.descendants() {
+        match descendant.tag_name().name() {
+            "t" | "instrText" => text.push_str(descendant.text().unwrap_or_default()),
+            "tab" => text.push('\t'),
+            "br" | "cr" => text.push('\n'),
+            "p" | "tr" if !text.is_empty() && !text.ends_with('\n') => text.push('\n'),
+            "tc" if !text.is_empty() => text.push('\t'),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// `xl/sharedStrings.xml`: the workbook's string table
+fn shared_strings(xml: &str) -> Vec<String> {
+    roxmltree::Document::parse(xml)
+        .map(|doc| {
+            doc.root_element()
+                .children()
+                .filter(|n| n.tag_name().name() == "si")
+                .map(|si| {
+                    si.descendants()
+                        .filter(|n| n.tag_name().name() == "t")
+                        .filter_map(|n| n.text())
+                        .collect()
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A worksheet's cell values, one row per line
+fn sheet_text(doc: &roxmltree::Document, shared_strings: &[String]) -> String {
+    doc.descendants()
+        .filter(|n| n.tag_name().name() == "row")
+        .map(|row| {
+            row.children()
+                .filter(|n| n.tag_name().name() == "c")
+                .filter_map(|cell| {
+                    let value = cell
+                        .children()
+                        .find(|n| n.tag_name().name() == "v")
+                        .and_then(|v| v.text());
+                    match cell.attribute("t") {
+                        Some("s") => value
+                            .and_then(|i| i.parse::<usize>().ok())
+                            .and_then(|i| shared_strings.get(i).cloned()),
+                        Some("inlineStr") => Some(ooxml_text(cell)),
+                        _ => value.map(str::to_string),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn attribute(node: Node, name: &str) -> Option<String> {
+    node.attributes()
+        .find(|a| a.name() == name)
+        .map(|a| a.value().trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn read_email(bytes: &[u8]) -> Result<Parsed, String> {
+    let message = MessageParser::default()
+        .parse(bytes)
+        .ok_or_else(|| "Unreadable email".to_string())?;
+
+    let mut metadata = DocumentMetadata {
+        title: message.subject().map(str::to_string),
+        author: message.from().and_then(|from| {
+            from.first()
+                .map(|addr| match (addr.name(), addr.address()) {
+                    (Some(name), Some(address)) => format!("{} <{}>", name, address),
+                    (name, address) => name.or(address).unwrap_or_default().to_string(),
+                })
+        }),
+        creator_tool: message
+            .header_raw("X-Mailer")
+            .or_else(|| message.header_raw("User-Agent"))
+            .map(|tool| tool.trim().to_string()),
+        created: message
+            .date()
+            .and_then(|date| parse_date(&date.to_rfc3339())),
+        ..Default::default()
+    };
+    for (name, value) in message.headers_raw() {
+        if let Some(field) = EMAIL_FIELDS.iter().find(|f| f.eq_ignore_ascii_case(name)) {
+            let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            metadata.fields.push((field.to_string(), value));
+        }
+    }
+    for attachment in message.attachments() {
+        if let Some(name) = attachment.attachment_name() {
+            metadata
+                .fields
+                .push(("Attachment".to_string(), name.to_string()));
+        }
+    }
+
+    let text = (0..message.text_body_count())
+        .filter_map(|i| message.body_text(i))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    // Anchors in HTML parts, where the link target is often not the text
+    let anchor = Selector::parse("a[href]").expect("static selector");
+    let links = (0..message.html_body_count())
+        .filter_map(|i| message.body_html(i))
+        .flat_map(|html| {
+            Html::parse_document(&html)
+                .select(&anchor)
+                .filter_map(|a| a.value().attr("href"))
+                .filter(|href| {
+                    let href = href.to_lowercase();
+                    href.starts_with("http:")
+                        || href.starts_with("https:")
+                        || href.starts_with("mailto:")
+                })
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    Ok((text, metadata, links))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn office(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_docx_text_metadata_and_links() {
+        let bytes = office(&[
+            (
+                "word/document.xml",
+                r#"<w:document xmlns:w="w"><w:body>
+                    <w:p><w:r><w:t>Contact </w:t></w:r><w:r><w:t>ops@leak.example</w:t></w:r></w:p>
+                    <w:p><w:r><w:t>Wallet below</w:t></w:r></w:p>
+                </w:body></w:document>"#,
+            ),
+            (
+                "word/_rels/document.xml.rels",
+                r#"<Relationships><Relationship Id="r1" TargetMode="External" Target="https://hidden.example/drop"/></Relationships>"#,
+            ),
+            (
+                "docProps/core.xml",
+                r#"<cp:coreProperties xmlns:cp="cp" xmlns:dc="dc" xmlns:dcterms="dcterms">
+                    <dc:creator>J. Doe</dc:creator><cp:lastModifiedBy>admin</cp:lastModifiedBy>
+                    <dcterms:created>2023-04-01T09:30:00Z</dcterms:created>
+                </cp:coreProperties>"#,
+            ),
+            (
+                "docProps/app.xml",
+                r#"<Properties><Application>Microsoft Office Word</Application><AppVersion>16.0000</AppVersion><Company>Acme</Company></Properties>"#,
+            ),
+        ]);
+        let doc = parse_document(&bytes, "leak.docx").unwrap();
+        assert_eq!(doc.format, DocumentFormat::Docx);
+        assert_eq!(doc.text, "Contact ops@leak.example\nWallet below");
+        assert_eq!(doc.metadata.author.as_deref(), Some("J. Doe"));
+        assert_eq!(doc.metadata.last_modified_by.as_deref(), Some("admin"));
+        assert_eq!(
+            doc.metadata.creator_tool.as_deref(),
+            Some("Microsoft Office Word 16.0000")
+        );
+        assert_eq!(
+            doc.metadata.created.as_ref().unwrap().value,
+            "2023-04-01T09:30:00Z"
+        );
+        assert_eq!(doc.links, vec!["https://hidden.example/drop"]);
+        assert_eq!(doc.sha256.len(), 64);
+        assert!(doc.extraction_text().contains("Company: Acme"));
+    }
+
+    #[test]
+    fn test_xlsx_cells() {
+        let bytes = office(&[
+            ("xl/workbook.xml", "<workbook/>"),
+            (
+                "xl/sharedStrings.xml",
+                "<sst><si><t>name</t></si><si><t>bob@corp.example</t></si></sst>",
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet><sheetData>
+                    <row><c t="s"><v>0</v></c><c><v>42</v></c></row>
+                    <row><c t="s"><v>1</v></c><c t="inlineStr"><is><t>inline</t></is></c></row>
+                </sheetData></worksheet>"#,
+            ),
+        ]);
+        let doc = parse_document(&bytes, "book.xlsx").unwrap();
+        assert_eq!(doc.format, DocumentFormat::Xlsx);
+        assert_eq!(doc.text, "name\t42\nbob@corp.example\tinline");
+    }
+
+    #[test]
+    fn test_email_headers_and_anchors() {
+        let eml = "From: Alice <alice@sender.example>\r\n\
+                   To: bob@target.example\r\n\
+                   Subject: Invoice\r\n\
+                   Date: Tue, 5 Mar 2024 14:30:00 +0100\r\n\
+                   Received: from mail.sender.example ([203.0.113.9]) by mx.target.example\r\n\
+                   X-Mailer: PhishKit 2\r\n\
+                   MIME-Version: 1.0\r\n\
+                   Content-Type: text/html; charset=utf-8\r\n\
+                   \r\n\
+                   <p>Pay <a href=\"https://pay.evil.example/x\">here</a></p>\r\n";
+        let doc = parse_document(eml.as_bytes(), "message").unwrap();
+        assert_eq!(doc.format, DocumentFormat::Eml);
+        assert_eq!(doc.metadata.title.as_deref(), Some("Invoice"));
+        assert_eq!(
+            doc.metadata.author.as_deref(),
+            Some("Alice <alice@sender.example>")
+        );
+        assert_eq!(doc.metadata.creator_tool.as_deref(), Some("PhishKit 2"));
+        assert_eq!(
+            doc.metadata.created.as_ref().unwrap().value,
+            "2024-03-05T13:30:00Z"
+        );
+        assert!(doc
+            .metadata
+            .fields
+            .iter()
+            .any(|(name, value)| name == "Received" && value.contains("203.0.113.9")));
+        assert_eq!(doc.links, vec!["https://pay.evil.example/x"]);
+    }
+
+    #[test]
+    fn test_pdf_dates_and_text_formats() {
+        assert_eq!(
+            parse_pdf_date("D:20230401093000+02'00'").unwrap().value,
+            "2023-04-01T07:30:00Z"
+        );
+        assert_eq!(parse_pdf_date("D:20230401").unwrap().value, "2023-04-01");
+        assert!(parse_pdf_date("garbage").is_none());
+
+        let csv = parse_document(b"name,email\nbob,bob@corp.example\n", "list.csv").unwrap();
+        assert_eq!(csv.format, DocumentFormat::Csv);
+        let utf16: Vec<u8> = b"\xFF\xFE"
+            .iter()
+            .copied()
+            .chain("hi".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_eq!(parse_document(&utf16, "notes.txt").unwrap().text, "hi");
+        assert!(parse_document(b"PK\x03\x04junk", "x.zip").is_err());
+    }
+}
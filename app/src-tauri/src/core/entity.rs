@@ -3,6 +3,7 @@
 //! Entities are pieces of intelligence discovered during OSINT investigations.
 //! The Hivemind tracks and correlates entities across all identities.
 
+use crate::core::normalize::canonicalize;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
@@ -27,6 +28,27 @@ pub enum EntityType {
     Coordinate,
     MacAddress,
     Uuid,
+    Iban,
+    MoneroAddress,
+    LitecoinAddress,
+    TronAddress,
+    SolanaAddress,
+    /// Tor v3 onion service address
+    OnionAddress,
+    /// Autonomous system number
+    Asn,
+    Cidr,
+    Cve,
+    Md5Hash,
+    Sha1Hash,
+    Sha256Hash,
+    Jwt,
+    AwsAccessKey,
+    GithubToken,
+    SlackToken,
+    PgpFingerprint,
+    TelegramHandle,
+    DiscordInvite,
     Custom(String),
 }
 
@@ -49,6 +71,22 @@ impl EntityType {
             EntityType::Coordinate => "map-pin",
             EntityType::MacAddress => "wifi",
             EntityType::Uuid => "key",
+            EntityType::Iban => "landmark",
+            EntityType::MoneroAddress
+            | EntityType::LitecoinAddress
+            | EntityType::TronAddress
+            | EntityType::SolanaAddress => "wallet",
+            EntityType::OnionAddress => "eye-off",
+            EntityType::Asn | EntityType::Cidr => "network",
+            EntityType::Cve => "bug",
+            EntityType::Md5Hash | EntityType::Sha1Hash | EntityType::Sha256Hash => "binary",
+            EntityType::Jwt
+            | EntityType::AwsAccessKey
+            | EntityType::GithubToken
+            | EntityType::SlackToken => "key-round",
+            EntityType::PgpFingerprint => "fingerprint",
+            EntityType::TelegramHandle => "send",
+            EntityType::DiscordInvite => "message-circle",
             EntityType::Custom(_) => "file",
         }
     }
@@ -72,6 +110,25 @@ impl EntityType {
             EntityType::Coordinate => "Coordinate".to_string(),
             EntityType::MacAddress => "MAC Address".to_string(),
             EntityType::Uuid => "UUID".to_string(),
+            EntityType::Iban => "IBAN".to_string(),
+            EntityType::MoneroAddress => "Monero".to_string(),
+            EntityType::LitecoinAddress => "Litecoin".to_string(),
+            EntityType::TronAddress => "Tron".to_string(),
+            EntityType::SolanaAddress => "Solana".to_string(),
+            EntityType::OnionAddress => "Onion Address".to_string(),
+            EntityType::Asn => "ASN".to_string(),
+            EntityType::Cidr => "CIDR Range".to_string(),
+            EntityType::Cve => "CVE".to_string(),
+            EntityType::Md5Hash => "MD5".to_string(),
+            EntityType::Sha1Hash => "SHA-1".to_string(),
+            EntityType::Sha256Hash => "SHA-256".to_string(),
+            EntityType::Jwt => "JWT".to_string(),
+            EntityType::AwsAccessKey => "AWS Access Key".to_string(),
+            EntityType::GithubToken => "GitHub Token".to_string(),
+            EntityType::SlackToken => "Slack Token".to_string(),
+            EntityType::PgpFingerprint => "PGP Fingerprint".to_string(),
+            EntityType::TelegramHandle => "Telegram".to_string(),
+            EntityType::DiscordInvite => "Discord Invite".to_string(),
             EntityType::Custom(name) => name.clone(),
         }
     }
@@ -89,10 +146,66 @@ pub struct EntitySource {
     /// Surrounding context
     pub context: Option<String>,
 
+    /// Where the match sits in the source text and in `context`
+    #[serde(default)]
+    pub position: Option<SourcePosition>,
+
+    /// Whether the value was written defanged (`hxxps://evil[.]com`) here
+    #[serde(default)]
+    pub defanged: bool,
+
+    /// Extraction confidence (0-100); none for manual additions
+    #[serde(default)]
+    pub confidence: Option<u8>,
+
+    /// SHA-256 of the ingested file the value was found in
+    #[serde(default)]
+    pub file_sha256: Option<String>,
+
     /// Discovery timestamp
     pub timestamp: DateTime<Utc>,
 }
 
+impl EntitySource {
+    /// A sighting by `identity_id` now, with nothing else known about it
+    pub fn new(identity_id: impl Into<String>) -> Self {
+        Self {
+            identity_id: identity_id.into(),
+            url: None,
+            context: None,
+            position: None,
+            defanged: false,
+            confidence: None,
+            file_sha256: None,
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Byte range within a text
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl TextSpan {
+    pub fn contains(&self, other: &TextSpan) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+}
+
+/// Location of a sighting, for showing the snippet with the match highlighted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourcePosition {
+    /// Match offsets in the text it was extracted from
+    pub span: TextSpan,
+    /// Match offsets within the source's `context`
+    pub highlight: TextSpan,
+    /// XPath of the page element holding the match
+    pub locator: Option<String>,
+}
+
 /// An entity in the Hivemind
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entity {
@@ -102,9 +215,13 @@ pub struct Entity {
     /// Type of entity
     pub entity_type: EntityType,
 
-    /// The entity value
+    /// The entity value, in canonical form
     pub value: String,
 
+    /// Raw forms the value was seen in before canonicalisation
+    #[serde(default)]
+    pub aliases: Vec<String>,
+
     /// All sources that found this entity
     pub sources: Vec<EntitySource>,
 
@@ -128,15 +245,21 @@ pub struct Entity {
 }
 
 impl Entity {
-    /// Create a new entity
+    /// Create a new entity from a raw value, keeping the raw form as an alias
+    /// when it differs from the canonical one. Domains, emails and URLs are
+    /// also tagged with what their hostname's form gives away (see [`idn`]).
+    ///
+    /// [`idn`]: crate::core::idn
     pub fn new(entity_type: EntityType, value: String, source: EntitySource) -> Self {
-        let hash = Self::compute_hash(&entity_type, &value);
+        let canonical = canonicalize(&entity_type, &value);
+        let hash = Self::compute_hash(&entity_type, &canonical);
         let now = Utc::now();
 
-        Self {
+        let mut entity = Self {
             hash,
             entity_type,
-            value,
+            value: canonical,
+            aliases: vec![],
             sources: vec![source],
             first_seen: now,
             last_seen: now,
@@ -144,17 +267,55 @@ impl Entity {
             risk_score: None,
             tags: vec![],
             notes: None,
-        }
+        };
+        entity.add_alias(&value);
+        crate::core::idn::annotate(&mut entity);
+        entity
     }
 
-    /// Compute the unique hash for an entity
-    pub fn compute_hash(entity_type: &EntityType, value: &str) -> String {
+    /// Compute the unique hash for an entity from its canonical value, as
+    /// stored in `value`. The value is not re-canonicalised, so a custom
+    /// entity keeps its hash when its rule is edited or deleted.
+    pub fn compute_hash(entity_type: &EntityType, canonical: &str) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(format!("{:?}:{}", entity_type, value.to_lowercase()).as_bytes());
+        hasher.update(format!("{:?}:{}", entity_type, canonical).as_bytes());
         let result = hasher.finalize();
         base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, &result[..16])
     }
 
+    /// Remember a raw form of the value, unless it is the canonical value or
+    /// already known
+    pub fn add_alias(&mut self, raw: &str) {
+        let raw = raw.trim();
+        if raw != self.value && !self.aliases.iter().any(|a| a == raw) {
+            self.aliases.push(raw.to_string());
+        }
+    }
+
+    /// Fold another record of the same canonical entity into this one
+    pub fn absorb(&mut self, other: Entity) {
+        self.add_alias(&other.value);
+        for alias in &other.aliases {
+            self.add_alias(alias);
+        }
+        self.sources.extend(other.sources);
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+        self.occurrence_count += other.occurrence_count;
+        self.risk_score = self.risk_score.max(other.risk_score);
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self.notes = match (self.notes.take(), other.notes) {
+            (Some(mine), Some(theirs)) if mine != theirs => {
+                Some(format!("{}\n\n{}", mine, theirs))
+            }
+            (mine, theirs) => mine.or(theirs),
+        };
+    }
+
     /// Get unique identity IDs that found this entity
     pub fn unique_sources(&self) -> Vec<String> {
         self.sources
@@ -165,6 +326,12 @@ impl Entity {
             .collect()
     }
 
+    /// Whether any source wrote this entity defanged. Defanged entities come
+    /// from threat reporting and are never opened without the user asking.
+    pub fn is_defanged(&self) -> bool {
+        self.sources.iter().any(|s| s.defanged)
+    }
+
     /// Check if this is a cross-reference (found by multiple identities)
     pub fn is_cross_reference(&self) -> bool {
         self.unique_sources().len() > 1
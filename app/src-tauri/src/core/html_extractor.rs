@@ -0,0 +1,603 @@
+//! HTML Entity Extraction
+//!
+//! Pulls entities out of page markup that text extraction never sees:
+//! `mailto:` and `tel:` links, `rel="me"` profile links, author, Twitter and
+//! OpenGraph `<meta>` tags, and JSON-LD `schema.org` Person and Organization
+//! objects.
+//!
+//! Structured sources become [`StructuredRecord`]s, and every entity read
+//! from one points back at it, so a JSON-LD Person's email and `sameAs`
+//! profiles arrive linked to each other (and to the Organization it works
+//! for).
+
+use crate::core::entity::EntityType;
+use crate::core::normalize::canonicalize;
+use crate::core::validators::{validate, ValidationStatus};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+/// How deep JSON-LD objects are followed
+const MAX_JSON_LD_DEPTH: usize = 16;
+
+/// schema.org types read as organisations
+const ORGANIZATION_TYPES: &[&str] = &[
+    "Organization",
+    "Corporation",
+    "LocalBusiness",
+    "NGO",
+    "EducationalOrganization",
+    "GovernmentOrganization",
+    "NewsMediaOrganization",
+    "OnlineBusiness",
+];
+
+/// JSON-LD properties that hold related people or organisations
+const RELATED_PROPERTIES: &[&str] = &[
+    "founder",
+    "employee",
+    "member",
+    "members",
+    "worksFor",
+    "affiliation",
+    "memberOf",
+    "parentOrganization",
+    "subOrganization",
+    "alumniOf",
+];
+
+/// An entity read from markup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkupEntity {
+    pub entity_type: EntityType,
+    /// Canonical value, as hashed into the Hivemind
+    pub value: String,
+    /// The value as it appeared in the markup
+    pub raw: String,
+    pub validation: ValidationStatus,
+    /// Where it was found, e.g. `a[href^=mailto:]` or `json-ld Person.sameAs`
+    pub origin: String,
+    /// The record it belongs to, as an index into [`HtmlExtraction::records`]
+    pub record: Option<usize>,
+    /// The record field it came from (`email`, `sameAs`, ...)
+    pub field: Option<String>,
+}
+
+/// A structured object on the page whose entities belong together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredRecord {
+    /// `Person`, `Organization`, `Profile` (the page owner, from OpenGraph
+    /// and `rel="me"`) or `Author`
+    pub kind: String,
+    pub name: Option<String>,
+    /// `json-ld`, `opengraph`, `rel-me` or `meta`
+    pub origin: String,
+    /// The record this one was nested in, e.g. the Organization a Person
+    /// was listed as `employee` of
+    pub parent: Option<usize>,
+}
+
+/// Everything found in one document
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HtmlExtraction {
+    pub entities: Vec<MarkupEntity>,
+    pub records: Vec<StructuredRecord>,
+}
+
+impl HtmlExtraction {
+    fn add_record(&mut self, kind: &str, name: Option<String>, origin: &str) -> usize {
+        self.records.push(StructuredRecord {
+            kind: kind.to_string(),
+            name: name.filter(|n| !n.trim().is_empty()),
+            origin: origin.to_string(),
+            parent: None,
+        });
+        self.records.len() - 1
+    }
+
+    /// Validate and canonicalise `raw`, keeping it unless invalid or already
+    /// recorded for the same record
+    fn add(
+        &mut self,
+        entity_type: EntityType,
+        raw: &str,
+        origin: &str,
+        record: Option<usize>,
+        field: Option<&str>,
+    ) {
+        let raw = raw.trim();
+        if raw.is_empty() || (entity_type == EntityType::Email && !raw.contains('@')) {
+            return;
+        }
+        let validation = validate(&entity_type, raw);
+        if validation == ValidationStatus::Invalid {
+            return;
+        }
+        let value = canonicalize(&entity_type, raw);
+        let duplicate = self
+            .entities
+            .iter()
+            .any(|e| e.entity_type == entity_type && e.value == value && e.record == record);
+        if duplicate {
+            return;
+        }
+        self.entities.push(MarkupEntity {
+            entity_type,
+            value,
+            raw: raw.to_string(),
+            validation,
+            origin: origin.to_string(),
+            record,
+            field: field.map(str::to_string),
+        });
+    }
+}
+
+/// Extract entities from an HTML document. `page_url` resolves relative
+/// links.
+pub fn extract_html(html: &str, page_url: Option<&str>) -> HtmlExtraction {
+    let document = Html::parse_document(html);
+    let base = page_url.and_then(|u| Url::parse(u).ok());
+    let mut out = HtmlExtraction::default();
+
+    extract_links(&document, base.as_ref(), &mut out);
+    extract_meta(&document, base.as_ref(), &mut out);
+    extract_json_ld(&document, base.as_ref(), &mut out);
+
+    out
+}
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("static selector")
+}
+
+/// `mailto:`/`tel:` hrefs anywhere, and `rel="me"` links as the page owner's
+/// profiles
+fn extract_links(document: &Html, base: Option<&Url>, out: &mut HtmlExtraction) {
+    let mut owner: Option<usize> = None;
+    for element in document.select(&selector("a[href], link[href]")) {
+        let Some(href) = element.value().attr("href").map(str::trim) else {
+            continue;
+        };
+        let (scheme, rest) = href.split_once(':').unwrap_or_default();
+        match scheme.to_lowercase().as_str() {
+            "mailto" => {
+                let addresses = rest.split('?').next().unwrap_or_default();
+                for address in percent_decode(addresses).split(',') {
+                    out.add(EntityType::Email, address, "a[href^=mailto:]", None, None);
+                }
+            }
+            "tel" => {
+                let number = rest.split(';').next().unwrap_or_default();
+                out.add(
+                    EntityType::Phone,
+                    &percent_decode(number),
+                    "a[href^=tel:]",
+                    None,
+                    None,
+                );
+            }
+            _ => {
+                let is_me = element.value().attr("rel").is_some_and(|rel| {
+                    rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("me"))
+                });
+                if let (true, Some(url)) = (is_me, resolve(href, base)) {
+                    let record =
+                        *owner.get_or_insert_with(|| out.add_record("Profile", None, "rel-me"));
+                    out.add(EntityType::Url, &url, "a[rel=me]", Some(record), Some("me"));
+                }
+            }
+        }
+    }
+}
+
+/// Author, Twitter and OpenGraph `<meta>` tags
+fn extract_meta(document: &Html, base: Option<&Url>, out: &mut HtmlExtraction) {
+    let mut tags: Vec<(String, String)> = Vec::new();
+    for element in document.select(&selector("meta[content]")) {
+        let el = element.value();
+        let Some(key) = el.attr("property").or_else(|| el.attr("name")) else {
+            continue;
+        };
+        let content = el.attr("content").unwrap_or_default().trim();
+        if !content.is_empty() {
+            tags.push((key.trim().to_lowercase(), content.to_string()));
+        }
+    }
+    let get = |key: &str| tags.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+    // The page author
+    let author = get("author");
+    let creator = get("twitter:creator");
+    let article_author = get("article:author");
+    if author.is_some() || creator.is_some() || article_author.is_some() {
+        let name = author.filter(|a| !a.contains('@') && resolve(a, None).is_none());
+        let record = out.add_record("Author", name.map(str::to_string), "meta");
+        if let Some(author) = author {
+            add_handle_or_contact(out, author, "meta[name=author]", record);
+        }
+        if let Some(creator) = creator {
+            add_handle_or_contact(out, creator, "meta[name=twitter:creator]", record);
+        }
+        if let Some(url) = article_author.and_then(|a| resolve(a, base)) {
+            out.add(
+                EntityType::Url,
+                &url,
+                "meta[property=article:author]",
+                Some(record),
+                Some("url"),
+            );
+        }
+    }
+
+    // An OpenGraph profile describes the page owner
+    let is_profile = get("og:type").is_some_and(|t| t.eq_ignore_ascii_case("profile"))
+        || tags.iter().any(|(k, _)| k.starts_with("profile:"));
+    if is_profile {
+        let full_name = match (get("profile:first_name"), get("profile:last_name")) {
+            (Some(first), Some(last)) => Some(format!("{} {}", first, last)),
+            (first, last) => first.or(last).or(get("og:title")).map(str::to_string),
+        };
+        let record = out.add_record("Profile", full_name, "opengraph");
+        if let Some(username) = get("profile:username") {
+            out.add(
+                EntityType::Username,
+                &as_handle(username),
+                "meta[property=profile:username]",
+                Some(record),
+                Some("username"),
+            );
+        }
+        if let Some(url) = get("og:url").and_then(|u| resolve(u, base)) {
+            out.add(
+                EntityType::Url,
+                &url,
+                "meta[property=og:url]",
+                Some(record),
+                Some("url"),
+            );
+        }
+    }
+
+    if let Some(email) = get("og:email") {
+        out.add(
+            EntityType::Email,
+            email,
+            "meta[property=og:email]",
+            None,
+            None,
+        );
+    }
+    if let Some(phone) = get("og:phone_number") {
+        out.add(
+            EntityType::Phone,
+            phone,
+            "meta[property=og:phone_number]",
+            None,
+            None,
+        );
+    }
+    if let Some(site) = get("twitter:site") {
+        out.add(
+            EntityType::Username,
+            &as_handle(site),
+            "meta[name=twitter:site]",
+            None,
+            None,
+        );
+    }
+}
+
+/// An author field may hold an email, a URL or an `@handle`
+fn add_handle_or_contact(out: &mut HtmlExtraction, value: &str, origin: &str, record: usize) {
+    if value.contains('@') && !value.starts_with('@') {
+        out.add(
+            EntityType::Email,
+            value,
+            origin,
+            Some(record),
+            Some("email"),
+        );
+    } else if value.starts_with('@') {
+        out.add(
+            EntityType::Username,
+            value,
+            origin,
+            Some(record),
+            Some("username"),
+        );
+    } else if let Some(url) = resolve(value, None) {
+        out.add(EntityType::Url, &url, origin, Some(record), Some("url"));
+    }
+}
+
+fn extract_json_ld(document: &Html, base: Option<&Url>, out: &mut HtmlExtraction) {
+    for script in document.select(&selector(r#"script[type="application/ld+json"]"#)) {
+        let json: String = script.text().collect();
+        match serde_json::from_str::<Value>(&json) {
+            Ok(value) => walk_json_ld(&value, None, base, out, 0),
+            Err(e) => tracing::debug!("Skipping malformed JSON-LD block: {}", e),
+        }
+    }
+}
+
+fn walk_json_ld(
+    value: &Value,
+    parent: Option<usize>,
+    base: Option<&Url>,
+    out: &mut HtmlExtraction,
+    depth: usize,
+) {
+    if depth > MAX_JSON_LD_DEPTH {
+        return;
+    }
+    let object = match value {
+        Value::Array(items) => {
+            for item in items {
+                walk_json_ld(item, parent, base, out, depth + 1);
+            }
+            return;
+        }
+        Value::Object(object) => object,
+        _ => return,
+    };
+
+    let Some(kind) = schema_kind(object) else {
+        // Not a person or organisation, but may contain some (an Article's
+        // author, a WebPage's @graph)
+        for child in object.values() {
+            walk_json_ld(child, parent, base, out, depth + 1);
+        }
+        return;
+    };
+
+    let name = object
+        .get("name")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let record = out.add_record(kind, name, "json-ld");
+    out.records[record].parent = parent;
+    add_contact_fields(object, kind, record, base, out);
+
+    if let Some(points) = object.get("contactPoint") {
+        for point in as_objects(points) {
+            add_contact_fields(point, kind, record, base, out);
+        }
+    }
+    for property in RELATED_PROPERTIES {
+        if let Some(related) = object.get(*property) {
+            walk_json_ld(related, Some(record), base, out, depth + 1);
+        }
+    }
+}
+
+fn add_contact_fields(
+    object: &Map<String, Value>,
+    kind: &str,
+    record: usize,
+    base: Option<&Url>,
+    out: &mut HtmlExtraction,
+) {
+    let fields: [(&str, EntityType); 5] = [
+        ("email", EntityType::Email),
+        ("telephone", EntityType::Phone),
+        ("faxNumber", EntityType::Phone),
+        ("url", EntityType::Url),
+        ("sameAs", EntityType::Url),
+    ];
+    for (field, entity_type) in fields {
+        let origin = format!("json-ld {}.{}", kind, field);
+        for raw in object.get(field).map(as_strings).unwrap_or_default() {
+            let raw = match entity_type {
+                EntityType::Email => raw.strip_prefix("mailto:").unwrap_or(&raw).to_string(),
+                EntityType::Url => match resolve(&raw, base) {
+                    Some(url) => url,
+                    None => continue,
+                },
+                _ => raw,
+            };
+            out.add(
+                entity_type.clone(),
+                &raw,
+                &origin,
+                Some(record),
+                Some(field),
+            );
+        }
+    }
+}
+
+/// `Person`, `Organization` or neither, from `@type` (string or array,
+/// possibly a full schema.org IRI)
+fn schema_kind(object: &Map<String, Value>) -> Option<&'static str> {
+    let types = object.get("@type").map(as_strings).unwrap_or_default();
+    let types: Vec<&str> = types
+        .iter()
+        .map(|t| t.rsplit(['/', '#', ':']).next().unwrap_or(t))
+        .collect();
+    if types.contains(&"Person") {
+        Some("Person")
+    } else if types.iter().any(|t| ORGANIZATION_TYPES.contains(t)) {
+        Some("Organization")
+    } else {
+        None
+    }
+}
+
+/// A JSON-LD value as strings: a string, an array of strings, or an `@id`
+fn as_strings(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items.iter().flat_map(as_strings).collect(),
+        Value::Object(object) => object
+            .get("@id")
+            .and_then(Value::as_str)
+            .map(|id| vec![id.to_string()])
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+fn as_objects(value: &Value) -> Vec<&Map<String, Value>> {
+    match value {
+        Value::Object(object) => vec![object],
+        Value::Array(items) => items.iter().filter_map(Value::as_object).collect(),
+        _ => vec![],
+    }
+}
+
+/// Absolute http(s) URL for `href`, resolved against `base` if relative
+fn resolve(href: &str, base: Option<&Url>) -> Option<String> {
+    let url = match Url::parse(href) {
+        Ok(url) => url,
+        Err(url::ParseError::RelativeUrlWithoutBase) => base?.join(href).ok()?,
+        Err(_) => return None,
+    };
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+fn as_handle(value: &str) -> String {
+    let value = value.trim();
+    if value.starts_with('@') {
+        value.to_string()
+    } else {
+        format!("@{}", value)
+    }
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values_of(out: &HtmlExtraction, record: Option<usize>) -> Vec<String> {
+        out.entities
+            .iter()
+            .filter(|e| e.record == record)
+            .map(|e| e.value.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_links_and_meta() {
+        let html = r#"<html><head>
+            <meta name="author" content="Jane Doe">
+            <meta name="twitter:creator" content="@janedoe">
+            <link rel="me" href="https://mastodon.social/@jane">
+            </head><body>
+            <a href="mailto:Jane%40Example.com?subject=hi">mail</a>
+            <a href="tel:+1-555-123-4567">call</a>
+            <a rel="me nofollow" href="/about">about</a>
+            </body></html>"#;
+        let out = extract_html(html, Some("https://jane.example/blog/post"));
+
+        assert_eq!(
+            values_of(&out, None),
+            vec!["jane@example.com", "+15551234567"]
+        );
+        let profile = out
+            .records
+            .iter()
+            .position(|r| r.kind == "Profile")
+            .unwrap();
+        assert_eq!(
+            values_of(&out, Some(profile)),
+            vec![
+                "https://mastodon.social/@jane",
+                "https://jane.example/about"
+            ]
+        );
+        let author = out.records.iter().position(|r| r.kind == "Author").unwrap();
+        assert_eq!(out.records[author].name.as_deref(), Some("Jane Doe"));
+        assert_eq!(values_of(&out, Some(author)), vec!["@janedoe"]);
+    }
+
+    #[test]
+    fn test_json_ld_person_arrives_linked() {
+        let html = r#"<script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [{
+                "@type": "Person",
+                "name": "Jane Doe",
+                "email": "mailto:jane@example.com",
+                "sameAs": ["https://twitter.com/janedoe", "https://github.com/janedoe"],
+                "worksFor": {
+                    "@type": "Corporation",
+                    "name": "Acme",
+                    "url": "https://acme.example",
+                    "contactPoint": {"@type": "ContactPoint", "telephone": "+1 555 123 4567"}
+                }
+            }]}
+            </script>
+            <script type="application/ld+json">{ not json</script>"#;
+        let out = extract_html(html, None);
+
+        assert_eq!(out.records.len(), 2);
+        let person = &out.records[0];
+        assert_eq!(
+            (person.kind.as_str(), person.name.as_deref()),
+            ("Person", Some("Jane Doe"))
+        );
+        assert_eq!(
+            values_of(&out, Some(0)),
+            vec![
+                "jane@example.com",
+                "https://twitter.com/janedoe",
+                "https://github.com/janedoe"
+            ]
+        );
+
+        let org = &out.records[1];
+        assert_eq!(org.kind, "Organization");
+        assert_eq!(org.parent, Some(0));
+        assert_eq!(
+            values_of(&out, Some(1)),
+            vec!["https://acme.example", "+15551234567"]
+        );
+        let same_as = out
+            .entities
+            .iter()
+            .filter(|e| e.field.as_deref() == Some("sameAs"));
+        assert_eq!(same_as.count(), 2);
+    }
+
+    #[test]
+    fn test_opengraph_profile() {
+        let html = r#"<meta property="og:type" content="profile">
+            <meta property="profile:first_name" content="Jane">
+            <meta property="profile:last_name" content="Doe">
+            <meta property="profile:username" content="JaneDoe">
+            <meta property="og:url" content="https://social.example/janedoe">"#;
+        let out = extract_html(html, None);
+
+        assert_eq!(out.records.len(), 1);
+        assert_eq!(out.records[0].name.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            values_of(&out, Some(0)),
+            vec!["@janedoe", "https://social.example/janedoe"]
+        );
+    }
+}
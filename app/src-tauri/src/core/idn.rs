@@ -0,0 +1,347 @@
+//! Internationalised Domains
+//!
+//! Domains are hashed in their ASCII (punycode) form; this module recovers
+//! the Unicode form analysts read, and flags the tricks look-alike domains
+//! use: labels mixing scripts (`pаypal` with a Cyrillic `а`), characters
+//! confusable with ASCII letters, and names that imitate a brand on the
+//! vault's watch list.
+//!
+//! Confusables are compared through UTS #39 skeletons: each character is
+//! replaced by its prototype from the full Unicode confusables data (as
+//! shipped in `unicode-security`), so two strings that render alike share a
+//! skeleton. Skeletons are folded to lowercase afterwards, since domains are
+//! case-insensitive and some prototypes are capitals (`0` → `O`).
+
+use crate::core::confidence::{host_of, is_within};
+use crate::core::entity::{Entity, EntityType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::sync::RwLock;
+use unicode_script::{Script, UnicodeScript};
+use url::{quirks::domain_to_unicode, Host};
+
+/// Store setting holding the [`BrandWatchList`]
+pub const WATCH_LIST_SETTING: &str = "brand_watch_list";
+
+/// Shortest brand name matched inside longer labels (`paypal-login`) or
+/// one edit away (`paypall`); shorter names would match too much
+const MIN_FUZZY_BRAND_LEN: usize = 5;
+
+/// Watched brand domains of the open vault
+static WATCHED: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Script combinations a single label may use (UTS #39 "highly
+/// restrictive"): one script, or Latin with the scripts written alongside it
+const ALLOWED_SCRIPT_MIXES: &[&[Script]] = &[
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+/// The persisted list of brands to watch for look-alikes of
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BrandWatchList {
+    /// Protected domains, e.g. `paypal.com`
+    #[serde(default)]
+    pub brands: Vec<String>,
+}
+
+/// What a domain's form says about it
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DomainAnalysis {
+    /// ASCII (punycode) form
+    pub ascii: String,
+    /// Unicode form, as displayed
+    pub unicode: String,
+    /// Scripts of its letters, excluding common characters
+    pub scripts: Vec<String>,
+    /// A label mixes scripts that are not normally written together
+    pub mixed_script: bool,
+    /// Contains non-ASCII characters that render like ASCII ones
+    pub confusable: bool,
+    /// Watched brands it imitates
+    pub looks_like: Vec<String>,
+}
+
+impl DomainAnalysis {
+    pub fn is_idn(&self) -> bool {
+        self.ascii != self.unicode
+    }
+
+    /// Entity tags for the findings
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags = Vec::new();
+        if self.is_idn() {
+            tags.push("idn".to_string());
+        }
+        if self.mixed_script {
+            tags.push("mixed-script".to_string());
+        }
+        if self.confusable {
+            tags.push("confusable".to_string());
+        }
+        for brand in &self.looks_like {
+            tags.push(format!("looks-like:{}", brand));
+        }
+        tags
+    }
+}
+
+impl BrandWatchList {
+    /// Canonical brand domains, rejecting anything that is not a domain
+    pub fn normalized(&self) -> Result<Vec<String>, String> {
+        let mut brands: Vec<String> = Vec::new();
+        for brand in &self.brands {
+            let domain = match Host::parse(brand.trim().trim_end_matches('.')) {
+                Ok(Host::Domain(domain)) if domain.contains('.') => domain,
+                _ => return Err(format!("'{}' is not a domain", brand)),
+            };
+            let domain = domain.strip_prefix("www.").unwrap_or(&domain).to_string();
+            if !brands.contains(&domain) {
+                brands.push(domain);
+            }
+        }
+        Ok(brands)
+    }
+}
+
+/// UTS #39 skeleton, lowercased: every character replaced by its
+/// confusable prototype
+pub fn skeleton(text: &str) -> String {
+    unicode_security::skeleton(text)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// A non-ASCII character that renders like ASCII text
+fn is_ascii_confusable(c: char) -> bool {
+    !c.is_ascii() && skeleton(c.encode_utf8(&mut [0; 4])).is_ascii()
+}
+
+/// Unicode form of an ASCII domain (unchanged if it has no `xn--` labels)
+pub fn to_unicode(domain: &str) -> String {
+    if domain.split('.').any(|label| label.starts_with("xn--")) {
+        domain_to_unicode(domain)
+    } else {
+        domain.to_string()
+    }
+}
+
+/// Analyse a domain against `brands` (canonical domains)
+pub fn analyze_with(domain: &str, brands: &[String]) -> DomainAnalysis {
+    let ascii = match Host::parse(domain.trim().trim_end_matches('.')) {
+        Ok(Host::Domain(ascii)) => ascii,
+        _ => domain.trim().to_lowercase(),
+    };
+    let unicode = to_unicode(&ascii);
+    let labels: Vec<&str> = unicode.split('.').collect();
+
+    let mut scripts: BTreeSet<&'static str> = BTreeSet::new();
+    let mut mixed_script = false;
+    for label in &labels {
+        let label_scripts: HashSet<Script> = label
+            .chars()
+            .map(|c| c.script())
+            .filter(|s| !matches!(s, Script::Common | Script::Inherited | Script::Unknown))
+            .collect();
+        scripts.extend(label_scripts.iter().map(|s| s.full_name()));
+        if label_scripts.len() > 1
+            && !ALLOWED_SCRIPT_MIXES
+                .iter()
+                .any(|allowed| label_scripts.iter().all(|s| allowed.contains(s)))
+        {
+            mixed_script = true;
+        }
+    }
+
+    let confusable = unicode.chars().any(is_ascii_confusable);
+
+    // Every label but the TLD, as skeletons
+    let names: Vec<String> = labels[..labels.len().saturating_sub(1)]
+        .iter()
+        .map(|label| skeleton(label))
+        .collect();
+    let looks_like = brands
+        .iter()
+        .filter(|brand| !is_within(&ascii, brand))
+        .filter(|brand| {
+            let name = skeleton(brand.split('.').next().unwrap_or(brand));
+            names.iter().any(|label| imitates(label, &name))
+        })
+        .cloned()
+        .collect();
+
+    DomainAnalysis {
+        ascii,
+        unicode,
+        scripts: scripts.into_iter().map(str::to_string).collect(),
+        mixed_script,
+        confusable,
+        looks_like,
+    }
+}
+
+/// Analyse a domain against the open vault's watch list
+pub fn analyze(domain: &str) -> DomainAnalysis {
+    let brands: Vec<String> = WATCHED
+        .read()
+        .map(|watched| watched.clone())
+        .unwrap_or_default();
+    analyze_with(domain, &brands)
+}
+
+/// Whether a label's skeleton is a brand name's: the same, containing it
+/// (`paypal-secure`) or one edit away (`paypa`, `paypall`)
+fn imitates(label: &str, brand: &str) -> bool {
+    if label == brand {
+        return true;
+    }
+    brand.chars().count() >= MIN_FUZZY_BRAND_LEN
+        && (label.contains(brand) || within_one_edit(label, brand))
+}
+
+fn within_one_edit(a: &str, b: &str) -> bool {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short.iter().zip(&long).take_while(|(x, y)| x == y).count();
+    if prefix == short.len() {
+        return true;
+    }
+    if short.len() == long.len() {
+        // One substitution
+        short[prefix + 1..] == long[prefix + 1..]
+    } else {
+        // One insertion
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+/// Tag a domain, email or URL entity with its findings and remember its
+/// Unicode form as an alias
+pub fn annotate(entity: &mut Entity) {
+    let Some(host) = host_of(&entity.entity_type, &entity.value) else {
+        return;
+    };
+    let analysis = analyze(&host);
+    if analysis.is_idn() {
+        let unicode = match entity.entity_type {
+            EntityType::Domain => analysis.unicode.clone(),
+            _ => entity.value.replacen(&analysis.ascii, &analysis.unicode, 1),
+        };
+        entity.add_alias(&unicode);
+    }
+    for tag in analysis.tags() {
+        if !entity.tags.contains(&tag) {
+            entity.tags.push(tag);
+        }
+    }
+}
+
+/// Replace the watch list with `list`
+pub fn install(list: &BrandWatchList) -> Result<(), String> {
+    let brands = list.normalized()?;
+    let mut watched = WATCHED
+        .write()
+        .map_err(|e| format!("Watch list lock poisoned: {}", e))?;
+    *watched = brands;
+    Ok(())
+}
+
+/// Drop the installed watch list
+pub fn clear() {
+    if let Ok(mut watched) = WATCHED.write() {
+        watched.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brands() -> Vec<String> {
+        vec!["paypal.com".to_string(), "bund.de".to_string()]
+    }
+
+    #[test]
+    fn test_unicode_and_punycode_forms() {
+        let analysis = analyze_with("Bücher.DE", &[]);
+        assert_eq!(analysis.ascii, "xn--bcher-kva.de");
+        assert_eq!(analysis.unicode, "bücher.de");
+        assert!(analysis.is_idn());
+        assert!(!analysis.mixed_script);
+        assert!(!analysis.confusable);
+        assert_eq!(analyze_with("xn--bcher-kva.de", &[]).unicode, "bücher.de");
+    }
+
+    #[test]
+    fn test_homoglyph_lookalikes() {
+        // Cyrillic а and о
+        let spoof = analyze_with("pаypаl.cоm", &brands());
+        assert!(spoof.mixed_script);
+        assert!(spoof.confusable);
+        assert_eq!(spoof.looks_like, vec!["paypal.com"]);
+        assert!(spoof.tags().contains(&"looks-like:paypal.com".to_string()));
+
+        assert_eq!(
+            analyze_with("paypa1.net", &brands()).looks_like,
+            vec!["paypal.com"]
+        );
+        assert_eq!(
+            analyze_with("secure-paypal-login.com", &brands()).looks_like,
+            vec!["paypal.com"]
+        );
+        assert_eq!(
+            analyze_with("paypall.com", &brands()).looks_like,
+            vec!["paypal.com"]
+        );
+
+        // The brand itself, its subdomains and unrelated names are fine
+        assert!(analyze_with("www.paypal.com", &brands())
+            .looks_like
+            .is_empty());
+        assert!(analyze_with("example.com", &brands()).looks_like.is_empty());
+        // Short names only match exactly
+        assert!(analyze_with("bundesamt.de", &brands())
+            .looks_like
+            .is_empty());
+        assert_eq!(
+            analyze_with("bund.net", &brands()).looks_like,
+            vec!["bund.de"]
+        );
+    }
+
+    #[test]
+    fn test_confusables_cover_the_full_data() {
+        // Lisu ꓲ renders as a Latin l
+        let spoof = analyze_with("paypaꓲ.com", &brands());
+        assert!(spoof.confusable);
+        assert_eq!(spoof.looks_like, vec!["paypal.com"]);
+        assert_eq!(skeleton("g00gle"), skeleton("google"));
+        assert_ne!(skeleton("bücher"), skeleton("bucher"));
+    }
+
+    #[test]
+    fn test_allowed_script_mixes() {
+        assert!(!analyze_with("東京tokyo.jp", &[]).mixed_script);
+        assert!(analyze_with("googlе.com", &[]).mixed_script);
+    }
+
+    #[test]
+    fn test_entities_are_annotated() {
+        let source = crate::core::entity::EntitySource::new("analyst");
+        let spoof = Entity::new(EntityType::Email, "billing@pаypal.com".to_string(), source);
+        assert_eq!(spoof.value, "billing@xn--pypal-4ve.com");
+        assert_eq!(spoof.aliases, vec!["billing@pаypal.com"]);
+        for tag in ["idn", "mixed-script", "confusable"] {
+            assert!(spoof.tags.contains(&tag.to_string()), "missing {}", tag);
+        }
+    }
+}
@@ -0,0 +1,607 @@
+//! Image Metadata
+//!
+//! Reads the EXIF and XMP metadata of photos (JPEG, PNG, HEIC and TIFF):
+//! where they were taken, when, with which camera and by whom. EXIF is
+//! preferred where both are present; XMP fills the gaps, and is often all
+//! that survives an editor's export.
+//!
+//! A GPS fix becomes a `Coordinate` entity, the capture time a `Date` and the
+//! camera body's serial number a `CameraSerial`, which links every photo the
+//! same camera took.
+
+use crate::core::dates::{parse_date, NormalizedDate};
+use crate::core::entity::EntityType;
+use exif::{In, Reader, Tag, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// Largest image read
+pub const MAX_IMAGE_BYTES: u64 = 128 * 1024 * 1024;
+
+/// Name of the custom entity type for camera serial numbers
+pub const CAMERA_SERIAL: &str = "CameraSerial";
+
+/// Image containers that can be read
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Heic,
+    Tiff,
+}
+
+/// Where a photo was taken
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GpsFix {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Metres above sea level
+    pub altitude: Option<f64>,
+    /// The fix's own UTC timestamp, when recorded
+    pub timestamp: Option<NormalizedDate>,
+}
+
+impl GpsFix {
+    /// The fix as a `Coordinate` entity value
+    pub fn coordinate(&self) -> String {
+        format!("{:.6},{:.6}", self.latitude, self.longitude)
+    }
+}
+
+/// What an image's metadata says about it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub format: ImageFormat,
+    /// Hex SHA-256 of the file
+    pub sha256: String,
+    pub size: u64,
+    pub gps: Option<GpsFix>,
+    /// When the photo was taken
+    pub captured: Option<NormalizedDate>,
+    /// When the file was last changed by software
+    pub modified: Option<NormalizedDate>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// Camera body serial number
+    pub camera_serial: Option<String>,
+    pub lens_serial: Option<String>,
+    /// Camera owner, artist and creator names
+    pub owners: Vec<String>,
+    pub copyright: Option<String>,
+    /// Editing or firmware software
+    pub software: Option<String>,
+    pub unique_id: Option<String>,
+}
+
+/// A value the metadata yields as an entity
+#[derive(Debug, Clone)]
+pub struct ImageEntity {
+    pub entity_type: EntityType,
+    pub value: String,
+    /// What the value is to the image, e.g. `GPS position` or `capture time`
+    pub origin: String,
+}
+
+impl ImageMetadata {
+    /// The camera as "make model", skipping a make the model repeats
+    pub fn camera(&self) -> Option<String> {
+        match (&self.make, &self.model) {
+            (Some(make), Some(model)) if model.starts_with(make.as_str()) => Some(model.clone()),
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.clone().or_else(|| model.clone()),
+        }
+    }
+
+    /// The location, capture time and camera serial as entities
+    pub fn entities(&self) -> Vec<ImageEntity> {
+        let mut entities = Vec::new();
+        if let Some(gps) = &self.gps {
+            entities.push(ImageEntity {
+                entity_type: EntityType::Coordinate,
+                value: gps.coordinate(),
+                origin: "GPS position".to_string(),
+            });
+        }
+        if let Some(captured) = &self.captured {
+            entities.push(ImageEntity {
+                entity_type: EntityType::Date,
+                value: captured.value.clone(),
+                origin: "capture time".to_string(),
+            });
+        }
+        if let Some(serial) = &self.camera_serial {
+            entities.push(ImageEntity {
+                entity_type: EntityType::Custom(CAMERA_SERIAL.to_string()),
+                value: serial.clone(),
+                origin: match self.camera() {
+                    Some(camera) => format!("serial number of {}", camera),
+                    None => "camera serial number".to_string(),
+                },
+            });
+        }
+        entities
+    }
+
+    /// A GeoJSON `Feature` placing the photo, if it has a GPS fix
+    pub fn geojson_feature(&self, file_name: &str) -> Option<serde_json::Value> {
+        let gps = self.gps.as_ref()?;
+        // Six decimals (about 10 cm), as RFC 7946 recommends
+        let round = |x: f64| (x * 1e6).round() / 1e6;
+        let mut coordinates = vec![round(gps.longitude), round(gps.latitude)];
+        coordinates.extend(gps.altitude);
+        Some(json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": coordinates },
+            "properties": {
+                "file_name": file_name,
+                "sha256": self.sha256,
+                "captured": self.captured.as_ref().map(|d| &d.value),
+                "gps_timestamp": gps.timestamp.as_ref().map(|d| &d.value),
+                "camera": self.camera(),
+                "camera_serial": self.camera_serial,
+                "owners": self.owners,
+                "software": self.software,
+            },
+        }))
+    }
+}
+
+/// A GeoJSON `FeatureCollection` of the photos with a GPS fix, given as
+/// (file name, metadata)
+pub fn to_geojson(images: &[(String, ImageMetadata)]) -> serde_json::Value {
+    let features: Vec<_> = images
+        .iter()
+        .filter_map(|(name, image)| image.geojson_feature(name))
+        .collect();
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+/// Read an image file's metadata
+pub fn read_image(path: &Path) -> Result<ImageMetadata, String> {
+    let file =
+        std::fs::File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut bytes = Vec::new();
+    file.take(MAX_IMAGE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    if bytes.len() as u64 > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "{:?} is larger than {} MiB",
+            path,
+            MAX_IMAGE_BYTES / (1024 * 1024)
+        ));
+    }
+    parse_image(&bytes)
+}
+
+/// Read an image's metadata from its bytes. Images without any metadata
+/// are not an error; their fields are simply empty.
+pub fn parse_image(bytes: &[u8]) -> Result<ImageMetadata, String> {
+    let format = detect_format(bytes).ok_or_else(|| "Unsupported image format".to_string())?;
+    let mut image = ImageMetadata {
+        format,
+        sha256: format!("{:x}", Sha256::digest(bytes)),
+        size: bytes.len() as u64,
+        gps: None,
+        captured: None,
+        modified: None,
+        make: None,
+        model: None,
+        camera_serial: None,
+        lens_serial: None,
+        owners: Vec::new(),
+        copyright: None,
+        software: None,
+        unique_id: None,
+    };
+
+    // A container without an EXIF block reads as an error; XMP may remain
+    if let Ok(exif) = Reader::new().read_from_container(&mut Cursor::new(bytes)) {
+        read_exif(&exif, &mut image);
+    }
+    if let Some(xmp) = find_xmp(bytes) {
+        read_xmp(&xmp, &mut image);
+    }
+    // EXIF and XMP often both name the owner; keep the first of each
+    let mut seen = HashSet::new();
+    image.owners.retain(|owner| seen.insert(owner.clone()));
+    Ok(image)
+}
+
+fn detect_format(bytes: &[u8]) -> Option<ImageFormat> {
+    if bytes.starts_with(b"\xFF\xD8\xFF") {
+        Some(ImageFormat::Jpeg)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(ImageFormat::Png)
+    } else if bytes.starts_with(b"II*\0") || bytes.starts_with(b"MM\0*") {
+        Some(ImageFormat::Tiff)
+    } else if bytes.get(4..8) == Some(b"ftyp")
+        && matches!(
+            bytes.get(8..12),
+            Some(b"heic" | b"heix" | b"hevc" | b"heim" | b"heis" | b"mif1" | b"msf1")
+        )
+    {
+        Some(ImageFormat::Heic)
+    } else {
+        None
+    }
+}
+
+fn read_exif(exif: &exif::Exif, image: &mut ImageMetadata) {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY).map(|f| &f.value);
+    let text = |tag: Tag| field(tag).and_then(ascii);
+
+    image.make = text(Tag::Make);
+    image.model = text(Tag::Model);
+    image.camera_serial = text(Tag::BodySerialNumber);
+    image.lens_serial = text(Tag::LensSerialNumber);
+    image.copyright = text(Tag::Copyright);
+    image.software = text(Tag::Software);
+    image.unique_id = text(Tag::ImageUniqueID);
+    image.owners.extend(text(Tag::CameraOwnerName));
+    image.owners.extend(text(Tag::Artist));
+
+    let date = |tag: Tag, offset: Tag| {
+        let value = text(tag)?;
+        exif_date(&value, text(offset).as_deref())
+    };
+    image.captured = date(Tag::DateTimeOriginal, Tag::OffsetTimeOriginal)
+        .or_else(|| date(Tag::DateTimeDigitized, Tag::OffsetTimeDigitized));
+    image.modified = date(Tag::DateTime, Tag::OffsetTime);
+
+    let degrees = |tag: Tag, reference: Tag, negative: &str| {
+        let Some(Value::Rational(parts)) = field(tag) else {
+            return None;
+        };
+        let mut value = 0.0;
+        for (part, scale) in parts.iter().zip([1.0, 60.0, 3600.0]) {
+            value += part.to_f64() / scale;
+        }
+        if !value.is_finite() {
+            return None;
+        }
+        match text(reference) {
+            Some(r) if r.eq_ignore_ascii_case(negative) => Some(-value),
+            _ => Some(value),
+        }
+    };
+    let latitude = degrees(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S");
+    let longitude = degrees(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W");
+    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+        let altitude = match field(Tag::GPSAltitude) {
+            Some(Value::Rational(parts)) => parts.first().map(|a| {
+                let below_sea_level = matches!(field(Tag::GPSAltitudeRef), Some(Value::Byte(r)) if r.first() == Some(&1));
+                if below_sea_level {
+                    -a.to_f64()
+                } else {
+                    a.to_f64()
+                }
+            }),
+            _ => None,
+        }
+        .filter(|a| a.is_finite());
+        let timestamp = match (text(Tag::GPSDateStamp), field(Tag::GPSTimeStamp)) {
+            (Some(date), Some(Value::Rational(time))) if time.len() == 3 => {
+                let [h, m, s] = [0, 1, 2].map(|i| time[i].to_f64());
+                parse_date(&format!(
+                    "{}T{:02}:{:02}:{:02}Z",
+                    date.replace(':', "-"),
+                    h as u32,
+                    m as u32,
+                    s as u32
+                ))
+            }
+            _ => None,
+        };
+        image.gps = valid_fix(latitude, longitude).then_some(GpsFix {
+            latitude,
+            longitude,
+            altitude,
+            timestamp,
+        });
+    }
+}
+
+/// The first string of an ASCII field, if not blank
+fn ascii(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(parts) => parts
+            .first()
+            .map(|part| String::from_utf8_lossy(part).trim().to_string())
+            .filter(|text| !text.is_empty()),
+        _ => None,
+    }
+}
+
+/// EXIF `YYYY:MM:DD HH:MM:SS`, with the offset EXIF 2.31 records separately
+fn exif_date(value: &str, offset: Option<&str>) -> Option<NormalizedDate> {
+    let (date, time) = value.trim().split_once(' ')?;
+    parse_date(&format!(
+        "{}T{}{}",
+        date.replace(':', "-"),
+        time,
+        offset.unwrap_or_default()
+    ))
+}
+
+/// Whether a fix is on Earth and not the `0,0` that unset receivers write
+fn valid_fix(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude)
+        && (-180.0..=180.0).contains(&longitude)
+        && (latitude != 0.0 || longitude != 0.0)
+}
+
+/// The XMP packet, which every container stores as plain XML
+fn find_xmp(bytes: &[u8]) -> Option<String> {
+    let find = |needle: &[u8], from: usize| {
+        bytes
+            .get(from..)?
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|i| i + from)
+    };
+    let start = find(b"<x:xmpmeta", 0)?;
+    let end = find(b"</x:xmpmeta>", start)? + b"</x:xmpmeta>".len();
+    Some(String::from_utf8_lossy(&bytes[start..end]).into_owned())
+}
+
+/// Fill the fields EXIF left empty from XMP properties
+fn read_xmp(xml: &str, image: &mut ImageMetadata) {
+    let Ok(doc) = roxmltree::Document::parse(xml) else {
+        return;
+    };
+    // Properties by local name, as attributes or elements of
+    // `rdf:Description`, with `rdf:Seq`/`Alt`/`Bag` items flattened
+    let mut properties: HashMap<&str, Vec<String>> = HashMap::new();
+    for description in doc
+        .descendants()
+        .filter(|n| n.tag_name().name() == "Description")
+    {
+        for attribute in description.attributes() {
+            properties
+                .entry(attribute.name())
+                .or_default()
+                .push(attribute.value().trim().to_string());
+        }
+        for property in description.children().filter(|n| n.is_element()) {
+            let values = property
+                .descendants()
+                .filter(|n| n.is_text())
+                .filter_map(|n| n.text())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string);
+            properties
+                .entry(property.tag_name().name())
+                .or_default()
+                .extend(values);
+        }
+    }
+    let get = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| properties.get(name).and_then(|v| v.first()))
+            .filter(|v| !v.is_empty())
+            .cloned()
+    };
+
+    image.make = image.make.take().or_else(|| get(&["Make"]));
+    image.model = image.model.take().or_else(|| get(&["Model"]));
+    image.camera_serial = image
+        .camera_serial
+        .take()
+        .or_else(|| get(&["BodySerialNumber", "SerialNumber"]));
+    image.lens_serial = image
+        .lens_serial
+        .take()
+        .or_else(|| get(&["LensSerialNumber"]));
+    image.copyright = image.copyright.take().or_else(|| get(&["rights"]));
+    image.software = image
+        .software
+        .take()
+        .or_else(|| get(&["CreatorTool", "Software"]));
+    image.unique_id = image
+        .unique_id
+        .take()
+        .or_else(|| get(&["ImageUniqueID", "DocumentID"]));
+    for name in ["CameraOwnerName", "OwnerName", "creator", "Artist"] {
+        image
+            .owners
+            .extend(properties.get(name).into_iter().flatten().cloned());
+    }
+    image.captured = image.captured.take().or_else(|| {
+        get(&["DateTimeOriginal", "DateCreated", "CreateDate"]).and_then(|d| parse_date(&d))
+    });
+    image.modified = image
+        .modified
+        .take()
+        .or_else(|| get(&["ModifyDate"]).and_then(|d| parse_date(&d)));
+
+    if image.gps.is_none() {
+        let latitude = get(&["GPSLatitude"]).and_then(|v| xmp_degrees(&v));
+        let longitude = get(&["GPSLongitude"]).and_then(|v| xmp_degrees(&v));
+        if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+            let altitude = get(&["GPSAltitude"]).and_then(|a| {
+                let metres = match a.split_once('/') {
+                    Some((n, d)) => n.parse::<f64>().ok()? / d.parse::<f64>().ok()?,
+                    None => a.parse().ok()?,
+                };
+                let below_sea_level = get(&["GPSAltitudeRef"]).as_deref() == Some("1");
+                let metres = if below_sea_level { -metres } else { metres };
+                metres.is_finite().then_some(metres)
+            });
+            image.gps = valid_fix(latitude, longitude).then_some(GpsFix {
+                latitude,
+                longitude,
+                altitude,
+                timestamp: get(&["GPSTimeStamp"]).and_then(|t| parse_date(&t)),
+            });
+        }
+    }
+}
+
+/// XMP GPS coordinates: `DDD,MM.mmk` or `DDD,MM,SSk`, `k` one of `NSEW`
+fn xmp_degrees(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let direction = value.chars().last()?.to_ascii_uppercase();
+    let numbers = value[..value.len() - direction.len_utf8()]
+        .split(',')
+        .map(|part| part.trim().parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    if numbers.is_empty() || numbers.len() > 3 {
+        return None;
+    }
+    let degrees: f64 = numbers
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, scale)| part / scale)
+        .sum();
+    match direction {
+        'N' | 'E' => Some(degrees),
+        'S' | 'W' => Some(-degrees),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, Rational};
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    fn ascii(text: &str) -> Value {
+        Value::Ascii(vec![text.as_bytes().to_vec()])
+    }
+
+    fn rationals(parts: &[(u32, u32)]) -> Value {
+        Value::Rational(
+            parts
+                .iter()
+                .map(|&(num, denom)| Rational { num, denom })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_exif_gps_camera_and_dates() {
+        let fields = [
+            field(Tag::Make, ascii("Canon")),
+            field(Tag::Model, ascii("Canon EOS R5")),
+            field(Tag::BodySerialNumber, ascii("032021001234")),
+            field(Tag::CameraOwnerName, ascii("J. Doe")),
+            field(Tag::DateTimeOriginal, ascii("2023:07:14 18:05:09")),
+            field(Tag::OffsetTimeOriginal, ascii("+02:00")),
+            field(Tag::GPSLatitudeRef, ascii("N")),
+            field(
+                Tag::GPSLatitude,
+                rationals(&[(48, 1), (51, 1), (2988, 100)]),
+            ),
+            field(Tag::GPSLongitudeRef, ascii("W")),
+            field(
+                Tag::GPSLongitude,
+                rationals(&[(2, 1), (17, 1), (4020, 100)]),
+            ),
+            field(Tag::GPSAltitude, rationals(&[(355, 10)])),
+        ];
+        let mut writer = Writer::new();
+        for f in &fields {
+            writer.push_field(f);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+
+        let image = parse_image(tiff.get_ref()).unwrap();
+        assert_eq!(image.format, ImageFormat::Tiff);
+        assert_eq!(image.camera().as_deref(), Some("Canon EOS R5"));
+        assert_eq!(image.owners, vec!["J. Doe"]);
+        assert_eq!(
+            image.captured.as_ref().unwrap().value,
+            "2023-07-14T16:05:09Z"
+        );
+        let gps = image.gps.as_ref().unwrap();
+        assert_eq!(gps.coordinate(), "48.858300,-2.294500");
+        assert_eq!(gps.altitude, Some(35.5));
+
+        let entities = image.entities();
+        let types: Vec<_> = entities.iter().map(|e| e.entity_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                EntityType::Coordinate,
+                EntityType::Date,
+                EntityType::Custom(CAMERA_SERIAL.to_string())
+            ]
+        );
+        assert_eq!(entities[2].value, "032021001234");
+
+        let geojson = to_geojson(&[("eiffel.tif".to_string(), image)]);
+        let feature = &geojson["features"][0];
+        let coordinate = |i: usize| feature["geometry"]["coordinates"][i].as_f64().unwrap();
+        assert!((coordinate(0) + 2.2945).abs() < 1e-9);
+        assert!((coordinate(1) - 48.8583).abs() < 1e-9);
+        assert_eq!(feature["properties"]["camera_serial"], "032021001234");
+    }
+
+    #[test]
+    fn test_xmp_fills_in() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:exif="http://ns.adobe.com/exif/1.0/" xmlns:aux="http://ns.adobe.com/exif/1.0/aux/"
+                xmlns:photoshop="http://ns.adobe.com/photoshop/1.0/" xmlns:dc="http://purl.org/dc/elements/1.1/"
+                exif:GPSLatitude="33,51.5408S" exif:GPSLongitude="151,12.8472E"
+                aux:SerialNumber="A1B2C3" photoshop:DateCreated="2022-11-02T08:15:00Z">
+                <dc:creator><rdf:Seq><rdf:li>Photo Owner</rdf:li></rdf:Seq></dc:creator>
+            </rdf:Description></rdf:RDF></x:xmpmeta>"#;
+        let mut jpeg = b"\xFF\xD8\xFF\xE1".to_vec();
+        jpeg.extend_from_slice(b"\0\0http://ns.adobe.com/xap/1.0/\0");
+        jpeg.extend_from_slice(xmp.as_bytes());
+        jpeg.extend_from_slice(b"\xFF\xD9");
+
+        let image = parse_image(&jpeg).unwrap();
+        assert_eq!(image.format, ImageFormat::Jpeg);
+        assert_eq!(image.gps.unwrap().coordinate(), "-33.859013,151.214120");
+        assert_eq!(image.camera_serial.as_deref(), Some("A1B2C3"));
+        assert_eq!(image.owners, vec!["Photo Owner"]);
+        assert_eq!(image.captured.unwrap().value, "2022-11-02T08:15:00Z");
+
+        assert!(parse_image(b"GIF89a").is_err());
+        assert_eq!(xmp_degrees("10,30,0n"), Some(10.5));
+        assert!(!valid_fix(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_owners_are_deduplicated_in_order() {
+        let mut writer = Writer::new();
+        let fields = [
+            field(Tag::CameraOwnerName, ascii("J. Doe")),
+            field(Tag::Artist, ascii("Studio")),
+        ];
+        for f in &fields {
+            writer.push_field(f);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+        let mut bytes = tiff.into_inner();
+        bytes.extend_from_slice(
+            br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+            <rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/"><dc:creator><rdf:Seq>
+                <rdf:li>J. Doe</rdf:li><rdf:li>Studio</rdf:li><rdf:li>Other</rdf:li>
+            </rdf:Seq></dc:creator></rdf:Description></rdf:RDF></x:xmpmeta>"#,
+        );
+
+        let image = parse_image(&bytes).unwrap();
+        assert_eq!(image.owners, vec!["J. Doe", "Studio", "Other"]);
+    }
+}
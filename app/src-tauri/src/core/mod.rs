@@ -3,8 +3,19 @@
 //! Core functionality for identity management, entity extraction, fingerprinting,
 //! and dynamic privacy protection.
 
+pub mod confidence;
+pub mod custom_extractors;
+pub mod dates;
+pub mod document_extractor;
 pub mod entity;
 pub mod entity_extractor;
 pub mod fingerprint;
+pub mod html_extractor;
+pub mod idn;
 pub mod identity;
+pub mod image_metadata;
+pub mod normalize;
 pub mod privacy_engine;
+pub mod refang;
+pub mod suppression;
+pub mod validators;
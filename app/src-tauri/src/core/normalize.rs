@@ -0,0 +1,361 @@
+//! Entity Normalisation
+//!
+//! Canonical forms per entity type, applied before hashing so that the same
+//! selector written differently (`+1 (555) 123-4567` vs `555-123-4567`,
+//! `HTTP://Example.com/` vs `https://example.com`) becomes one entity.
+//!
+//! Values that cannot be parsed for their type are only trimmed (and
+//! lowercased where the type is case-insensitive), never rejected. Custom
+//! types follow their [`custom_extractors`] rule's template. Network
+//! indicators are [`refang`](crate::core::refang)ed first, so
+//! `hxxps://evil[.]com` and `https://evil.com` are one entity.
+
+use crate::core::custom_extractors;
+use crate::core::dates::parse_date;
+use crate::core::entity::EntityType;
+use crate::core::refang;
+use crate::core::validators::{link_tail, parse_cidr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use url::{Host, Url};
+
+/// Canonical form of `value` for `entity_type`
+pub fn canonicalize(entity_type: &EntityType, value: &str) -> String {
+    let refanged;
+    let value = if refang::applies_to(entity_type) {
+        refanged = refang::refang(value.trim()).text;
+        refanged.as_str()
+    } else {
+        value.trim()
+    };
+    match entity_type {
+        EntityType::Email => canonical_email(value),
+        EntityType::Phone => canonical_phone(value),
+        EntityType::IpV4 => value
+            .parse::<Ipv4Addr>()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|_| value.to_string()),
+        EntityType::IpV6 => canonical_ipv6(value),
+        EntityType::Domain => canonical_domain(value),
+        EntityType::Url => canonical_url(value),
+        EntityType::BitcoinAddress => canonical_address(value, "bc1"),
+        EntityType::LitecoinAddress => canonical_address(value, "ltc1"),
+        EntityType::EthereumAddress => value.to_lowercase(),
+        EntityType::MoneroAddress
+        | EntityType::TronAddress
+        | EntityType::SolanaAddress
+        | EntityType::Jwt
+        | EntityType::AwsAccessKey
+        | EntityType::GithubToken
+        | EntityType::SlackToken => value.to_string(),
+        EntityType::Iban => value
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase(),
+        EntityType::OnionAddress
+        | EntityType::Md5Hash
+        | EntityType::Sha1Hash
+        | EntityType::Sha256Hash => value.to_lowercase(),
+        EntityType::Asn => digits(value)
+            .parse::<u64>()
+            .map(|n| format!("AS{}", n))
+            .unwrap_or_else(|_| value.to_uppercase()),
+        EntityType::Cidr => canonical_cidr(value),
+        EntityType::Cve => value.to_uppercase(),
+        EntityType::PgpFingerprint => value.split_whitespace().collect::<String>().to_uppercase(),
+        EntityType::TelegramHandle => {
+            format!(
+                "@{}",
+                link_tail(value).trim_start_matches('@').to_lowercase()
+            )
+        }
+        EntityType::DiscordInvite => format!("discord.gg/{}", link_tail(value)),
+        EntityType::CreditCard => digits(value),
+        EntityType::Ssn => canonical_ssn(value),
+        EntityType::MacAddress => canonical_mac(value),
+        EntityType::Uuid => canonical_uuid(value),
+        EntityType::Coordinate => canonical_coordinate(value),
+        EntityType::Date => parse_date(value)
+            .map(|date| date.value)
+            .unwrap_or_else(|| value.to_lowercase()),
+        EntityType::Custom(name) => {
+            custom_extractors::canonicalize(name, value).unwrap_or_else(|| value.to_lowercase())
+        }
+        EntityType::Username | EntityType::Hashtag => value.to_lowercase(),
+    }
+}
+
+/// Lowercased local part with a canonical domain
+fn canonical_email(value: &str) -> String {
+    match value.rsplit_once('@') {
+        Some((local, domain)) => format!("{}@{}", local.to_lowercase(), canonical_domain(domain)),
+        None => value.to_lowercase(),
+    }
+}
+
+/// E.164 (`+<country><number>`).
+///
+/// A number without an international prefix only gets `+1` when it has the
+/// North American shape (see [`is_nanp`]); anything else keeps its bare
+/// digits, since its country is unknown.
+fn canonical_phone(value: &str) -> String {
+    let all_digits = digits(value);
+    let (international, number) = if value.starts_with('+') {
+        (true, all_digits.as_str())
+    } else if let Some(rest) = all_digits.strip_prefix("00") {
+        (true, rest)
+    } else {
+        (false, all_digits.as_str())
+    };
+
+    match (international, number.len()) {
+        (true, 7..=15) => format!("+{}", number),
+        (false, 10) if is_nanp(number) => format!("+1{}", number),
+        (false, 11) if number.starts_with('1') && is_nanp(&number[1..]) => {
+            format!("+{}", number)
+        }
+        _ => all_digits,
+    }
+}
+
+/// Ten digits with an area code that cannot be a trunk prefix (`0` or `1`)
+fn is_nanp(number: &str) -> bool {
+    let digits = number.as_bytes();
+    digits.len() == 10 && digits.iter().all(u8::is_ascii_digit) && matches!(digits[0], b'2'..=b'9')
+}
+
+/// RFC 5952 text form (lowercase, longest zero run compressed)
+fn canonical_ipv6(value: &str) -> String {
+    let bare = value.trim_start_matches('[').trim_end_matches(']');
+    bare.parse::<Ipv6Addr>()
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|_| bare.to_lowercase())
+}
+
+/// Lowercased ASCII (IDNA) form without a trailing dot
+fn canonical_domain(value: &str) -> String {
+    let bare = value.trim_end_matches('.');
+    match Host::parse(bare) {
+        Ok(Host::Domain(domain)) => domain,
+        _ => bare.to_lowercase(),
+    }
+}
+
+/// `http` folded into `https`, default port, fragment, empty query and root
+/// slash dropped; scheme and host lowercased and IDNA-encoded by the parser
+fn canonical_url(value: &str) -> String {
+    let Ok(mut url) = Url::parse(value) else {
+        return value.to_string();
+    };
+    if url.scheme() == "http" {
+        let _ = url.set_scheme("https");
+    }
+    url.set_fragment(None);
+    if url.query() == Some("") {
+        url.set_query(None);
+    }
+
+    let mut canonical = url.to_string();
+    if url.path() == "/" && url.query().is_none() && canonical.ends_with('/') {
+        canonical.pop();
+    }
+    canonical
+}
+
+/// Bech32 addresses (starting `bech32_prefix`) are case-insensitive; Base58
+/// addresses are not
+fn canonical_address(value: &str, bech32_prefix: &str) -> String {
+    if value.to_lowercase().starts_with(bech32_prefix) {
+        value.to_lowercase()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Network address and prefix length, host bits cleared
+fn canonical_cidr(value: &str) -> String {
+    match parse_cidr(value) {
+        Some((IpAddr::V4(ip), len)) => {
+            let mask = u32::MAX.checked_shl(32 - len as u32).unwrap_or(0);
+            format!("{}/{}", Ipv4Addr::from(u32::from(ip) & mask), len)
+        }
+        Some((IpAddr::V6(ip), len)) => {
+            let mask = u128::MAX.checked_shl(128 - len as u32).unwrap_or(0);
+            format!("{}/{}", Ipv6Addr::from(u128::from(ip) & mask), len)
+        }
+        None => value.to_lowercase(),
+    }
+}
+
+/// `AAA-GG-SSSS`
+fn canonical_ssn(value: &str) -> String {
+    let d = digits(value);
+    if d.len() == 9 {
+        format!("{}-{}-{}", &d[..3], &d[3..5], &d[5..])
+    } else {
+        d
+    }
+}
+
+/// Lowercase, colon-separated octets, whatever the input separators
+fn canonical_mac(value: &str) -> String {
+    let hex: String = value.chars().filter(char::is_ascii_hexdigit).collect();
+    if hex.len() != 12 {
+        return value.to_lowercase();
+    }
+    hex.to_lowercase()
+        .as_bytes()
+        .chunks(2)
+        .map(|octet| String::from_utf8_lossy(octet).into_owned())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Lowercase and hyphenated
+fn canonical_uuid(value: &str) -> String {
+    let hex: String = value
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .collect::<String>()
+        .to_lowercase();
+    if hex.len() != 32 {
+        return value.to_lowercase();
+    }
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// `lat,lon` in plain decimal
+fn canonical_coordinate(value: &str) -> String {
+    let parsed = value.split_once(',').and_then(|(lat, lon)| {
+        Some((
+            lat.trim().parse::<f64>().ok()?,
+            lon.trim().parse::<f64>().ok()?,
+        ))
+    });
+    match parsed {
+        Some((lat, lon)) => format!("{},{}", lat, lon),
+        None => value.split_whitespace().collect(),
+    }
+}
+
+fn digits(value: &str) -> String {
+    value.chars().filter(char::is_ascii_digit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(entity_type: EntityType, a: &str, b: &str) {
+        assert_eq!(
+            canonicalize(&entity_type, a),
+            canonicalize(&entity_type, b),
+            "{:?}: {} vs {}",
+            entity_type,
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_formatting_variants_collapse() {
+        same(EntityType::Phone, "+1 (555) 123-4567", "555-123-4567");
+        same(EntityType::Phone, "1-555-123-4567", "555.123.4567");
+        same(EntityType::Phone, "0044 20 7946 0958", "+44 20 7946 0958");
+        same(
+            EntityType::Url,
+            "HTTP://Example.com/",
+            "https://example.com",
+        );
+        same(
+            EntityType::Url,
+            "https://example.com:443/a?#top",
+            "https://EXAMPLE.com/a",
+        );
+        same(EntityType::IpV6, "2001:DB8:0:0:0:0:0:1", "2001:db8::1");
+        same(EntityType::Url, "hxxps://evil[.]com", "https://evil.com");
+        same(
+            EntityType::Email,
+            "john [at] example (dot) com",
+            "john@example.com",
+        );
+        same(EntityType::Domain, "Bücher.DE.", "xn--bcher-kva.de");
+        same(
+            EntityType::MacAddress,
+            "AA-BB-CC-DD-EE-FF",
+            "aabb.ccdd.eeff",
+        );
+        same(
+            EntityType::EthereumAddress,
+            "0x52908400098527886E0F7030069857D2E4169EE7",
+            "0x52908400098527886e0f7030069857d2e4169ee7",
+        );
+        same(
+            EntityType::Coordinate,
+            "40.7128, -74.0060",
+            "40.7128,-74.006",
+        );
+    }
+
+    #[test]
+    fn test_canonical_forms() {
+        assert_eq!(
+            canonicalize(&EntityType::Phone, "(555) 123-4567"),
+            "+15551234567"
+        );
+        assert_eq!(
+            canonicalize(&EntityType::IpV6, "2001:db8:0:0:1:0:0:1"),
+            "2001:db8::1:0:0:1"
+        );
+        assert_eq!(canonicalize(&EntityType::Ssn, "123 45 6789"), "123-45-6789");
+        assert_eq!(
+            canonicalize(&EntityType::Email, "John.Doe@Example.COM"),
+            "john.doe@example.com"
+        );
+    }
+
+    #[test]
+    fn test_only_nanp_shaped_numbers_get_plus_one() {
+        // Area code starting with 0 or 1, or the wrong length
+        assert_eq!(
+            canonicalize(&EntityType::Phone, "112 345 6789"),
+            "1123456789"
+        );
+        assert_eq!(
+            canonicalize(&EntityType::Phone, "030 1234 5678"),
+            "03012345678"
+        );
+        assert_eq!(
+            canonicalize(&EntityType::Phone, "012 345 6789"),
+            "0123456789"
+        );
+        assert_eq!(
+            canonicalize(&EntityType::Phone, "1 (012) 345-6789"),
+            "10123456789"
+        );
+        assert_eq!(
+            canonicalize(&EntityType::Phone, "1 (212) 555-0100"),
+            "+12125550100"
+        );
+    }
+
+    #[test]
+    fn test_base58_bitcoin_keeps_case() {
+        let address = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+        assert_eq!(canonicalize(&EntityType::BitcoinAddress, address), address);
+        assert_eq!(
+            canonicalize(
+                &EntityType::BitcoinAddress,
+                "BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ"
+            ),
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"
+        );
+    }
+}
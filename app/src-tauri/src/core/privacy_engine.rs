@@ -5,9 +5,9 @@
 //!
 //! "Privacy is not about hiding. It's about control."
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// OPSEC (Operational Security) Levels
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -543,6 +543,103 @@ pub struct PrivacyStats {
     pub auto_escalations: u64,
 }
 
+impl PrivacyStats {
+    /// Add another set of counters to this one
+    pub fn accumulate(&mut self, other: &PrivacyStats) {
+        self.trackers_blocked += other.trackers_blocked;
+        self.fingerprint_attempts_blocked += other.fingerprint_attempts_blocked;
+        self.cookies_blocked += other.cookies_blocked;
+        self.webrtc_leaks_prevented += other.webrtc_leaks_prevented;
+        self.dns_queries_protected += other.dns_queries_protected;
+        self.scripts_blocked += other.scripts_blocked;
+        self.sites_assessed += other.sites_assessed;
+        self.high_risk_sites_visited += other.high_risk_sites_visited;
+        self.auto_escalations += other.auto_escalations;
+    }
+}
+
+/// Privacy statistics for one UTC day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyPrivacyStats {
+    pub date: NaiveDate,
+    pub stats: PrivacyStats,
+}
+
+/// Most site assessments kept per identity; the oldest are dropped first
+pub const MAX_SITE_ASSESSMENTS: usize = 500;
+
+/// An identity's privacy settings, statistics and site assessments
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrivacyProfile {
+    pub settings: PrivacySettings,
+    /// Counters per UTC day
+    #[serde(default)]
+    pub daily_stats: BTreeMap<NaiveDate, PrivacyStats>,
+    /// Latest assessment per domain, at most [`MAX_SITE_ASSESSMENTS`]
+    #[serde(default)]
+    pub site_assessments: HashMap<String, RiskAssessment>,
+}
+
+impl PrivacyProfile {
+    /// Store setting holding an identity's profile
+    pub fn setting_key(identity_id: &str) -> String {
+        format!("privacy/{}", identity_id)
+    }
+
+    /// Counters for `date`, created on first use
+    pub fn stats_for(&mut self, date: NaiveDate) -> &mut PrivacyStats {
+        self.daily_stats.entry(date).or_default()
+    }
+
+    /// Keep `assessment` as the latest for `domain`, dropping the oldest
+    /// assessments beyond [`MAX_SITE_ASSESSMENTS`]
+    pub fn record_assessment(&mut self, domain: String, assessment: RiskAssessment) {
+        self.site_assessments.insert(domain, assessment);
+        let excess = self
+            .site_assessments
+            .len()
+            .saturating_sub(MAX_SITE_ASSESSMENTS);
+        if excess == 0 {
+            return;
+        }
+        let mut by_age: Vec<(DateTime<Utc>, String)> = self
+            .site_assessments
+            .iter()
+            .map(|(domain, a)| (a.assessed_at, domain.clone()))
+            .collect();
+        by_age.sort();
+        for (_, domain) in by_age.into_iter().take(excess) {
+            self.site_assessments.remove(&domain);
+        }
+    }
+
+    /// Totals over every recorded day
+    pub fn total_stats(&self) -> PrivacyStats {
+        let mut total = PrivacyStats::default();
+        for stats in self.daily_stats.values() {
+            total.accumulate(stats);
+        }
+        total
+    }
+
+    /// Recorded days between `from` and `to` (inclusive), oldest first
+    pub fn stats_history(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Vec<DailyPrivacyStats> {
+        self.daily_stats
+            .iter()
+            .filter(|(date, _)| from.is_none_or(|from| **date >= from))
+            .filter(|(date, _)| to.is_none_or(|to| **date <= to))
+            .map(|(date, stats)| DailyPrivacyStats {
+                date: *date,
+                stats: stats.clone(),
+            })
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -568,4 +665,41 @@ mod tests {
         assert!(settings.spoof_canvas);
         assert!(settings.block_trackers);
     }
+
+    #[test]
+    fn test_profile_stats_by_day() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let mut profile = PrivacyProfile::default();
+        profile.stats_for(day(1)).trackers_blocked += 2;
+        profile.stats_for(day(3)).trackers_blocked += 5;
+        profile.stats_for(day(3)).auto_escalations += 1;
+
+        let total = profile.total_stats();
+        assert_eq!(total.trackers_blocked, 7);
+        assert_eq!(total.auto_escalations, 1);
+
+        let history = profile.stats_history(Some(day(2)), None);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].date, day(3));
+
+        let json = serde_json::to_value(&profile).unwrap();
+        let restored: PrivacyProfile = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.daily_stats.len(), 2);
+    }
+
+    #[test]
+    fn test_site_assessments_are_capped() {
+        let mut profile = PrivacyProfile::default();
+        for n in 0..=MAX_SITE_ASSESSMENTS {
+            let domain = format!("site{}.example", n);
+            let mut assessment = assess_domain_risk(&domain);
+            assessment.assessed_at = Utc::now() + chrono::Duration::seconds(n as i64);
+            profile.record_assessment(domain, assessment);
+        }
+        assert_eq!(profile.site_assessments.len(), MAX_SITE_ASSESSMENTS);
+        assert!(!profile.site_assessments.contains_key("site0.example"));
+        assert!(profile
+            .site_assessments
+            .contains_key(&format!("site{}.example", MAX_SITE_ASSESSMENTS)));
+    }
 }
@@ -0,0 +1,191 @@
+//! Refanging
+//!
+//! Threat reports and forums "defang" indicators so they cannot be clicked
+//! (`hxxps://evil[.]com`, `10.0.0[.]1`) and obfuscate addresses against
+//! scrapers (`john [at] example (dot) com`). Refanging rewrites these back to
+//! their live form before extraction, keeping a map from the rewritten text
+//! to the original so matches can still be located where they were written.
+
+use crate::core::entity::{EntityType, TextSpan};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    // Defanged schemes, and bracketed separators. Bracketed dots and ats
+    // take the spaces around them (`example [dot] com`).
+    static ref DEFANG_REGEX: Regex = Regex::new(
+        r"(?ix)
+        \b(?P<scheme>hxxps?|hxtps?|htxps?|fxp)\b
+        | \[ (?P<sep>://|:) \]
+        | [\ \t]* (?: \[ \s* (?P<b>\.|dot|@|at) \s* \]
+                    | \( \s* (?P<p>\.|dot|@|at) \s* \)
+                    | \{ \s* (?P<c>\.|dot|@|at) \s* \} ) [\ \t]*
+        "
+    ).unwrap();
+}
+
+/// Text with defanged notation rewritten
+#[derive(Debug, Clone)]
+pub struct Refanged {
+    pub text: String,
+    /// Each rewrite, as (range in `text`, range in the original), in order
+    edits: Vec<(TextSpan, TextSpan)>,
+}
+
+impl Refanged {
+    /// Whether anything was rewritten
+    pub fn is_defanged(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    /// Whether a range of `text` includes rewritten characters
+    pub fn touches_edit(&self, span: TextSpan) -> bool {
+        self.edits
+            .iter()
+            .any(|(new, _)| new.start < span.end && span.start < new.end)
+    }
+
+    /// The range of the original text that a range of `text` came from
+    pub fn original_span(&self, span: TextSpan) -> TextSpan {
+        TextSpan {
+            start: self.original_index(span.start, false),
+            end: self.original_index(span.end, true),
+        }
+    }
+
+    fn original_index(&self, index: usize, end: bool) -> usize {
+        let before = self.edits.partition_point(|(new, _)| new.end <= index);
+        if let Some((new, old)) = self.edits.get(before) {
+            // Inside a rewrite: widen to the whole original token
+            if new.start < index {
+                return if end { old.end } else { old.start };
+            }
+        }
+        match before.checked_sub(1).map(|i| &self.edits[i]) {
+            Some((new, old)) => index - new.end + old.end,
+            None => index,
+        }
+    }
+}
+
+/// Rewrite defanged schemes (`hxxp`, `fxp`) and bracketed separators
+/// (`[.]`, `(dot)`, `[at]`, `[:]`, `[://]`) to their live form
+pub fn refang(text: &str) -> Refanged {
+    let mut out = String::with_capacity(text.len());
+    let mut edits = Vec::new();
+    let mut last = 0;
+
+    for caps in DEFANG_REGEX.captures_iter(text) {
+        let whole = caps.get(0).expect("group 0 always matches");
+        let replacement = replacement(&caps);
+        out.push_str(&text[last..whole.start()]);
+        let start = out.len();
+        out.push_str(&replacement);
+        edits.push((
+            TextSpan {
+                start,
+                end: out.len(),
+            },
+            TextSpan {
+                start: whole.start(),
+                end: whole.end(),
+            },
+        ));
+        last = whole.end();
+    }
+    out.push_str(&text[last..]);
+
+    Refanged { text: out, edits }
+}
+
+/// Whether values of `entity_type` are refanged before extraction and
+/// normalisation. Other types are matched against the text as written, so
+/// prose like "meet (at) noon" cannot produce a username.
+pub fn applies_to(entity_type: &EntityType) -> bool {
+    matches!(
+        entity_type,
+        EntityType::Url
+            | EntityType::Domain
+            | EntityType::Email
+            | EntityType::IpV4
+            | EntityType::IpV6
+            | EntityType::Cidr
+            | EntityType::OnionAddress
+    )
+}
+
+/// Whether `text` contains defanged notation
+pub fn is_defanged(text: &str) -> bool {
+    DEFANG_REGEX.is_match(text)
+}
+
+fn replacement(caps: &Captures) -> String {
+    if let Some(scheme) = caps.name("scheme") {
+        let scheme = scheme.as_str().to_lowercase();
+        return if scheme == "fxp" {
+            "ftp".to_string()
+        } else {
+            format!("http{}", &scheme[4..])
+        };
+    }
+    if let Some(sep) = caps.name("sep") {
+        return sep.as_str().to_string();
+    }
+    let word = caps
+        .name("b")
+        .or_else(|| caps.name("p"))
+        .or_else(|| caps.name("c"))
+        .map(|m| m.as_str().to_lowercase())
+        .unwrap_or_default();
+    match word.as_str() {
+        "." | "dot" => ".".to_string(),
+        _ => "@".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refang_conventions() {
+        let cases = [
+            ("hxxps://evil[.]com/x", "https://evil.com/x"),
+            ("HXXP[://]evil(.)com", "http://evil.com"),
+            ("hxxp[:]//10.0.0[.]1", "http://10.0.0.1"),
+            ("john [at] example (dot) com", "john@example.com"),
+            ("jane{at}example{dot}co[.]uk", "jane@example.co.uk"),
+            ("fxp://files[.]example[.]org", "ftp://files.example.org"),
+            ("plain text, no change", "plain text, no change"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(refang(input).text, expected, "refanging {}", input);
+        }
+    }
+
+    #[test]
+    fn test_offsets_map_back() {
+        let text = "Beacon to hxxps://evil[.]com today";
+        let refanged = refang(text);
+        let start = refanged.text.find("https").unwrap();
+        let span = TextSpan {
+            start,
+            end: start + "https://evil.com".len(),
+        };
+        assert!(refanged.touches_edit(span));
+
+        let original = refanged.original_span(span);
+        assert_eq!(&text[original.start..original.end], "hxxps://evil[.]com");
+
+        let today = refanged.text.find("today").unwrap();
+        let after = refanged.original_span(TextSpan {
+            start: today,
+            end: today + 5,
+        });
+        assert_eq!(&text[after.start..after.end], "today");
+        assert!(!refanged.touches_edit(TextSpan {
+            start: today,
+            end: today + 5,
+        }));
+    }
+}
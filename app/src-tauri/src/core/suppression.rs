@@ -0,0 +1,292 @@
+//! Suppression List
+//!
+//! Values an analyst never wants in the Hivemind: their own sock-puppet
+//! accounts, infrastructure they run, and noise particular to a case. Rules
+//! match exact values, domains (with their subdomains, emails and URLs),
+//! CIDR ranges (with the addresses inside) or regexes over canonical values.
+//! Extractions scoring below the list's `min_confidence` are dropped too.
+//!
+//! Like [`custom_extractors`](crate::core::custom_extractors), the list is
+//! stored in the open vault's settings and compiled into a process-wide
+//! registry when the vault opens.
+
+use crate::core::confidence::{host_of, is_within};
+use crate::core::entity::EntityType;
+use crate::core::normalize::canonicalize;
+use crate::core::validators::parse_cidr;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::RwLock;
+
+/// Store setting holding the [`SuppressionList`]
+pub const SUPPRESSION_SETTING: &str = "suppression_list";
+
+/// Compiled size limit per pattern, to keep pathological rules cheap
+const MAX_PATTERN_SIZE: usize = 1 << 20;
+
+/// Compiled list of the open vault
+static REGISTRY: RwLock<Option<CompiledSuppression>> = RwLock::new(None);
+
+/// A value, or family of values, to keep out of the Hivemind
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SuppressionRule {
+    /// One value, of `entity_type` or (without one) of any type
+    Exact {
+        value: String,
+        #[serde(default)]
+        entity_type: Option<EntityType>,
+    },
+    /// A domain and its subdomains, as domains, email hosts and URL hosts
+    Domain { domain: String },
+    /// An address range: the addresses and narrower ranges inside it
+    Cidr { cidr: String },
+    /// A regex matched against canonical values (case-insensitive)
+    Regex { pattern: String },
+}
+
+/// The persisted suppression list
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SuppressionList {
+    #[serde(default)]
+    pub rules: Vec<SuppressionRule>,
+    /// Extractions scoring below this are dropped (0 keeps everything)
+    #[serde(default)]
+    pub min_confidence: u8,
+}
+
+/// A list with its rules parsed
+#[derive(Debug, Clone)]
+pub struct CompiledSuppression {
+    exact: Vec<(Option<EntityType>, String)>,
+    domains: Vec<String>,
+    ranges: Vec<(IpAddr, u8)>,
+    patterns: Vec<Regex>,
+    min_confidence: u8,
+}
+
+impl SuppressionList {
+    /// Parse every rule, rejecting the list if any is malformed
+    pub fn compile(&self) -> Result<CompiledSuppression, String> {
+        let mut compiled = CompiledSuppression {
+            exact: Vec::new(),
+            domains: Vec::new(),
+            ranges: Vec::new(),
+            patterns: Vec::new(),
+            min_confidence: self.min_confidence.min(100),
+        };
+        for rule in &self.rules {
+            match rule {
+                SuppressionRule::Exact { value, entity_type } => {
+                    let value = match entity_type {
+                        Some(entity_type) => canonicalize(entity_type, value),
+                        None => value.trim().to_lowercase(),
+                    };
+                    if value.is_empty() {
+                        return Err("Suppressed value is empty".to_string());
+                    }
+                    compiled.exact.push((entity_type.clone(), value));
+                }
+                SuppressionRule::Domain { domain } => {
+                    let domain = canonicalize(&EntityType::Domain, domain);
+                    if domain.is_empty() || !domain.contains('.') {
+                        return Err(format!("'{}' is not a domain", domain));
+                    }
+                    compiled.domains.push(domain);
+                }
+                SuppressionRule::Cidr { cidr } => {
+                    let range = parse_cidr(cidr)
+                        .or_else(|| {
+                            // A bare address is a single-address range
+                            let ip: IpAddr = cidr.trim().parse().ok()?;
+                            Some((ip, if ip.is_ipv4() { 32 } else { 128 }))
+                        })
+                        .ok_or_else(|| format!("'{}' is not a CIDR range", cidr))?;
+                    compiled.ranges.push(range);
+                }
+                SuppressionRule::Regex { pattern } => {
+                    let regex = RegexBuilder::new(pattern)
+                        .case_insensitive(true)
+                        .size_limit(MAX_PATTERN_SIZE)
+                        .build()
+                        .map_err(|e| format!("Invalid suppression pattern: {}", e))?;
+                    compiled.patterns.push(regex);
+                }
+            }
+        }
+        Ok(compiled)
+    }
+}
+
+impl CompiledSuppression {
+    /// Whether a rule covers the canonical `value`
+    pub fn suppresses(&self, entity_type: &EntityType, value: &str) -> bool {
+        let lower = value.to_lowercase();
+        if self.exact.iter().any(|(t, v)| match t {
+            Some(t) => t == entity_type && v == value,
+            None => *v == lower,
+        }) {
+            return true;
+        }
+        if let Some(host) = host_of(entity_type, value) {
+            if self.domains.iter().any(|d| is_within(&host, d)) {
+                return true;
+            }
+        }
+        if let Some((ip, len)) = address_of(entity_type, value) {
+            if self
+                .ranges
+                .iter()
+                .any(|&(network, prefix)| prefix <= len && in_range(ip, network, prefix))
+            {
+                return true;
+            }
+        }
+        self.patterns.iter().any(|p| p.is_match(value))
+    }
+
+    /// Whether an extraction should be kept
+    pub fn allows(&self, entity_type: &EntityType, value: &str, confidence: u8) -> bool {
+        confidence >= self.min_confidence && !self.suppresses(entity_type, value)
+    }
+}
+
+/// The address and prefix length of an IP or CIDR entity
+fn address_of(entity_type: &EntityType, value: &str) -> Option<(IpAddr, u8)> {
+    match entity_type {
+        EntityType::IpV4 | EntityType::IpV6 => {
+            let ip: IpAddr = value.parse().ok()?;
+            Some((ip, if ip.is_ipv4() { 32 } else { 128 }))
+        }
+        EntityType::Cidr => parse_cidr(value),
+        _ => None,
+    }
+}
+
+/// Whether `ip` lies in `network/prefix`
+fn in_range(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Replace the registry with `list` (all-or-nothing)
+pub fn install(list: &SuppressionList) -> Result<(), String> {
+    let compiled = list.compile()?;
+    let mut registry = REGISTRY
+        .write()
+        .map_err(|e| format!("Suppression registry lock poisoned: {}", e))?;
+    *registry = Some(compiled);
+    Ok(())
+}
+
+/// Drop the installed list
+pub fn clear() {
+    if let Ok(mut registry) = REGISTRY.write() {
+        *registry = None;
+    }
+}
+
+/// Whether the open vault's list covers the canonical `value`
+pub fn is_suppressed(entity_type: &EntityType, value: &str) -> bool {
+    REGISTRY
+        .read()
+        .ok()
+        .and_then(|registry| {
+            registry
+                .as_ref()
+                .map(|list| list.suppresses(entity_type, value))
+        })
+        .unwrap_or(false)
+}
+
+/// Whether the open vault's list lets an extraction through
+pub fn allows(entity_type: &EntityType, value: &str, confidence: u8) -> bool {
+    REGISTRY
+        .read()
+        .ok()
+        .and_then(|registry| {
+            registry
+                .as_ref()
+                .map(|list| list.allows(entity_type, value, confidence))
+        })
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(rules: Vec<SuppressionRule>) -> CompiledSuppression {
+        SuppressionList {
+            rules,
+            min_confidence: 30,
+        }
+        .compile()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rules_match_related_values() {
+        let list = list(vec![
+            SuppressionRule::Exact {
+                value: "Sock.Puppet@Proton.me".to_string(),
+                entity_type: Some(EntityType::Email),
+            },
+            SuppressionRule::Domain {
+                domain: "MyInfra.net".to_string(),
+            },
+            SuppressionRule::Cidr {
+                cidr: "10.0.0.0/8".to_string(),
+            },
+            SuppressionRule::Regex {
+                pattern: r"^@bot_\w+$".to_string(),
+            },
+        ]);
+        use EntityType::*;
+        assert!(list.suppresses(&Email, "sock.puppet@proton.me"));
+        assert!(!list.suppresses(&Username, "sock.puppet@proton.me"));
+        assert!(list.suppresses(&Domain, "vpn.myinfra.net"));
+        assert!(list.suppresses(&Email, "ops@myinfra.net"));
+        assert!(list.suppresses(&Url, "https://myinfra.net/login"));
+        assert!(!list.suppresses(&Domain, "notmyinfra.net"));
+        assert!(list.suppresses(&IpV4, "10.20.30.40"));
+        assert!(list.suppresses(&Cidr, "10.1.0.0/16"));
+        assert!(!list.suppresses(&Cidr, "0.0.0.0/0"));
+        assert!(!list.suppresses(&IpV4, "11.0.0.1"));
+        assert!(list.suppresses(&Username, "@bot_alerts"));
+
+        assert!(!list.allows(&IpV4, "8.8.8.8", 20));
+        assert!(list.allows(&IpV4, "8.8.8.8", 90));
+    }
+
+    #[test]
+    fn test_bad_rules_are_rejected() {
+        let bad = |rule: SuppressionRule| {
+            SuppressionList {
+                rules: vec![rule],
+                min_confidence: 0,
+            }
+            .compile()
+            .is_err()
+        };
+        assert!(bad(SuppressionRule::Cidr {
+            cidr: "10.0.0.0/33".to_string()
+        }));
+        assert!(bad(SuppressionRule::Regex {
+            pattern: "(".to_string()
+        }));
+        assert!(bad(SuppressionRule::Domain {
+            domain: "localhost".to_string()
+        }));
+    }
+}
@@ -4,6 +4,7 @@
 //! All entities discovered by any identity are shared across the swarm.
 
 use crate::core::entity::EntityType;
+use crate::core::entity_extractor::ExtractionProgress;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -35,6 +36,12 @@ pub enum HivemindEvent {
     IdentityDisconnected {
         identity_id: String,
     },
+    /// Progress of a running text extraction
+    ExtractionProgress {
+        identity_id: String,
+        source_url: Option<String>,
+        progress: ExtractionProgress,
+    },
 }
 
 /// Cross-reference information
@@ -4,16 +4,18 @@
 //! - Timeline of all investigation actions (navigation, entity discovery, analysis)
 //! - Entity relationship graph (nodes = entities, edges = relationships)
 //! - Per-investigation isolation (multiple concurrent investigations)
+//! - Write-through persistence to the sled store (cases survive restarts)
 //! - Export to JSON for external visualization
 //!
 //! Jessica Jones v12 - "Every PI keeps a case file."
 
+use crate::core::dates::NormalizedDate;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::RwLock;
 
-/// Global investigation store
+/// Global investigation cache, mirrored to the sled store on every write
 static INVESTIGATIONS: RwLock<Option<HashMap<String, Investigation>>> = RwLock::new(None);
 
 // ─── Investigation ───────────────────────────────────────────────────
@@ -63,6 +65,9 @@ pub struct TimelineEvent {
     pub entity_hash: Option<String>,
     pub importance: u8,
     pub metadata: Option<HashMap<String, serde_json::Value>>,
+    /// When the event happened in the world, as opposed to when it was logged
+    #[serde(default)]
+    pub occurred_at: Option<NormalizedDate>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -82,6 +87,7 @@ pub enum TimelineEventType {
     ConnectionFound,
     EvidenceCollected,
     Hypothesis,
+    DateReference,
     Custom,
 }
 
@@ -197,6 +203,17 @@ impl Investigation {
             .collect()
     }
 
+    /// Events with a known real-world date, earliest first
+    pub fn chronology(&self) -> Vec<&TimelineEvent> {
+        let mut events: Vec<(DateTime<Utc>, &TimelineEvent)> = self
+            .timeline
+            .iter()
+            .filter_map(|e| Some((e.occurred_at.as_ref()?.start()?, e)))
+            .collect();
+        events.sort_by_key(|(start, _)| *start);
+        events.into_iter().map(|(_, e)| e).collect()
+    }
+
     /// Generate summary
     pub fn to_summary(&self) -> InvestigationSummary {
         InvestigationSummary {
@@ -241,6 +258,7 @@ pub fn parse_event_type(s: &str) -> TimelineEventType {
         "connection_found" => TimelineEventType::ConnectionFound,
         "evidence_collected" => TimelineEventType::EvidenceCollected,
         "hypothesis" => TimelineEventType::Hypothesis,
+        "date_reference" => TimelineEventType::DateReference,
         _ => TimelineEventType::Custom,
     }
 }
@@ -258,15 +276,50 @@ pub fn parse_status(s: &str) -> Result<InvestigationStatus, String> {
 
 // ─── Module Init & Global Access ─────────────────────────────────────
 
-/// Initialize the investigation module
+/// Initialize the investigation module.
+///
+/// Reloads every persisted case from the sled store, so storage must be
+/// initialized first.
 pub fn init() -> Result<(), Box<dyn std::error::Error>> {
-    tracing::info!("Investigation timeline module initialized");
+    let count = reload()?;
+
+    tracing::info!(
+        "Investigation timeline module initialized ({} cases loaded)",
+        count
+    );
+
+    Ok(())
+}
+
+/// Replace the in-memory cases with what is persisted in the sled store,
+/// e.g. after a restore. Returns the number of cases loaded.
+pub fn reload() -> Result<usize, String> {
+    let persisted = crate::storage::get_store()
+        .and_then(|store| store.get_all_investigations())
+        .map_err(|e| format!("Storage error: {}", e))?;
+    let count = persisted.len();
 
     let mut store = INVESTIGATIONS
         .write()
         .map_err(|e| format!("Investigation lock poisoned: {}", e))?;
-    *store = Some(HashMap::new());
+    *store = Some(
+        persisted
+            .into_iter()
+            .map(|inv| (inv.id.clone(), inv))
+            .collect(),
+    );
+
+    Ok(count)
+}
 
+/// Drop every cached case, e.g. when the open vault is closed.
+///
+/// Until the next [`init`], accessors fail with "not initialized".
+pub fn close() -> Result<(), String> {
+    let mut store = INVESTIGATIONS
+        .write()
+        .map_err(|e| format!("Investigation lock poisoned: {}", e))?;
+    *store = None;
     Ok(())
 }
 
@@ -282,7 +335,10 @@ where
     f(store)
 }
 
-/// Access investigations with write lock
+/// Access investigations with write lock.
+///
+/// After `f` succeeds, every investigation it created, modified (detected via
+/// `updated_at`) or removed is written through to the sled store.
 pub fn with_investigations_mut<F, R>(f: F) -> Result<R, String>
 where
     F: FnOnce(&mut HashMap<String, Investigation>) -> Result<R, String>,
@@ -291,5 +347,25 @@ where
         .write()
         .map_err(|e| format!("Investigation lock poisoned: {}", e))?;
     let store = guard.as_mut().ok_or("Investigations not initialized")?;
-    f(store)
+
+    let before: HashMap<String, DateTime<Utc>> = store
+        .iter()
+        .map(|(id, inv)| (id.clone(), inv.updated_at))
+        .collect();
+
+    let result = f(store)?;
+
+    let db = crate::storage::get_store().map_err(|e| format!("Storage error: {}", e))?;
+    for (id, inv) in store.iter() {
+        if before.get(id) != Some(&inv.updated_at) {
+            db.save_investigation(inv)
+                .map_err(|e| format!("Failed to persist investigation '{}': {}", id, e))?;
+        }
+    }
+    for id in before.keys().filter(|id| !store.contains_key(*id)) {
+        db.delete_investigation(id)
+            .map_err(|e| format!("Failed to delete investigation '{}': {}", id, e))?;
+    }
+
+    Ok(result)
 }
@@ -5,15 +5,18 @@
 //!
 //! "Every case starts with a question. Every answer leads to another."
 
+pub mod burn;
 pub mod cef;
 pub mod commands;
 pub mod core;
 pub mod hivemind;
 pub mod investigation;
 pub mod mcp;
+pub mod retention;
 pub mod session;
 pub mod storage;
 pub mod ui;
+pub mod vault;
 
 use std::path::PathBuf;
 use tracing::info;
@@ -23,8 +26,11 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 ///
 /// 1. Initialises logging.
 /// 2. Resolves the platform data directory (replaces `AppHandle::path()`).
-/// 3. Boots all backend modules (storage → CEF → session → hivemind → investigation → MCP).
-/// 4. Runs the iced GUI event loop.
+/// 3. Opens the case vault (storage → CEF → investigation), or leaves it to
+///    the GUI's unlock screen when no passphrase is in the environment.
+/// 4. Boots the remaining backend modules (session → hivemind → MCP →
+///    retention sweeper).
+/// 5. Runs the iced GUI event loop, then closes the vault.
 pub fn run() -> iced::Result {
     // ── Tracing ────────────────────────────────────────────────────────────
     tracing_subscriber::registry()
@@ -36,20 +42,72 @@ pub fn run() -> iced::Result {
 
     // ── Platform data directory ────────────────────────────────────────────
     // Replaces tauri::AppHandle::path().app_data_dir()
-    let data_dir: PathBuf = dirs::data_local_dir()
+    let app_dir: PathBuf = dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("spin");
 
-    std::fs::create_dir_all(&data_dir)
+    std::fs::create_dir_all(&app_dir)
         .expect("Failed to create Spin data directory");
 
-    info!("Data directory: {:?}", data_dir);
+    // ── Vault selection ────────────────────────────────────────────────────
+    // `--ephemeral` opens a throwaway in-memory vault; `--vault <name>` opens
+    // (creating if needed) a named vault; otherwise the last opened vault is
+    // used.
+    let args: Vec<String> = std::env::args().collect();
+    vault::init(&app_dir).expect("Failed to load vault registry");
+    let mut unlock = None;
+    if args.iter().any(|a| a == "--ephemeral") {
+        vault::open_ephemeral().expect("Failed to open ephemeral vault");
+    } else {
+        let vault_name = match args.iter().position(|a| a == "--vault") {
+            Some(i) => args.get(i + 1).cloned().expect("--vault needs a name"),
+            None => vault::last_opened().expect("Failed to read vault registry"),
+        };
+        if vault::vault_dir(&vault_name).is_err() {
+            vault::create(&vault_name).expect("Failed to create vault");
+        }
+        let data_dir = vault::vault_dir(&vault_name).expect("Failed to resolve vault");
 
-    // ── Storage (sled) ─────────────────────────────────────────────────────
-    storage::init(&data_dir).expect("Failed to initialise storage");
+        info!("Vault '{}', data directory: {:?}", vault_name, data_dir);
 
-    // ── CEF (Chromium Embedded Framework) ──────────────────────────────────
-    cef::init(&data_dir).expect("Failed to initialise CEF");
+        // `--migrate-dry-run` reports pending schema migrations and exits,
+        // reading the passphrase from `$SPIN_PASSPHRASE` or the terminal.
+        if args.iter().any(|a| a == "--migrate-dry-run") {
+            let passphrase =
+                storage::read_passphrase(&data_dir).expect("Failed to read store passphrase");
+            let report = storage::plan_migrations(&data_dir, &passphrase)
+                .expect("Failed to plan schema migrations");
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).unwrap_or_default()
+            );
+            std::process::exit(0);
+        }
+        // `--no-migration-backup` skips the copy taken before migrating.
+        let migration_options = storage::MigrationOptions {
+            backup: !args.iter().any(|a| a == "--no-migration-backup"),
+            ..Default::default()
+        };
+
+        // ── Storage (sled, encrypted at rest) ──────────────────────────────
+        // With `$SPIN_PASSPHRASE` set the vault is opened here; otherwise the
+        // GUI starts on its unlock screen and opens it from there. Opening
+        // unlocks the store, then initialises CEF and the investigation cache
+        // against the vault's directory.
+        match storage::env_passphrase().expect("Failed to read store passphrase") {
+            Some(passphrase) => {
+                vault::open(&vault_name, &passphrase, &migration_options)
+                    .expect("Failed to open vault");
+            }
+            None => {
+                unlock = Some(ui::state::UnlockState::new(
+                    vault_name,
+                    !data_dir.join("spin.db").exists(),
+                    migration_options,
+                ));
+            }
+        }
+    }
 
     // ── Session cloning ────────────────────────────────────────────────────
     session::init().expect("Failed to initialise session module");
@@ -57,16 +115,16 @@ pub fn run() -> iced::Result {
     // ── Hivemind (entity sync) ─────────────────────────────────────────────
     hivemind::init().expect("Failed to initialise Hivemind");
 
-    // ── Investigation timeline ─────────────────────────────────────────────
-    investigation::init().expect("Failed to initialise investigation module");
-
     // ── MCP / Claude API ───────────────────────────────────────────────────
     mcp::init().expect("Failed to initialise MCP");
 
+    // ── Retention sweeper ──────────────────────────────────────────────────
+    retention::init().expect("Failed to start retention sweeper");
+
     info!("All modules ready. Case file open.");
 
     // ── iced GUI ───────────────────────────────────────────────────────────
-    iced::application(
+    let result = iced::application(
         ui::app::SpinApp::title,
         ui::app::SpinApp::update,
         ui::app::SpinApp::view,
@@ -80,5 +138,13 @@ pub fn run() -> iced::Result {
         transparent: false,
         ..Default::default()
     })
-    .run_with(ui::app::SpinApp::new)
+    .run_with(move || ui::app::SpinApp::new(unlock));
+
+    // Flush the store and discard an ephemeral vault's scratch files.
+    retention::shutdown();
+    if let Err(e) = vault::close() {
+        tracing::warn!("Failed to close vault: {}", e);
+    }
+
+    result
 }
@@ -0,0 +1,575 @@
+//! Data Retention
+//!
+//! Time-based purge rules for collected entities and captured pages. A rule
+//! selects entity sources by entity type and discovering identity, and
+//! expires them once they are older than `max_age_days` — unless the entity
+//! is linked to a case whose status the rule protects (by default, `Active`
+//! cases).
+//!
+//! Sources expire individually: an entity seen by several identities loses
+//! only the expired sightings, and is deleted once none remain. Rules that
+//! name no entity type also expire the pages their identity captured, unless
+//! a protected case's timeline visited the page's URL. Every purge is written
+//! to the audit log.
+//!
+//! The policy is stored in the open vault's settings, so each vault keeps its
+//! own rules. A background sweeper applies it on the configured interval.
+
+use crate::core::entity::{Entity, EntityType};
+use crate::investigation::{Investigation, InvestigationStatus};
+use crate::storage::audit::{AuditAction, SYSTEM_ACTOR};
+use crate::storage::{self, CapturedPage, StorageError, Store};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Store setting holding the [`RetentionPolicy`]
+const POLICY_SETTING: &str = "retention_policy";
+
+/// How often the sweeper checks whether a sweep is due
+const TICK: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Whether the sweeper thread is running
+static SWEEPER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Outcome of the most recent sweep in this process
+static LAST_SWEEP: RwLock<Option<SweepReport>> = RwLock::new(None);
+
+// ─── Policy ──────────────────────────────────────────────────────────
+
+/// A vault's retention rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Whether the background sweeper enforces the rules
+    pub enabled: bool,
+    /// Minutes between automatic sweeps
+    pub sweep_interval_minutes: u64,
+    pub rules: Vec<RetentionRule>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sweep_interval_minutes: 60,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// One retention rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionRule {
+    pub id: String,
+    /// Entity types the rule covers; empty covers every type and captured
+    /// pages as well
+    #[serde(default)]
+    pub entity_types: Vec<EntityType>,
+    /// Only sources discovered by this identity; `None` covers every identity
+    #[serde(default)]
+    pub identity_id: Option<String>,
+    /// Age after which a source is purged
+    pub max_age_days: u32,
+    /// Entities linked to a case in one of these states are kept
+    #[serde(default = "default_keep_statuses")]
+    pub keep_statuses: Vec<InvestigationStatus>,
+}
+
+fn default_keep_statuses() -> Vec<InvestigationStatus> {
+    vec![InvestigationStatus::Active]
+}
+
+impl RetentionRule {
+    fn covers(&self, entity_type: &EntityType, identity_id: &str) -> bool {
+        (self.entity_types.is_empty() || self.entity_types.contains(entity_type))
+            && self
+                .identity_id
+                .as_deref()
+                .is_none_or(|id| id == identity_id)
+    }
+
+    fn covers_pages(&self, identity_id: &str) -> bool {
+        self.entity_types.is_empty()
+            && self
+                .identity_id
+                .as_deref()
+                .is_none_or(|id| id == identity_id)
+    }
+
+    fn expired(&self, now: DateTime<Utc>, seen: DateTime<Utc>) -> bool {
+        now - seen > Duration::days(self.max_age_days as i64)
+    }
+
+    fn protects(&self, linked: &[InvestigationStatus]) -> bool {
+        linked.iter().any(|s| self.keep_statuses.contains(s))
+    }
+}
+
+/// Read the open vault's policy (the default when none has been saved)
+pub fn get_policy(store: &dyn Store) -> Result<RetentionPolicy, StorageError> {
+    match store.get_setting(POLICY_SETTING)? {
+        Some(value) => Ok(serde_json::from_value(value)?),
+        None => Ok(RetentionPolicy::default()),
+    }
+}
+
+/// Replace the open vault's policy
+pub fn set_policy(store: &dyn Store, policy: &RetentionPolicy) -> Result<(), StorageError> {
+    store.set_setting(POLICY_SETTING, &serde_json::to_value(policy)?)
+}
+
+// ─── Sweep ───────────────────────────────────────────────────────────
+
+/// An entity touched by a sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgedEntity {
+    pub entity_hash: String,
+    pub entity_type: EntityType,
+    pub sources_removed: usize,
+    /// Whether the whole entity was deleted
+    pub deleted: bool,
+    /// Rules that expired at least one source
+    pub rule_ids: Vec<String>,
+}
+
+/// A captured page deleted by a sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgedPage {
+    pub page_id: String,
+    pub identity_id: String,
+    pub url: String,
+    pub rule_ids: Vec<String>,
+}
+
+/// Outcome of a sweep
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepReport {
+    pub swept_at: DateTime<Utc>,
+    pub dry_run: bool,
+    pub entities_scanned: usize,
+    pub purged: Vec<PurgedEntity>,
+    pub pages_scanned: usize,
+    pub purged_pages: Vec<PurgedPage>,
+}
+
+/// Apply `policy` to every entity and captured page in `store` as of `now`.
+///
+/// With `dry_run` set, nothing is changed and the report lists what would be
+/// purged.
+pub fn sweep(
+    store: &dyn Store,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<SweepReport, StorageError> {
+    let mut report = SweepReport {
+        swept_at: now,
+        dry_run,
+        entities_scanned: 0,
+        purged: Vec::new(),
+        pages_scanned: 0,
+        purged_pages: Vec::new(),
+    };
+    if policy.rules.is_empty() {
+        return Ok(report);
+    }
+
+    let investigations = store.get_all_investigations()?;
+
+    for mut entity in store.get_all_entities()? {
+        report.entities_scanned += 1;
+        let linked = linked_statuses(&entity, &investigations);
+        let entity_type = entity.entity_type.clone();
+
+        let mut rule_ids: Vec<String> = Vec::new();
+        let before = entity.sources.len();
+        entity.sources.retain(|source| {
+            let expired_by: Vec<&RetentionRule> = policy
+                .rules
+                .iter()
+                .filter(|rule| rule.covers(&entity_type, &source.identity_id))
+                .filter(|rule| rule.expired(now, source.timestamp))
+                .filter(|rule| !rule.protects(&linked))
+                .collect();
+            for rule in &expired_by {
+                if !rule_ids.contains(&rule.id) {
+                    rule_ids.push(rule.id.clone());
+                }
+            }
+            expired_by.is_empty()
+        });
+
+        let sources_removed = before - entity.sources.len();
+        if sources_removed == 0 {
+            continue;
+        }
+        let deleted = entity.sources.is_empty();
+
+        if !dry_run {
+            if deleted {
+                store.delete_entity(&entity.hash)?;
+            } else {
+                entity.occurrence_count = entity
+                    .occurrence_count
+                    .saturating_sub(sources_removed as u32)
+                    .max(1);
+                if let Some(first) = entity.sources.iter().map(|s| s.timestamp).min() {
+                    entity.first_seen = first;
+                }
+                if let Some(last) = entity.sources.iter().map(|s| s.timestamp).max() {
+                    entity.last_seen = last;
+                }
+                store.save_entity(&entity)?;
+            }
+            store.append_audit(
+                SYSTEM_ACTOR,
+                AuditAction::EntityPurged {
+                    entity_hash: entity.hash.clone(),
+                    entity_type: entity.entity_type.clone(),
+                    sources_removed,
+                    deleted,
+                    rule_ids: rule_ids.clone(),
+                },
+            )?;
+            tracing::info!(
+                "Retention purged {} source(s) of {:?} entity {}{} (rules: {})",
+                sources_removed,
+                entity.entity_type,
+                entity.hash,
+                if deleted { ", entity deleted" } else { "" },
+                rule_ids.join(", ")
+            );
+        }
+
+        report.purged.push(PurgedEntity {
+            entity_hash: entity.hash,
+            entity_type: entity.entity_type,
+            sources_removed,
+            deleted,
+            rule_ids,
+        });
+    }
+
+    sweep_pages(store, policy, now, dry_run, &investigations, &mut report)?;
+    Ok(report)
+}
+
+/// Delete captured pages that rules covering pages have expired
+fn sweep_pages(
+    store: &dyn Store,
+    policy: &RetentionPolicy,
+    now: DateTime<Utc>,
+    dry_run: bool,
+    investigations: &[Investigation],
+    report: &mut SweepReport,
+) -> Result<(), StorageError> {
+    for page in store.get_all_pages()? {
+        report.pages_scanned += 1;
+        let linked = page_statuses(&page, investigations);
+        let rule_ids: Vec<String> = policy
+            .rules
+            .iter()
+            .filter(|rule| rule.covers_pages(&page.identity_id))
+            .filter(|rule| rule.expired(now, page.captured_at))
+            .filter(|rule| !rule.protects(&linked))
+            .map(|rule| rule.id.clone())
+            .collect();
+        if rule_ids.is_empty() {
+            continue;
+        }
+
+        if !dry_run {
+            store.delete_page(&page.id)?;
+            store.append_audit(
+                SYSTEM_ACTOR,
+                AuditAction::PagePurged {
+                    page_id: page.id.clone(),
+                    identity_id: page.identity_id.clone(),
+                    rule_ids: rule_ids.clone(),
+                },
+            )?;
+            tracing::info!(
+                "Retention purged page {} captured by {} (rules: {})",
+                page.id,
+                page.identity_id,
+                rule_ids.join(", ")
+            );
+        }
+
+        report.purged_pages.push(PurgedPage {
+            page_id: page.id,
+            identity_id: page.identity_id,
+            url: page.url,
+            rule_ids,
+        });
+    }
+    Ok(())
+}
+
+/// Statuses of the cases an entity appears in, via a timeline event's
+/// `entity_hash` or a graph node whose id or value is the entity
+fn linked_statuses(entity: &Entity, investigations: &[Investigation]) -> Vec<InvestigationStatus> {
+    investigations
+        .iter()
+        .filter(|inv| {
+            inv.timeline
+                .iter()
+                .any(|event| event.entity_hash.as_deref() == Some(entity.hash.as_str()))
+                || inv
+                    .graph
+                    .nodes
+                    .iter()
+                    .any(|node| node.id == entity.hash || node.value == entity.value)
+        })
+        .map(|inv| inv.status.clone())
+        .collect()
+}
+
+/// Statuses of the cases whose timeline records a visit to a page's URL
+fn page_statuses(
+    page: &CapturedPage,
+    investigations: &[Investigation],
+) -> Vec<InvestigationStatus> {
+    investigations
+        .iter()
+        .filter(|inv| {
+            inv.timeline
+                .iter()
+                .any(|event| event.url.as_deref() == Some(page.url.as_str()))
+        })
+        .map(|inv| inv.status.clone())
+        .collect()
+}
+
+// ─── Sweeper ─────────────────────────────────────────────────────────
+
+/// Start the background sweeper (once per process).
+///
+/// Each tick it re-reads the open vault's policy, so vault switches and
+/// policy edits take effect without a restart.
+pub fn init() -> Result<(), Box<dyn std::error::Error>> {
+    if SWEEPER_RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    std::thread::Builder::new()
+        .name("retention-sweeper".to_string())
+        .spawn(|| {
+            let mut last_run: HashMap<String, DateTime<Utc>> = HashMap::new();
+            while SWEEPER_RUNNING.load(Ordering::SeqCst) {
+                if let Err(e) = tick(&mut last_run) {
+                    tracing::warn!("Retention sweep failed: {}", e);
+                }
+                std::thread::sleep(TICK);
+            }
+        })?;
+
+    tracing::info!("Retention sweeper started");
+    Ok(())
+}
+
+/// Stop the background sweeper after its current tick
+pub fn shutdown() {
+    SWEEPER_RUNNING.store(false, Ordering::SeqCst);
+}
+
+/// Sweep the open vault if its policy is enabled and a sweep is due.
+///
+/// `last_run` is keyed by data directory so each vault keeps its own clock.
+fn tick(last_run: &mut HashMap<String, DateTime<Utc>>) -> Result<(), StorageError> {
+    let (store, data_dir) = match (storage::get_store(), storage::data_dir()) {
+        (Ok(store), Ok(data_dir)) => (store, data_dir.to_string_lossy().to_string()),
+        _ => return Ok(()),
+    };
+
+    let policy = get_policy(store.as_ref())?;
+    if !policy.enabled {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let interval = Duration::minutes(policy.sweep_interval_minutes.max(1) as i64);
+    if last_run
+        .get(&data_dir)
+        .is_some_and(|last| now - *last < interval)
+    {
+        return Ok(());
+    }
+    last_run.insert(data_dir, now);
+
+    run(store.as_ref(), &policy, false).map(|_| ())
+}
+
+/// Sweep now and remember the report
+pub fn run(
+    store: &dyn Store,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+) -> Result<SweepReport, StorageError> {
+    let report = sweep(store, policy, Utc::now(), dry_run)?;
+    if !dry_run {
+        tracing::info!(
+            "Retention sweep: {} entities scanned, {} purged; {} pages scanned, {} purged",
+            report.entities_scanned,
+            report.purged.len(),
+            report.pages_scanned,
+            report.purged_pages.len()
+        );
+        if let Ok(mut last) = LAST_SWEEP.write() {
+            *last = Some(report.clone());
+        }
+    }
+    Ok(report)
+}
+
+/// Report of the most recent (non-dry-run) sweep in this process
+pub fn last_sweep() -> Option<SweepReport> {
+    LAST_SWEEP.read().ok().and_then(|last| last.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entity::EntitySource;
+    use crate::storage::MemoryStore;
+
+    fn source(identity_id: &str, age_days: i64) -> EntitySource {
+        EntitySource {
+            timestamp: Utc::now() - Duration::days(age_days),
+            ..EntitySource::new(identity_id)
+        }
+    }
+
+    fn rule(entity_types: Vec<EntityType>, identity_id: Option<&str>) -> RetentionRule {
+        RetentionRule {
+            id: "pii-30d".to_string(),
+            entity_types,
+            identity_id: identity_id.map(str::to_string),
+            max_age_days: 30,
+            keep_statuses: default_keep_statuses(),
+        }
+    }
+
+    #[test]
+    fn test_expired_sources_are_purged_per_identity() {
+        let store = MemoryStore::new();
+        let mut entity = Entity::new(
+            EntityType::Email,
+            "a@example.com".to_string(),
+            source("dupe", 40),
+        );
+        entity.sources.push(source("prime", 40));
+        store.save_entity(&entity).unwrap();
+
+        let policy = RetentionPolicy {
+            rules: vec![rule(vec![EntityType::Email], Some("dupe"))],
+            ..Default::default()
+        };
+        let report = sweep(&store, &policy, Utc::now(), false).unwrap();
+        assert_eq!(report.purged.len(), 1);
+        assert!(!report.purged[0].deleted);
+
+        let kept = store.get_entity(&entity.hash).unwrap().unwrap();
+        assert_eq!(kept.unique_sources(), vec!["prime".to_string()]);
+        assert_eq!(store.audit_log().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_entities_on_active_cases_are_kept() {
+        let store = MemoryStore::new();
+        let entity = Entity::new(
+            EntityType::Email,
+            "a@example.com".to_string(),
+            source("prime", 40),
+        );
+        store.save_entity(&entity).unwrap();
+
+        let mut case = Investigation::new("Case".to_string(), String::new());
+        case.graph.nodes.push(crate::investigation::GraphNode {
+            id: entity.hash.clone(),
+            node_type: "entity".to_string(),
+            label: entity.value.clone(),
+            value: entity.value.clone(),
+            entity_type: None,
+            color: None,
+            metadata: None,
+        });
+        store.save_investigation(&case).unwrap();
+
+        let policy = RetentionPolicy {
+            rules: vec![rule(Vec::new(), None)],
+            ..Default::default()
+        };
+        assert!(sweep(&store, &policy, Utc::now(), false)
+            .unwrap()
+            .purged
+            .is_empty());
+
+        case.status = InvestigationStatus::Closed;
+        store.save_investigation(&case).unwrap();
+        let report = sweep(&store, &policy, Utc::now(), false).unwrap();
+        assert!(report.purged[0].deleted);
+        assert!(store.get_entity(&entity.hash).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_expired_pages_are_purged_unless_on_active_cases() {
+        let store = MemoryStore::new();
+        let page = |identity_id: &str, url: &str, age_days: i64| {
+            let mut page = CapturedPage::new(
+                identity_id.to_string(),
+                url.to_string(),
+                "Title".to_string(),
+                "needle".to_string(),
+            );
+            page.captured_at = Utc::now() - Duration::days(age_days);
+            store.save_page(&page).unwrap();
+            page
+        };
+        let old = page("dupe", "https://example.com/old", 40);
+        let fresh = page("dupe", "https://example.com/fresh", 5);
+        let other = page("prime", "https://example.com/other", 40);
+        let visited = page("dupe", "https://example.com/case", 40);
+
+        let mut case = Investigation::new("Case".to_string(), String::new());
+        case.timeline.push(crate::investigation::TimelineEvent {
+            id: "visit".to_string(),
+            investigation_id: case.id.clone(),
+            event_type: crate::investigation::TimelineEventType::PageVisit,
+            title: "Visit".to_string(),
+            description: String::new(),
+            identity_id: "dupe".to_string(),
+            url: Some(visited.url.clone()),
+            entity_hash: None,
+            importance: 1,
+            metadata: None,
+            occurred_at: None,
+            created_at: Utc::now(),
+        });
+        store.save_investigation(&case).unwrap();
+
+        let policy = RetentionPolicy {
+            rules: vec![
+                rule(Vec::new(), Some("dupe")),
+                RetentionRule {
+                    id: "emails".to_string(),
+                    ..rule(vec![EntityType::Email], None)
+                },
+            ],
+            ..Default::default()
+        };
+        let report = sweep(&store, &policy, Utc::now(), false).unwrap();
+        assert_eq!(report.pages_scanned, 4);
+        assert_eq!(report.purged_pages.len(), 1);
+        assert_eq!(report.purged_pages[0].page_id, old.id);
+        assert_eq!(report.purged_pages[0].rule_ids, vec!["pii-30d".to_string()]);
+
+        assert!(store.get_page(&old.id).unwrap().is_none());
+        for kept in [&fresh, &other, &visited] {
+            assert!(store.get_page(&kept.id).unwrap().is_some());
+        }
+        assert_eq!(store.search("needle", 10).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,275 @@
+//! Audit Log
+//!
+//! An append-only record of every data mutation, kept apart from the
+//! user-editable investigation timeline. Each record carries a sequence
+//! number, timestamp, acting identity and a SHA-256 link to the previous
+//! record, so any edit, deletion or reordering breaks the chain.
+//!
+//! The store also keeps the hash of the newest record (the head), which
+//! catches records dropped from the end. Exports carry the head hash so it
+//! can be anchored outside the vault (e.g. in case notes or an email).
+
+use crate::core::entity::EntityType;
+use crate::storage::backup::RestoreScope;
+use crate::storage::{get_store, StorageError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Actor recorded for mutations made by the app itself (e.g. retention)
+pub const SYSTEM_ACTOR: &str = "system";
+
+/// `prev_hash` of the first record
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What a record attests to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum AuditAction {
+    EntityAdded {
+        entity_hash: String,
+        entity_type: EntityType,
+    },
+    /// A further source was attached to an existing entity
+    EntitySighted {
+        entity_hash: String,
+        source_count: usize,
+    },
+    EntitiesCleared,
+    IdentityCreated {
+        identity_id: String,
+        name: String,
+    },
+    IdentityDestroyed {
+        identity_id: String,
+    },
+    /// Identity burned; details are in the destruction receipt
+    IdentityBurned {
+        identity_id: String,
+        receipt_id: String,
+    },
+    SessionCloned {
+        source_identity_id: String,
+        target_identity_id: String,
+    },
+    SessionImported {
+        target_identity_id: String,
+    },
+    SessionCleared {
+        identity_id: String,
+    },
+    InvestigationEventAdded {
+        investigation_id: String,
+        event_id: String,
+    },
+    /// The key itself is never recorded
+    ApiKeyChanged {
+        provider: String,
+    },
+    /// Sources removed by a retention rule
+    EntityPurged {
+        entity_hash: String,
+        entity_type: EntityType,
+        sources_removed: usize,
+        /// Whether the whole entity was deleted
+        deleted: bool,
+        rule_ids: Vec<String>,
+    },
+    /// Captured page deleted by a retention rule
+    PagePurged {
+        page_id: String,
+        identity_id: String,
+        rule_ids: Vec<String>,
+    },
+    BackupRestored {
+        archive: String,
+        scope: RestoreScope,
+    },
+}
+
+/// One link in the audit chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Position in the log, starting at 0
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Identity the mutation was performed as
+    pub actor: String,
+    #[serde(flatten)]
+    pub action: AuditAction,
+    /// `hash` of the previous record, or [`GENESIS_HASH`]
+    pub prev_hash: String,
+    /// Hex SHA-256 over every other field
+    pub hash: String,
+}
+
+/// The fields covered by a record's hash, in a fixed order
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    seq: u64,
+    timestamp: &'a DateTime<Utc>,
+    actor: &'a str,
+    action: &'a AuditAction,
+    prev_hash: &'a str,
+}
+
+impl AuditRecord {
+    /// Build the record that follows `prev` (or the first record)
+    pub fn next(prev: Option<&AuditRecord>, actor: &str, action: AuditAction) -> Self {
+        let (seq, prev_hash) = match prev {
+            Some(prev) => (prev.seq + 1, prev.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let mut record = Self {
+            seq,
+            timestamp: Utc::now(),
+            actor: actor.to_string(),
+            action,
+            prev_hash,
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash();
+        record
+    }
+
+    /// Recompute the hash from the record's contents
+    pub fn compute_hash(&self) -> String {
+        let fields = HashedFields {
+            seq: self.seq,
+            timestamp: &self.timestamp,
+            actor: &self.actor,
+            action: &self.action,
+            prev_hash: &self.prev_hash,
+        };
+        let mut hasher = Sha256::new();
+        hasher.update(serde_json::to_vec(&fields).unwrap_or_default());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// Result of checking the chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub record_count: usize,
+    /// Hash of the newest record
+    pub head_hash: Option<String>,
+    /// First record that failed, if any
+    pub failed_seq: Option<u64>,
+    pub failure: Option<String>,
+}
+
+/// Check every link in `records` (in sequence order) against `head`, the
+/// newest record hash the store holds.
+pub fn verify_chain(records: &[AuditRecord], head: Option<&str>) -> AuditVerification {
+    let failure = find_break(records, head);
+    AuditVerification {
+        valid: failure.is_none(),
+        record_count: records.len(),
+        head_hash: records.last().map(|r| r.hash.clone()),
+        failed_seq: failure.as_ref().map(|(seq, _)| *seq),
+        failure: failure.map(|(_, reason)| reason),
+    }
+}
+
+/// First broken link in the chain, as `(seq, reason)`
+fn find_break(records: &[AuditRecord], head: Option<&str>) -> Option<(u64, String)> {
+    let mut prev_hash = GENESIS_HASH;
+    for (position, record) in records.iter().enumerate() {
+        if record.seq != position as u64 {
+            return Some((
+                record.seq,
+                format!("Expected record {}, found {}", position, record.seq),
+            ));
+        }
+        if record.prev_hash != prev_hash {
+            return Some((record.seq, "Link to previous record is broken".to_string()));
+        }
+        if record.compute_hash() != record.hash {
+            return Some((
+                record.seq,
+                "Record contents do not match its hash".to_string(),
+            ));
+        }
+        prev_hash = &record.hash;
+    }
+
+    if records.last().map(|r| r.hash.as_str()) != head {
+        let seq = records.last().map_or(0, |r| r.seq + 1);
+        return Some((seq, "Log ends before the recorded head".to_string()));
+    }
+    None
+}
+
+/// Portable copy of the log written by [`export`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditExport {
+    pub exported_at: DateTime<Utc>,
+    pub verification: AuditVerification,
+    pub records: Vec<AuditRecord>,
+}
+
+/// Record a mutation performed as the active identity
+pub fn record(action: AuditAction) -> Result<AuditRecord, StorageError> {
+    let store = get_store()?;
+    let actor = store.get_active_identity()?.id;
+    store.append_audit(&actor, action)
+}
+
+/// Record a mutation performed as `actor`
+pub fn record_as(actor: &str, action: AuditAction) -> Result<AuditRecord, StorageError> {
+    get_store()?.append_audit(actor, action)
+}
+
+/// Verify the open store's log and bundle it for export
+pub fn export() -> Result<AuditExport, StorageError> {
+    let store = get_store()?;
+    let records = store.audit_log()?;
+    let verification = verify_chain(&records, store.audit_head()?.as_deref());
+    Ok(AuditExport {
+        exported_at: Utc::now(),
+        verification,
+        records,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(len: usize) -> Vec<AuditRecord> {
+        let mut records: Vec<AuditRecord> = Vec::new();
+        for i in 0..len {
+            let action = AuditAction::IdentityDestroyed {
+                identity_id: format!("dupe-{}", i),
+            };
+            records.push(AuditRecord::next(records.last(), "prime", action));
+        }
+        records
+    }
+
+    #[test]
+    fn test_intact_chain_verifies() {
+        let records = chain(3);
+        let head = records[2].hash.clone();
+        assert!(verify_chain(&records, Some(&head)).valid);
+        assert!(verify_chain(&[], None).valid);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let records = chain(3);
+        let head = records[2].hash.clone();
+
+        let mut edited = records.clone();
+        edited[1].actor = "someone-else".to_string();
+        assert_eq!(verify_chain(&edited, Some(&head)).failed_seq, Some(1));
+
+        let mut removed = records.clone();
+        removed.remove(1);
+        assert_eq!(verify_chain(&removed, Some(&head)).failed_seq, Some(2));
+
+        let truncated = &records[..2];
+        assert_eq!(verify_chain(truncated, Some(&head)).failed_seq, Some(2));
+    }
+}
@@ -0,0 +1,818 @@
+//! Encrypted Backups
+//!
+//! A backup is one archive holding a snapshot of the store and the browser
+//! profile directories (`<data_dir>/cef/contexts/<identity_id>`) of the
+//! selected identities.
+//!
+//! ```text
+//! "SPINBAK1" | u32 BE header length | header JSON | frame*
+//! frame = u32 BE length | sealed(u64 BE index | kind | payload)
+//! ```
+//!
+//! The header holds the KDF parameters for the archive passphrase. Frames are
+//! sealed with AES-256-GCM and numbered, so none can be dropped or reordered.
+//! The last frame is the manifest: it lists every file with its SHA-256 and
+//! pins the header. A restore checks all of it before touching live data.
+//!
+//! A snapshot taken at an older schema version is migrated before it is
+//! restored, so its entities are keyed the way the live store's are.
+//!
+//! A restore keeps the decrypted store snapshot in memory only. Profile files
+//! have to be staged on disk before they are swapped in; the staging directory
+//! and the live profiles they replace are shredded like a burned identity's.
+
+use crate::cef::browser_context::shred_dir;
+use crate::core::entity::Entity;
+use crate::core::identity::Identity;
+use crate::investigation::Investigation;
+use crate::storage::crypto::{KdfParams, StoreCipher};
+use crate::storage::migrations::{self, SCHEMA_VERSION};
+use crate::storage::search::CapturedPage;
+use crate::storage::{StorageError, Store};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use zeroize::Zeroizing;
+
+const MAGIC: &[u8; 8] = b"SPINBAK1";
+const FORMAT_VERSION: u32 = 1;
+
+/// Plaintext bytes per data frame
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Upper bound on a sealed frame (data chunks are far smaller; the manifest
+/// grows with the file count), to reject corrupt length prefixes early
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+const FRAME_FILE_START: u8 = 1;
+const FRAME_DATA: u8 = 2;
+const FRAME_FILE_END: u8 = 3;
+const FRAME_MANIFEST: u8 = 4;
+
+/// Archive path of the store snapshot
+const STORE_ENTRY: &str = "store.json";
+
+/// Archive path prefix of identity browser profiles
+const PROFILES_PREFIX: &str = "cef/contexts";
+
+/// What to put in a backup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BackupOptions {
+    /// Identities whose browser profiles are archived (`None` = all)
+    pub identities: Option<Vec<String>>,
+    /// Also archive browser caches, which are usually large and disposable
+    pub include_cache: bool,
+}
+
+/// What to restore from a backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum RestoreScope {
+    /// Replace the whole store and every archived profile
+    Everything,
+    /// One identity: its record, session, captured pages and profile
+    Identity { identity_id: String },
+    /// One investigation, plus any entities its timeline refers to that
+    /// are missing from the live store
+    Investigation { investigation_id: String },
+}
+
+/// Unencrypted archive header
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveHeader {
+    format_version: u32,
+    kdf: KdfParams,
+}
+
+/// A file stored in an archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    /// `/`-separated path relative to the data directory
+    pub path: String,
+    pub size: u64,
+    /// Hex SHA-256 of the content
+    pub sha256: String,
+}
+
+/// Final, authenticated description of an archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub schema_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub identities: Vec<String>,
+    pub investigations: Vec<String>,
+    pub profiles: Vec<String>,
+    pub files: Vec<ArchivedFile>,
+    /// Frames preceding the manifest
+    pub frame_count: u64,
+    /// Hex SHA-256 of the header bytes
+    pub header_sha256: String,
+}
+
+/// Outcome of a restore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub scope: RestoreScope,
+    pub backup_created_at: DateTime<Utc>,
+    pub identities_restored: Vec<String>,
+    pub investigations_restored: Vec<String>,
+    pub entities_restored: usize,
+    pub profiles_restored: Vec<String>,
+}
+
+/// A captured identity session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub identity_id: String,
+    pub data: Vec<u8>,
+}
+
+/// Decrypted, point-in-time copy of every record in the store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreSnapshot {
+    pub schema_version: u32,
+    pub active_identity: Option<String>,
+    pub identities: Vec<Identity>,
+    pub entities: Vec<Entity>,
+    pub sessions: Vec<SessionRecord>,
+    pub investigations: Vec<Investigation>,
+    pub pages: Vec<CapturedPage>,
+    /// Absent from archives written before settings were stored
+    #[serde(default)]
+    pub settings: BTreeMap<String, serde_json::Value>,
+}
+
+/// Write an encrypted backup of the store and identity profiles to
+/// `archive_path`.
+///
+/// The store is snapshotted with writers blocked; the archive is written
+/// beside the target and renamed into place once complete.
+pub fn create_backup(
+    store: &dyn Store,
+    data_dir: &Path,
+    archive_path: &Path,
+    passphrase: &str,
+    options: &BackupOptions,
+) -> Result<BackupManifest, StorageError> {
+    let snapshot = store.snapshot()?;
+    write_backup(&snapshot, data_dir, archive_path, passphrase, options)
+}
+
+/// Write `snapshot` and the selected profiles to an archive at
+/// `archive_path`
+fn write_backup(
+    snapshot: &StoreSnapshot,
+    data_dir: &Path,
+    archive_path: &Path,
+    passphrase: &str,
+    options: &BackupOptions,
+) -> Result<BackupManifest, StorageError> {
+    let profiles = select_profiles(data_dir, options)?;
+
+    let kdf = KdfParams::generate();
+    let cipher = StoreCipher::derive(passphrase, &kdf)?;
+    let header = serde_json::to_vec(&ArchiveHeader {
+        format_version: FORMAT_VERSION,
+        kdf,
+    })?;
+
+    let mut partial_path = archive_path.as_os_str().to_owned();
+    partial_path.push(".partial");
+    let partial_path = PathBuf::from(partial_path);
+    let mut out = BufWriter::new(File::create(&partial_path)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&(header.len() as u32).to_be_bytes())?;
+    out.write_all(&header)?;
+
+    let mut writer = ArchiveWriter {
+        out,
+        cipher,
+        index: 0,
+        files: Vec::new(),
+    };
+
+    writer.add_file(STORE_ENTRY, serde_json::to_vec(snapshot)?.as_slice())?;
+    for identity_id in &profiles {
+        let root = data_dir.join(PROFILES_PREFIX).join(identity_id);
+        for relative in walk_files(&root, options.include_cache)? {
+            let path = format!("{}/{}/{}", PROFILES_PREFIX, identity_id, relative);
+            writer.add_file(&path, BufReader::new(File::open(root.join(&relative))?))?;
+        }
+    }
+
+    let manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        schema_version: snapshot.schema_version,
+        created_at: Utc::now(),
+        identities: snapshot.identities.iter().map(|i| i.id.clone()).collect(),
+        investigations: snapshot
+            .investigations
+            .iter()
+            .map(|i| i.id.clone())
+            .collect(),
+        profiles,
+        files: writer.files.clone(),
+        frame_count: writer.index,
+        header_sha256: sha256_hex(&header),
+    };
+    writer.frame(FRAME_MANIFEST, &serde_json::to_vec(&manifest)?)?;
+
+    let mut out = writer.out;
+    out.flush()?;
+    out.get_ref().sync_all()?;
+    drop(out);
+    std::fs::rename(&partial_path, archive_path)?;
+
+    tracing::info!(
+        "Backup written to {:?} ({} files, {} profiles)",
+        archive_path,
+        manifest.files.len(),
+        manifest.profiles.len()
+    );
+    Ok(manifest)
+}
+
+/// Read and verify just the manifest of an archive
+pub fn inspect_backup(
+    archive_path: &Path,
+    passphrase: &str,
+) -> Result<BackupManifest, StorageError> {
+    extract(archive_path, passphrase, None, None, |_| false)
+}
+
+/// Restore `scope` from an encrypted backup.
+///
+/// The archive is fully decrypted and checked against its manifest first;
+/// nothing live is modified if any check fails. `release_profile` is called
+/// for every identity whose profile is about to be replaced, before anything
+/// live changes, so its browser can be torn down.
+pub fn restore_backup<R>(
+    store: &dyn Store,
+    data_dir: &Path,
+    archive_path: &Path,
+    passphrase: &str,
+    scope: &RestoreScope,
+    release_profile: R,
+) -> Result<RestoreReport, StorageError>
+where
+    R: Fn(&str) -> Result<(), String>,
+{
+    let staging = tempdir_in(data_dir)?;
+    let result = restore_from(
+        store,
+        data_dir,
+        archive_path,
+        passphrase,
+        scope,
+        &staging,
+        release_profile,
+    );
+    if let Err(e) = shred_dir(&staging) {
+        tracing::warn!("Failed to shred restore staging {:?}: {}", staging, e);
+    }
+    result
+}
+
+fn restore_from<R>(
+    store: &dyn Store,
+    data_dir: &Path,
+    archive_path: &Path,
+    passphrase: &str,
+    scope: &RestoreScope,
+    staging: &Path,
+    release_profile: R,
+) -> Result<RestoreReport, StorageError>
+where
+    R: Fn(&str) -> Result<(), String>,
+{
+    let mut store_json = Zeroizing::new(Vec::new());
+    let manifest = extract(
+        archive_path,
+        passphrase,
+        Some(staging),
+        Some(&mut store_json),
+        |path| match scope {
+            RestoreScope::Everything => true,
+            RestoreScope::Identity { identity_id } => profile_of(path) == Some(identity_id),
+            RestoreScope::Investigation { .. } => false,
+        },
+    )?;
+    if !manifest.files.iter().any(|f| f.path == STORE_ENTRY) {
+        return Err(StorageError::Backup(
+            "Archive has no store snapshot".to_string(),
+        ));
+    }
+
+    let mut snapshot: StoreSnapshot = serde_json::from_slice(&store_json)?;
+    if snapshot.schema_version > SCHEMA_VERSION {
+        return Err(StorageError::SchemaTooNew {
+            found: snapshot.schema_version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    if snapshot.schema_version < SCHEMA_VERSION {
+        tracing::info!(
+            "Upgrading backup snapshot from schema v{} to v{}",
+            snapshot.schema_version,
+            SCHEMA_VERSION
+        );
+        migrations::upgrade_snapshot(&mut snapshot);
+    }
+
+    let profiles: Vec<String> = match scope {
+        RestoreScope::Everything => manifest.profiles.clone(),
+        RestoreScope::Identity { identity_id } if manifest.profiles.contains(identity_id) => {
+            vec![identity_id.clone()]
+        }
+        _ => Vec::new(),
+    };
+    for identity_id in &profiles {
+        release_profile(identity_id).map_err(|e| {
+            StorageError::Backup(format!(
+                "Failed to release the profile of '{}': {}",
+                identity_id, e
+            ))
+        })?;
+    }
+
+    let mut report = RestoreReport {
+        scope: scope.clone(),
+        backup_created_at: manifest.created_at,
+        identities_restored: Vec::new(),
+        investigations_restored: Vec::new(),
+        entities_restored: 0,
+        profiles_restored: Vec::new(),
+    };
+
+    match scope {
+        RestoreScope::Everything => {
+            store.restore_snapshot(&snapshot)?;
+            report.identities_restored = manifest.identities.clone();
+            report.investigations_restored = manifest.investigations.clone();
+            report.entities_restored = snapshot.entities.len();
+        }
+        RestoreScope::Identity { identity_id } => {
+            let identity = snapshot
+                .identities
+                .iter()
+                .find(|i| &i.id == identity_id)
+                .ok_or_else(|| StorageError::NotFound(identity_id.clone()))?;
+            store.save_identity(identity)?;
+            if let Some(session) = snapshot
+                .sessions
+                .iter()
+                .find(|s| &s.identity_id == identity_id)
+            {
+                store.save_session(identity_id, &session.data)?;
+            }
+            for page in snapshot
+                .pages
+                .iter()
+                .filter(|p| &p.identity_id == identity_id)
+            {
+                store.save_page(page)?;
+            }
+            report.identities_restored.push(identity_id.clone());
+        }
+        RestoreScope::Investigation { investigation_id } => {
+            let investigation = snapshot
+                .investigations
+                .iter()
+                .find(|i| &i.id == investigation_id)
+                .ok_or_else(|| StorageError::NotFound(investigation_id.clone()))?;
+            for hash in investigation
+                .timeline
+                .iter()
+                .filter_map(|e| e.entity_hash.as_ref())
+            {
+                if store.get_entity(hash)?.is_some() {
+                    continue;
+                }
+                if let Some(entity) = snapshot.entities.iter().find(|e| &e.hash == hash) {
+                    store.save_entity(entity)?;
+                    report.entities_restored += 1;
+                }
+            }
+            store.save_investigation(investigation)?;
+            report
+                .investigations_restored
+                .push(investigation_id.clone());
+        }
+    }
+
+    for identity_id in profiles {
+        replace_profile(staging, data_dir, &identity_id)?;
+        report.profiles_restored.push(identity_id);
+    }
+
+    store.flush()?;
+    Ok(report)
+}
+
+/// Where the content of a file being extracted goes
+enum Destination {
+    Discard,
+    Staging(File),
+    Memory,
+}
+
+/// Decrypt an archive, writing files accepted by `keep` into `staging` and
+/// the store snapshot into `store_entry`, and verify every frame and file
+/// against the manifest
+fn extract<F: Fn(&str) -> bool>(
+    archive_path: &Path,
+    passphrase: &str,
+    staging: Option<&Path>,
+    mut store_entry: Option<&mut Zeroizing<Vec<u8>>>,
+    keep: F,
+) -> Result<BackupManifest, StorageError> {
+    let mut input = BufReader::new(File::open(archive_path)?);
+
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(StorageError::Backup(
+            "Not a Spin backup archive".to_string(),
+        ));
+    }
+    let mut len = [0u8; 4];
+    input.read_exact(&mut len)?;
+    let mut header_bytes = vec![0u8; u32::from_be_bytes(len) as usize];
+    input.read_exact(&mut header_bytes)?;
+    let header: ArchiveHeader = serde_json::from_slice(&header_bytes)?;
+    if header.format_version != FORMAT_VERSION {
+        return Err(StorageError::Backup(format!(
+            "Unsupported archive format v{}",
+            header.format_version
+        )));
+    }
+
+    let mut reader = ArchiveReader {
+        input,
+        cipher: StoreCipher::derive(passphrase, &header.kdf)?,
+        index: 0,
+    };
+
+    let mut files = Vec::new();
+    let mut current: Option<(String, Destination, Sha256, u64)> = None;
+
+    loop {
+        let (kind, payload) = reader
+            .next_frame()?
+            .ok_or_else(|| StorageError::Backup("Archive is truncated".to_string()))?;
+        match (kind, current.as_mut()) {
+            (FRAME_FILE_START, None) => {
+                let path = String::from_utf8(payload)
+                    .map_err(|_| StorageError::Backup("Invalid file path".to_string()))?;
+                let relative = checked_relative(&path)?;
+                let destination = match staging {
+                    _ if path == STORE_ENTRY && store_entry.is_some() => Destination::Memory,
+                    Some(staging) if keep(&path) => {
+                        let target = staging.join(relative);
+                        if let Some(parent) = target.parent() {
+                            std::fs::create_dir_all(parent)?;
+                        }
+                        Destination::Staging(File::create(&target)?)
+                    }
+                    _ => Destination::Discard,
+                };
+                current = Some((path, destination, Sha256::new(), 0));
+            }
+            (FRAME_DATA, Some((_, destination, hasher, size))) => {
+                match destination {
+                    Destination::Discard => {}
+                    Destination::Staging(file) => file.write_all(&payload)?,
+                    Destination::Memory => {
+                        if let Some(buffer) = store_entry.as_mut() {
+                            buffer.extend_from_slice(&payload);
+                        }
+                    }
+                }
+                hasher.update(&payload);
+                *size += payload.len() as u64;
+            }
+            (FRAME_FILE_END, Some(_)) => {
+                if let Some((path, _, hasher, size)) = current.take() {
+                    files.push(ArchivedFile {
+                        path,
+                        size,
+                        sha256: format!("{:x}", hasher.finalize()),
+                    });
+                }
+            }
+            (FRAME_MANIFEST, None) => {
+                let frame_count = reader.index - 1;
+                let manifest: BackupManifest = serde_json::from_slice(&payload)?;
+                if reader.next_frame()?.is_some() {
+                    return Err(StorageError::Backup("Data after manifest".to_string()));
+                }
+                if manifest.frame_count != frame_count
+                    || manifest.header_sha256 != sha256_hex(&header_bytes)
+                    || manifest.files != files
+                {
+                    return Err(StorageError::Backup(
+                        "Archive does not match its manifest".to_string(),
+                    ));
+                }
+                return Ok(manifest);
+            }
+            _ => {
+                return Err(StorageError::Backup(format!(
+                    "Unexpected frame {} (kind {})",
+                    reader.index - 1,
+                    kind
+                )))
+            }
+        }
+    }
+}
+
+struct ArchiveWriter<W: Write> {
+    out: W,
+    cipher: StoreCipher,
+    index: u64,
+    files: Vec<ArchivedFile>,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    fn frame(&mut self, kind: u8, payload: &[u8]) -> Result<(), StorageError> {
+        let mut plaintext = Vec::with_capacity(9 + payload.len());
+        plaintext.extend_from_slice(&self.index.to_be_bytes());
+        plaintext.push(kind);
+        plaintext.extend_from_slice(payload);
+
+        let sealed = self.cipher.seal(&plaintext)?;
+        self.out.write_all(&(sealed.len() as u32).to_be_bytes())?;
+        self.out.write_all(&sealed)?;
+        self.index += 1;
+        Ok(())
+    }
+
+    fn add_file<R: Read>(&mut self, path: &str, mut content: R) -> Result<(), StorageError> {
+        self.frame(FRAME_FILE_START, path.as_bytes())?;
+
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let mut chunk = vec![0u8; CHUNK_SIZE];
+        loop {
+            let n = read_full(&mut content, &mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&chunk[..n]);
+            size += n as u64;
+            self.frame(FRAME_DATA, &chunk[..n])?;
+        }
+
+        self.frame(FRAME_FILE_END, &[])?;
+        self.files.push(ArchivedFile {
+            path: path.to_string(),
+            size,
+            sha256: format!("{:x}", hasher.finalize()),
+        });
+        Ok(())
+    }
+}
+
+struct ArchiveReader<R: Read> {
+    input: R,
+    cipher: StoreCipher,
+    index: u64,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    /// Next frame, or `None` at a clean end of input
+    fn next_frame(&mut self) -> Result<Option<(u8, Vec<u8>)>, StorageError> {
+        let mut len = [0u8; 4];
+        match self.input.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(StorageError::Backup("Corrupt frame length".to_string()));
+        }
+        let mut sealed = vec![0u8; len];
+        self.input.read_exact(&mut sealed)?;
+
+        let plaintext = match self.cipher.open(&sealed) {
+            Ok(plaintext) => plaintext,
+            // The first frame failing to open means the key is wrong
+            Err(_) if self.index == 0 => return Err(StorageError::WrongPassphrase),
+            Err(e) => return Err(e),
+        };
+        if plaintext.len() < 9 || plaintext[..8] != self.index.to_be_bytes() {
+            return Err(StorageError::Backup(format!(
+                "Frame {} is out of order",
+                self.index
+            )));
+        }
+        self.index += 1;
+        Ok(Some((plaintext[8], plaintext[9..].to_vec())))
+    }
+}
+
+/// Identity IDs whose profile directories go into the backup
+fn select_profiles(data_dir: &Path, options: &BackupOptions) -> Result<Vec<String>, StorageError> {
+    let contexts = data_dir.join(PROFILES_PREFIX);
+    let mut available = Vec::new();
+    if contexts.is_dir() {
+        for entry in std::fs::read_dir(&contexts)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                available.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+    }
+    available.sort();
+
+    Ok(match &options.identities {
+        None => available,
+        Some(wanted) => available
+            .into_iter()
+            .filter(|id| wanted.contains(id))
+            .collect(),
+    })
+}
+
+/// Every regular file under `root`, as sorted `/`-separated relative paths
+fn walk_files(root: &Path, include_cache: bool) -> Result<Vec<String>, StorageError> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in std::fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if include_cache || path != Path::new("cache") {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() {
+                let parts: Vec<String> = path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                files.push(parts.join("/"));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Swap an identity's live profile directory for the staged copy, shredding
+/// the one it replaces
+fn replace_profile(staging: &Path, data_dir: &Path, identity_id: &str) -> Result<(), StorageError> {
+    let staged = staging.join(PROFILES_PREFIX).join(identity_id);
+    let live = data_dir.join(PROFILES_PREFIX).join(identity_id);
+    shred_dir(&live)?;
+    if let Some(parent) = live.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    if staged.exists() {
+        std::fs::rename(&staged, &live)?;
+    } else {
+        std::fs::create_dir_all(&live)?;
+    }
+    Ok(())
+}
+
+/// Identity a profile file belongs to, from its archive path
+fn profile_of(path: &str) -> Option<&str> {
+    path.strip_prefix(PROFILES_PREFIX)?
+        .strip_prefix('/')?
+        .split('/')
+        .next()
+}
+
+/// Reject archive paths that could escape the staging directory
+fn checked_relative(path: &str) -> Result<PathBuf, StorageError> {
+    let relative = PathBuf::from(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(StorageError::Backup(format!(
+            "Unsafe path in archive: {}",
+            path
+        )));
+    }
+    Ok(relative)
+}
+
+/// Fresh, empty scratch directory under `parent`
+fn tempdir_in(parent: &Path) -> Result<PathBuf, StorageError> {
+    let dir = parent.join(format!(".spin-restore-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Fill `buf` as far as the reader allows
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entity::{EntitySource, EntityType};
+    use crate::investigation::GraphNode;
+    use crate::storage::MemoryStore;
+
+    #[test]
+    fn test_checked_relative_rejects_escapes() {
+        assert!(checked_relative("cef/contexts/prime/cookies.db").is_ok());
+        assert!(checked_relative("../spin.db").is_err());
+        assert!(checked_relative("/etc/passwd").is_err());
+        assert!(checked_relative("cef/./x").is_ok());
+        assert!(checked_relative("").is_err());
+    }
+
+    #[test]
+    fn test_profile_of() {
+        assert_eq!(profile_of("cef/contexts/prime/cookies.db"), Some("prime"));
+        assert_eq!(profile_of(STORE_ENTRY), None);
+    }
+
+    #[test]
+    fn test_older_snapshot_is_migrated_on_restore() {
+        let data_dir = std::env::temp_dir().join(format!("spin-backup-v2-{}", std::process::id()));
+        std::fs::create_dir_all(&data_dir).unwrap();
+
+        // Before v3, values were stored and hashed as written
+        let mut phone = Entity::new(
+            EntityType::Phone,
+            "555-123-4567".to_string(),
+            EntitySource::new("prime"),
+        );
+        let canonical_hash = phone.hash.clone();
+        phone.value = "555-123-4567".to_string();
+        phone.hash = Entity::compute_hash(&EntityType::Phone, &phone.value);
+        phone.aliases.clear();
+        let mut investigation = Investigation::new("case".to_string(), String::new());
+        investigation.add_node(GraphNode {
+            id: phone.hash.clone(),
+            node_type: "entity".to_string(),
+            label: phone.value.clone(),
+            value: phone.value.clone(),
+            entity_type: Some("Phone".to_string()),
+            color: None,
+            metadata: None,
+        });
+
+        let old = MemoryStore::new();
+        old.save_entity(&phone).unwrap();
+        old.save_investigation(&investigation).unwrap();
+        let mut snapshot = old.snapshot().unwrap();
+        snapshot.schema_version = 2;
+        let archive = data_dir.join("v2.spinbak");
+        write_backup(
+            &snapshot,
+            &data_dir,
+            &archive,
+            "archive passphrase",
+            &BackupOptions::default(),
+        )
+        .unwrap();
+
+        let store = MemoryStore::new();
+        restore_backup(
+            &store,
+            &data_dir,
+            &archive,
+            "archive passphrase",
+            &RestoreScope::Everything,
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert!(store.get_entity(&phone.hash).unwrap().is_none());
+        let restored = store.get_entity(&canonical_hash).unwrap().unwrap();
+        assert_eq!(restored.value, "+15551234567");
+        assert_eq!(restored.aliases, vec!["555-123-4567"]);
+        let restored = store.get_investigation(&investigation.id).unwrap().unwrap();
+        assert_eq!(restored.graph.nodes[0].id, canonical_hash);
+
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+//! Encryption at Rest
+//!
+//! Every value written to the sled store is sealed with AES-256-GCM under a
+//! key derived from the user's passphrase with Argon2id. The salt, KDF
+//! parameters and a sealed verifier live in the plaintext `meta` tree so a
+//! wrong passphrase is rejected before any record is touched.
+//!
+//! Tree keys cannot be sealed, since lookups and prefix scans need them to be
+//! stable. Where a key would name collected data (an entity hash, type, tag
+//! or the identity that found it), it is an HMAC-SHA256 under a random
+//! [`TreeKeys`] key that is itself sealed in `meta`, so a copy of the
+//! database cannot be checked for a guessed selector without the passphrase.
+
+use crate::storage::StorageError;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// Length of the AES-GCM nonce prepended to every sealed value
+const NONCE_LEN: usize = 12;
+
+/// Known plaintext sealed into `meta` to check a passphrase
+const VERIFIER_PLAINTEXT: &[u8] = b"spin-store-verifier-v1";
+
+/// Length of a keyed tree key component
+pub const TREE_KEY_LEN: usize = 32;
+
+/// Argon2id parameters, persisted alongside the salt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    /// Number of passes
+    pub iterations: u32,
+    /// Degree of parallelism
+    pub parallelism: u32,
+    /// Base64 salt
+    pub salt: String,
+}
+
+impl KdfParams {
+    /// Fresh parameters with a random 16-byte salt
+    pub fn generate() -> Self {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        Self {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+            salt: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, salt),
+        }
+    }
+}
+
+/// Symmetric cipher for store values
+pub struct StoreCipher {
+    cipher: Aes256Gcm,
+}
+
+impl StoreCipher {
+    /// Derive the store key from a passphrase
+    pub fn derive(passphrase: &str, params: &KdfParams) -> Result<Self, StorageError> {
+        let salt = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &params.salt)
+            .map_err(|e| StorageError::Crypto(format!("Invalid KDF salt: {}", e)))?;
+        let argon_params = Params::new(
+            params.memory_kib,
+            params.iterations,
+            params.parallelism,
+            Some(32),
+        )
+        .map_err(|e| StorageError::Crypto(format!("Invalid KDF parameters: {}", e)))?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| StorageError::Crypto(format!("Key derivation failed: {}", e)))?;
+
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref())
+            .map_err(|e| StorageError::Crypto(format!("Invalid key: {}", e)))?;
+        Ok(Self { cipher })
+    }
+
+    /// Encrypt a value. Output is `nonce || ciphertext || tag`.
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| StorageError::Crypto("Encryption failed".to_string()))?;
+
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value produced by [`StoreCipher::seal`]
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(StorageError::Crypto("Sealed value too short".to_string()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                StorageError::Crypto("Decryption failed (wrong key or tampered data)".to_string())
+            })
+    }
+
+    /// Seal the known verifier plaintext
+    pub fn make_verifier(&self) -> Result<Vec<u8>, StorageError> {
+        self.seal(VERIFIER_PLAINTEXT)
+    }
+
+    /// Check a verifier produced by [`StoreCipher::make_verifier`]
+    pub fn check_verifier(&self, verifier: &[u8]) -> Result<(), StorageError> {
+        match self.open(verifier) {
+            Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => Ok(()),
+            _ => Err(StorageError::WrongPassphrase),
+        }
+    }
+}
+
+/// Key for the keyed hashes that stand in for collected data in tree keys.
+///
+/// It is random rather than derived from the passphrase, so changing the
+/// passphrase only re-seals it and every tree key stays valid.
+pub struct TreeKeys {
+    key: Zeroizing<[u8; TREE_KEY_LEN]>,
+}
+
+impl TreeKeys {
+    /// A fresh random key
+    pub fn generate() -> Self {
+        let mut key = Zeroizing::new([0u8; TREE_KEY_LEN]);
+        rand::thread_rng().fill_bytes(key.as_mut());
+        Self { key }
+    }
+
+    /// Seal the key for storage in `meta`
+    pub fn seal(&self, cipher: &StoreCipher) -> Result<Vec<u8>, StorageError> {
+        cipher.seal(self.key.as_ref())
+    }
+
+    /// Open a key sealed with [`TreeKeys::seal`]
+    pub fn open(cipher: &StoreCipher, sealed: &[u8]) -> Result<Self, StorageError> {
+        let plaintext = Zeroizing::new(cipher.open(sealed)?);
+        let mut key = Zeroizing::new([0u8; TREE_KEY_LEN]);
+        if plaintext.len() != TREE_KEY_LEN {
+            return Err(StorageError::Crypto("Malformed tree key".to_string()));
+        }
+        key.copy_from_slice(&plaintext);
+        Ok(Self { key })
+    }
+
+    /// Keyed hash of `value`. `domain` separates the uses, so the same
+    /// string hashed as a tag and as an identity id gives unrelated keys.
+    pub fn derive(&self, domain: &str, value: &str) -> [u8; TREE_KEY_LEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.key.as_ref())
+            .expect("HMAC takes keys of any length");
+        mac.update(domain.as_bytes());
+        mac.update(&[0]);
+        mac.update(value.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+}
+
+impl Default for TreeKeys {
+    fn default() -> Self {
+        Self::generate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 64,
+            iterations: 1,
+            ..KdfParams::generate()
+        }
+    }
+
+    #[test]
+    fn test_seal_roundtrip() {
+        let cipher = StoreCipher::derive("correct horse", &test_params()).unwrap();
+        let sealed = cipher.seal(b"sock puppet cookies").unwrap();
+        assert_ne!(&sealed[NONCE_LEN..], b"sock puppet cookies");
+        assert_eq!(cipher.open(&sealed).unwrap(), b"sock puppet cookies");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_rejected() {
+        let params = test_params();
+        let verifier = StoreCipher::derive("correct horse", &params)
+            .unwrap()
+            .make_verifier()
+            .unwrap();
+        let wrong = StoreCipher::derive("battery staple", &params).unwrap();
+        assert!(matches!(
+            wrong.check_verifier(&verifier),
+            Err(StorageError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn test_tree_keys_survive_resealing() {
+        let keys = TreeKeys::generate();
+        let old = StoreCipher::derive("correct horse", &test_params()).unwrap();
+        let new = StoreCipher::derive("battery staple", &test_params()).unwrap();
+        let sealed = keys.seal(&old).unwrap();
+        let resealed = TreeKeys::open(&old, &sealed).unwrap().seal(&new).unwrap();
+        let reopened = TreeKeys::open(&new, &resealed).unwrap();
+
+        let key = keys.derive("entity", "Email:alice@example.com");
+        assert_eq!(reopened.derive("entity", "Email:alice@example.com"), key);
+        assert_ne!(keys.derive("tag", "Email:alice@example.com"), key);
+        assert_ne!(
+            TreeKeys::generate().derive("entity", "Email:alice@example.com"),
+            key
+        );
+    }
+}
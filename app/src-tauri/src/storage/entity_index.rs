@@ -0,0 +1,186 @@
+//! Entity Secondary Indexes
+//!
+//! Key layout for `entities` and the index trees maintained alongside it.
+//! `K(x)` is the store's keyed hash ([`TreeKeys`]), so no key names an entity,
+//! type, tag or identity in plaintext. Every index entry is a key with an
+//! empty value:
+//!
+//! - `entities`             `<K(entity hash)>` (the entity key)
+//! - `idx_entity_type`      `<K(type)><entity key>`
+//! - `idx_entity_tag`       `<K(lowercase tag)><entity key>`
+//! - `idx_entity_identity`  `<K(identity id)><entity key>`
+//! - `idx_entity_last_seen` `<u64 BE micros><entity key>`
+
+use crate::core::entity::{Entity, EntityType};
+use crate::storage::crypto::{TreeKeys, TREE_KEY_LEN};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Which secondary index to query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityFilter {
+    /// Entities of one type
+    Type(EntityType),
+    /// Entities carrying a tag (case-insensitive)
+    Tag(String),
+    /// Entities discovered by an identity
+    Identity(String),
+}
+
+/// One page of an index query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityPage {
+    pub entities: Vec<Entity>,
+    /// Opaque cursor for the next page; `None` when exhausted
+    pub next_cursor: Option<String>,
+}
+
+/// All index keys an entity occupies
+pub(super) struct EntityIndexKeys {
+    pub by_type: Vec<u8>,
+    pub by_tag: Vec<Vec<u8>>,
+    pub by_identity: Vec<Vec<u8>>,
+    pub by_last_seen: Vec<u8>,
+}
+
+impl EntityIndexKeys {
+    pub fn for_entity(entity: &Entity, keys: &TreeKeys) -> Self {
+        let entity_key = entity_key(keys, &entity.hash);
+        let mut by_tag: Vec<Vec<u8>> = entity
+            .tags
+            .iter()
+            .map(|tag| {
+                entry_key(
+                    &prefix_for(&EntityFilter::Tag(tag.clone()), keys),
+                    &entity_key,
+                )
+            })
+            .collect();
+        by_tag.sort();
+        by_tag.dedup();
+
+        let by_identity = entity
+            .unique_sources()
+            .into_iter()
+            .map(|id| entry_key(&prefix_for(&EntityFilter::Identity(id), keys), &entity_key))
+            .collect();
+
+        Self {
+            by_type: entry_key(
+                &prefix_for(&EntityFilter::Type(entity.entity_type.clone()), keys),
+                &entity_key,
+            ),
+            by_tag,
+            by_identity,
+            by_last_seen: entry_key(&time_prefix(entity.last_seen), &entity_key),
+        }
+    }
+}
+
+/// Key of an entity's record in `entities`
+pub(super) fn entity_key(keys: &TreeKeys, hash: &str) -> Vec<u8> {
+    keys.derive("entity", hash).to_vec()
+}
+
+/// Key prefix shared by every entry matching `filter`
+pub(super) fn prefix_for(filter: &EntityFilter, keys: &TreeKeys) -> Vec<u8> {
+    let derived = match filter {
+        EntityFilter::Type(entity_type) => keys.derive(
+            "type",
+            &serde_json::to_string(entity_type).unwrap_or_else(|_| format!("{:?}", entity_type)),
+        ),
+        EntityFilter::Tag(tag) => keys.derive("tag", &tag.trim().to_lowercase()),
+        EntityFilter::Identity(identity_id) => keys.derive("identity", identity_id),
+    };
+    derived.to_vec()
+}
+
+/// Length of every prefix from [`prefix_for`]
+pub(super) const PREFIX_LEN: usize = TREE_KEY_LEN;
+
+/// Order-preserving 8-byte encoding of a timestamp
+pub(super) fn time_prefix(timestamp: DateTime<Utc>) -> Vec<u8> {
+    ((timestamp.timestamp_micros() as u64) ^ (1 << 63))
+        .to_be_bytes()
+        .to_vec()
+}
+
+/// Full index key for an entity under `prefix`
+pub(super) fn entry_key(prefix: &[u8], entity_key: &[u8]) -> Vec<u8> {
+    let mut key = prefix.to_vec();
+    key.extend_from_slice(entity_key);
+    key
+}
+
+/// Entity key stored at the end of an index key
+pub(super) fn entity_key_from(key: &[u8], prefix_len: usize) -> &[u8] {
+    &key[prefix_len..]
+}
+
+/// Encode an index key as a page cursor
+pub(super) fn encode_cursor(key: &[u8]) -> String {
+    base64::Engine::encode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, key)
+}
+
+/// Decode a page cursor back into an index key
+pub(super) fn decode_cursor(cursor: &str) -> Option<Vec<u8>> {
+    base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, cursor).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_time_prefix_orders_bytewise() {
+        let before_epoch = Utc.timestamp_millis_opt(-1_000).unwrap();
+        let epoch = Utc.timestamp_millis_opt(0).unwrap();
+        let now = Utc::now();
+        assert!(time_prefix(before_epoch) < time_prefix(epoch));
+        assert!(time_prefix(epoch) < time_prefix(now));
+    }
+
+    #[test]
+    fn test_tag_prefix_is_case_insensitive() {
+        let keys = TreeKeys::generate();
+        assert_eq!(
+            prefix_for(&EntityFilter::Tag("Suspect ".to_string()), &keys),
+            prefix_for(&EntityFilter::Tag("suspect".to_string()), &keys)
+        );
+    }
+
+    #[test]
+    fn test_keys_do_not_name_entities() {
+        let keys = TreeKeys::generate();
+        let mut entity = Entity::new(
+            EntityType::Email,
+            "alice@example.com".to_string(),
+            crate::core::entity::EntitySource::new("sockpuppet"),
+        );
+        entity.tags.push("suspect".to_string());
+        let index = EntityIndexKeys::for_entity(&entity, &keys);
+
+        let plaintext = [
+            entity.hash.as_bytes(),
+            b"alice@example.com",
+            b"sockpuppet",
+            b"suspect",
+            b"Email",
+        ];
+        let all_keys = [&index.by_type, &index.by_last_seen]
+            .into_iter()
+            .chain(&index.by_tag)
+            .chain(&index.by_identity);
+        for key in all_keys {
+            assert_eq!(
+                entity_key_from(key, key.len() - TREE_KEY_LEN),
+                entity_key(&keys, &entity.hash)
+            );
+            for needle in plaintext {
+                assert!(!key.windows(needle.len()).any(|w| w == needle));
+            }
+        }
+    }
+}
@@ -0,0 +1,524 @@
+//! In-Memory Store
+//!
+//! A [`Store`] that never touches disk. Used for ephemeral "burn after use"
+//! sessions and for exercising commands in tests. Everything lives behind a
+//! single lock and is dropped with the store; session blobs are zeroed on
+//! drop.
+//!
+//! Entity indexes use the same key layout as the sled trees (see
+//! [`entity_index`]), so page cursors behave identically on both backends.
+
+use crate::core::entity::Entity;
+use crate::core::identity::Identity;
+use crate::investigation::Investigation;
+use crate::storage::audit::{AuditAction, AuditRecord};
+use crate::storage::backup::{SessionRecord, StoreSnapshot};
+use crate::storage::crypto::TreeKeys;
+use crate::storage::entity_index::{self, EntityFilter, EntityIndexKeys, EntityPage};
+use crate::storage::migrations::SCHEMA_VERSION;
+use crate::storage::search::{CapturedPage, SearchDoc, SearchHit, SearchIndex};
+use crate::storage::store::Store;
+use crate::storage::StorageError;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use zeroize::Zeroizing;
+
+/// Store backed by process memory
+pub struct MemoryStore {
+    state: RwLock<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    identities: BTreeMap<String, Identity>,
+    active_identity: Option<String>,
+    settings: BTreeMap<String, serde_json::Value>,
+    /// Keys the entity indexes the same way the sled store does
+    keys: TreeKeys,
+    /// Entities by entity key
+    entities: BTreeMap<Vec<u8>, Entity>,
+    idx_entity_type: BTreeSet<Vec<u8>>,
+    idx_entity_tag: BTreeSet<Vec<u8>>,
+    idx_entity_identity: BTreeSet<Vec<u8>>,
+    idx_entity_last_seen: BTreeSet<Vec<u8>>,
+    sessions: BTreeMap<String, Zeroizing<Vec<u8>>>,
+    investigations: BTreeMap<String, Investigation>,
+    pages: BTreeMap<String, CapturedPage>,
+    audit: Vec<AuditRecord>,
+    search: SearchIndex,
+}
+
+impl MemoryStore {
+    /// Create an empty store holding only the Prime identity
+    pub fn new() -> Self {
+        let prime = Identity::prime();
+        let mut state = MemoryState {
+            active_identity: Some(prime.id.clone()),
+            ..Default::default()
+        };
+        state.identities.insert(prime.id.clone(), prime);
+        Self {
+            state: RwLock::new(state),
+        }
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, MemoryState>, StorageError> {
+        self.state.read().map_err(|_| StorageError::LockPoisoned)
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, MemoryState>, StorageError> {
+        self.state.write().map_err(|_| StorageError::LockPoisoned)
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryState {
+    fn index_entity(&mut self, entity: &Entity) {
+        let key = entity_index::entity_key(&self.keys, &entity.hash);
+        if let Some(previous) = self.entities.insert(key, entity.clone()) {
+            self.unindex_entity(&previous);
+        }
+
+        let keys = EntityIndexKeys::for_entity(entity, &self.keys);
+        self.idx_entity_type.insert(keys.by_type);
+        self.idx_entity_tag.extend(keys.by_tag);
+        self.idx_entity_identity.extend(keys.by_identity);
+        self.idx_entity_last_seen.insert(keys.by_last_seen);
+        self.search.index_entity(entity);
+    }
+
+    fn unindex_entity(&mut self, entity: &Entity) {
+        let keys = EntityIndexKeys::for_entity(entity, &self.keys);
+        self.idx_entity_type.remove(&keys.by_type);
+        for key in &keys.by_tag {
+            self.idx_entity_tag.remove(key);
+        }
+        for key in &keys.by_identity {
+            self.idx_entity_identity.remove(key);
+        }
+        self.idx_entity_last_seen.remove(&keys.by_last_seen);
+    }
+
+    fn set_investigation(&mut self, investigation: &Investigation) {
+        self.search.remove_timeline(&investigation.id);
+        for event in &investigation.timeline {
+            self.search.index_timeline_event(event);
+        }
+        self.investigations
+            .insert(investigation.id.clone(), investigation.clone());
+    }
+
+    /// Load up to `limit` entities named by a stream of index keys
+    fn collect_page<'a, I>(&self, keys: I, prefix_len: usize, limit: usize) -> EntityPage
+    where
+        I: Iterator<Item = &'a Vec<u8>>,
+    {
+        let mut entities = Vec::new();
+        let mut last_key = None;
+        let mut has_more = false;
+
+        for key in keys {
+            if entities.len() == limit {
+                has_more = true;
+                break;
+            }
+            let entity_key = entity_index::entity_key_from(key, prefix_len);
+            if let Some(entity) = self.entities.get(entity_key) {
+                entities.push(entity.clone());
+            }
+            last_key = Some(key);
+        }
+
+        EntityPage {
+            entities,
+            next_cursor: if has_more {
+                last_key.map(|k| entity_index::encode_cursor(k))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl Store for MemoryStore {
+    // ============ Identity Operations ============
+
+    fn save_identity(&self, identity: &Identity) -> Result<(), StorageError> {
+        self.write()?
+            .identities
+            .insert(identity.id.clone(), identity.clone());
+        Ok(())
+    }
+
+    fn get_identity(&self, id: &str) -> Result<Option<Identity>, StorageError> {
+        Ok(self.read()?.identities.get(id).cloned())
+    }
+
+    fn get_all_identities(&self) -> Result<Vec<Identity>, StorageError> {
+        Ok(self.read()?.identities.values().cloned().collect())
+    }
+
+    fn delete_identity(&self, id: &str) -> Result<(), StorageError> {
+        self.write()?.identities.remove(id);
+        Ok(())
+    }
+
+    // ============ Config Operations ============
+
+    fn set_active_identity(&self, id: &str) -> Result<(), StorageError> {
+        self.write()?.active_identity = Some(id.to_string());
+        Ok(())
+    }
+
+    fn get_active_identity(&self) -> Result<Identity, StorageError> {
+        let state = self.read()?;
+        let active_id = state.active_identity.as_deref().unwrap_or("prime");
+        state
+            .identities
+            .get(active_id)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(active_id.to_string()))
+    }
+
+    fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>, StorageError> {
+        Ok(self.read()?.settings.get(key).cloned())
+    }
+
+    fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), StorageError> {
+        self.write()?
+            .settings
+            .insert(key.to_string(), value.clone());
+        Ok(())
+    }
+
+    fn delete_setting(&self, key: &str) -> Result<(), StorageError> {
+        self.write()?.settings.remove(key);
+        Ok(())
+    }
+
+    /// Always the current schema: nothing older was ever written
+    fn schema_version(&self) -> Result<Option<u32>, StorageError> {
+        Ok(Some(SCHEMA_VERSION))
+    }
+
+    fn change_passphrase(&self, _current: &str, _new: &str) -> Result<(), StorageError> {
+        Err(StorageError::Unsupported(
+            "in-memory stores are not encrypted".to_string(),
+        ))
+    }
+
+    // ============ Entity Operations ============
+
+    fn save_entity(&self, entity: &Entity) -> Result<(), StorageError> {
+        self.write()?.index_entity(entity);
+        Ok(())
+    }
+
+    fn get_entity(&self, hash: &str) -> Result<Option<Entity>, StorageError> {
+        let state = self.read()?;
+        let key = entity_index::entity_key(&state.keys, hash);
+        Ok(state.entities.get(&key).cloned())
+    }
+
+    fn get_all_entities(&self) -> Result<Vec<Entity>, StorageError> {
+        Ok(self.read()?.entities.values().cloned().collect())
+    }
+
+    fn delete_entity(&self, hash: &str) -> Result<(), StorageError> {
+        let mut state = self.write()?;
+        let key = entity_index::entity_key(&state.keys, hash);
+        if let Some(previous) = state.entities.remove(&key) {
+            state.unindex_entity(&previous);
+        }
+        state.search.remove(&SearchDoc::Entity {
+            hash: hash.to_string(),
+        });
+        Ok(())
+    }
+
+    fn clear_entities(&self) -> Result<(), StorageError> {
+        let mut state = self.write()?;
+        state.entities.clear();
+        state.idx_entity_type.clear();
+        state.idx_entity_tag.clear();
+        state.idx_entity_identity.clear();
+        state.idx_entity_last_seen.clear();
+        state
+            .search
+            .remove_where(|doc| matches!(doc, SearchDoc::Entity { .. }));
+        Ok(())
+    }
+
+    fn query_entities(
+        &self,
+        filter: &EntityFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError> {
+        let state = self.read()?;
+        let index = match filter {
+            EntityFilter::Type(_) => &state.idx_entity_type,
+            EntityFilter::Tag(_) => &state.idx_entity_tag,
+            EntityFilter::Identity(_) => &state.idx_entity_identity,
+        };
+        let prefix = entity_index::prefix_for(filter, &state.keys);
+
+        let start = match cursor.and_then(entity_index::decode_cursor) {
+            Some(key) if key.starts_with(&prefix) => Bound::Excluded(key),
+            _ => Bound::Included(prefix.clone()),
+        };
+
+        let keys = index
+            .range((start, Bound::Unbounded))
+            .take_while(|k| k.starts_with(&prefix));
+        Ok(state.collect_page(keys, prefix.len(), limit))
+    }
+
+    fn entities_by_last_seen(
+        &self,
+        before: Option<DateTime<Utc>>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError> {
+        let end = match cursor.and_then(entity_index::decode_cursor) {
+            Some(key) => Bound::Excluded(key),
+            None => match before {
+                Some(before) => Bound::Excluded(entity_index::time_prefix(before)),
+                None => Bound::Unbounded,
+            },
+        };
+
+        let state = self.read()?;
+        let keys = state
+            .idx_entity_last_seen
+            .range((Bound::Unbounded, end))
+            .rev();
+        Ok(state.collect_page(keys, 8, limit))
+    }
+
+    fn cross_reference_hashes(&self) -> Result<Vec<String>, StorageError> {
+        Ok(self
+            .read()?
+            .entities
+            .values()
+            .filter(|entity| entity.unique_sources().len() > 1)
+            .map(|entity| entity.hash.clone())
+            .collect())
+    }
+
+    // ============ Session Operations ============
+
+    fn save_session(&self, identity_id: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.write()?
+            .sessions
+            .insert(identity_id.to_string(), Zeroizing::new(data.to_vec()));
+        Ok(())
+    }
+
+    fn get_session(&self, identity_id: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self
+            .read()?
+            .sessions
+            .get(identity_id)
+            .map(|data| data.to_vec()))
+    }
+
+    fn clear_session(&self, identity_id: &str) -> Result<(), StorageError> {
+        self.write()?.sessions.remove(identity_id);
+        Ok(())
+    }
+
+    // ============ Investigation Operations ============
+
+    fn save_investigation(&self, investigation: &Investigation) -> Result<(), StorageError> {
+        self.write()?.set_investigation(investigation);
+        Ok(())
+    }
+
+    fn get_investigation(&self, id: &str) -> Result<Option<Investigation>, StorageError> {
+        Ok(self.read()?.investigations.get(id).cloned())
+    }
+
+    fn get_all_investigations(&self) -> Result<Vec<Investigation>, StorageError> {
+        Ok(self.read()?.investigations.values().cloned().collect())
+    }
+
+    fn delete_investigation(&self, id: &str) -> Result<(), StorageError> {
+        let mut state = self.write()?;
+        state.investigations.remove(id);
+        state.search.remove_timeline(id);
+        Ok(())
+    }
+
+    // ============ Page Capture Operations ============
+
+    fn save_page(&self, page: &CapturedPage) -> Result<(), StorageError> {
+        let mut state = self.write()?;
+        state.search.index_page(page);
+        state.pages.insert(page.id.clone(), page.clone());
+        Ok(())
+    }
+
+    fn get_page(&self, id: &str) -> Result<Option<CapturedPage>, StorageError> {
+        Ok(self.read()?.pages.get(id).cloned())
+    }
+
+    fn get_all_pages(&self) -> Result<Vec<CapturedPage>, StorageError> {
+        Ok(self.read()?.pages.values().cloned().collect())
+    }
+
+    fn delete_page(&self, id: &str) -> Result<(), StorageError> {
+        let mut state = self.write()?;
+        state.pages.remove(id);
+        state.search.remove(&SearchDoc::Page { id: id.to_string() });
+        Ok(())
+    }
+
+    fn delete_pages_by_identity(&self, identity_id: &str) -> Result<usize, StorageError> {
+        let mut state = self.write()?;
+        let removed: Vec<String> = state
+            .pages
+            .values()
+            .filter(|page| page.identity_id == identity_id)
+            .map(|page| page.id.clone())
+            .collect();
+        for id in &removed {
+            state.pages.remove(id);
+            state.search.remove(&SearchDoc::Page { id: id.clone() });
+        }
+        Ok(removed.len())
+    }
+
+    // ============ Search Operations ============
+
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, StorageError> {
+        self.read()?.search.search(query, limit)
+    }
+
+    // ============ Audit Operations ============
+
+    fn append_audit(&self, actor: &str, action: AuditAction) -> Result<AuditRecord, StorageError> {
+        let mut state = self.write()?;
+        let record = AuditRecord::next(state.audit.last(), actor, action);
+        state.audit.push(record.clone());
+        Ok(record)
+    }
+
+    fn audit_log(&self) -> Result<Vec<AuditRecord>, StorageError> {
+        Ok(self.read()?.audit.clone())
+    }
+
+    fn audit_head(&self) -> Result<Option<String>, StorageError> {
+        Ok(self.read()?.audit.last().map(|r| r.hash.clone()))
+    }
+
+    // ============ Snapshot Operations ============
+
+    fn snapshot(&self) -> Result<StoreSnapshot, StorageError> {
+        let state = self.read()?;
+        let mut investigations: Vec<Investigation> =
+            state.investigations.values().cloned().collect();
+        investigations.sort_by_key(|i| i.created_at);
+
+        Ok(StoreSnapshot {
+            schema_version: SCHEMA_VERSION,
+            active_identity: state.active_identity.clone(),
+            identities: state.identities.values().cloned().collect(),
+            entities: state.entities.values().cloned().collect(),
+            sessions: state
+                .sessions
+                .iter()
+                .map(|(identity_id, data)| SessionRecord {
+                    identity_id: identity_id.clone(),
+                    data: data.to_vec(),
+                })
+                .collect(),
+            investigations,
+            pages: state.pages.values().cloned().collect(),
+            settings: state.settings.clone(),
+        })
+    }
+
+    fn restore_snapshot(&self, snapshot: &StoreSnapshot) -> Result<(), StorageError> {
+        let mut state = MemoryState {
+            active_identity: snapshot.active_identity.clone(),
+            settings: snapshot.settings.clone(),
+            ..Default::default()
+        };
+        for identity in &snapshot.identities {
+            state
+                .identities
+                .insert(identity.id.clone(), identity.clone());
+        }
+        for entity in &snapshot.entities {
+            state.index_entity(entity);
+        }
+        for session in &snapshot.sessions {
+            state.sessions.insert(
+                session.identity_id.clone(),
+                Zeroizing::new(session.data.clone()),
+            );
+        }
+        for investigation in &snapshot.investigations {
+            state.set_investigation(investigation);
+        }
+        for page in &snapshot.pages {
+            state.search.index_page(page);
+            state.pages.insert(page.id.clone(), page.clone());
+        }
+
+        let mut current = self.write()?;
+        state.audit = std::mem::take(&mut current.audit);
+        *current = state;
+        Ok(())
+    }
+
+    // ============ Database Operations ============
+
+    /// Nothing to flush: writes are visible as soon as they return
+    fn flush(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entity::{EntitySource, EntityType};
+
+    #[test]
+    fn test_new_store_has_active_prime() {
+        let store = MemoryStore::new();
+        assert_eq!(store.get_active_identity().unwrap().id, "prime");
+        assert_eq!(store.schema_version().unwrap(), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_resave_moves_type_index_entry() {
+        let store = MemoryStore::new();
+        let mut entity = Entity::new(
+            EntityType::Email,
+            "a@example.com".to_string(),
+            EntitySource::new("prime"),
+        );
+        store.save_entity(&entity).unwrap();
+        entity.tags.push("suspect".to_string());
+        store.save_entity(&entity).unwrap();
+
+        let page = store
+            .query_entities(&EntityFilter::Type(EntityType::Email), None, 10)
+            .unwrap();
+        assert_eq!(page.entities.len(), 1);
+        let page = store
+            .query_entities(&EntityFilter::Tag("Suspect".to_string()), None, 10)
+            .unwrap();
+        assert_eq!(page.entities.len(), 1);
+    }
+}
@@ -0,0 +1,310 @@
+//! Schema Versioning & Migrations
+//!
+//! The store's schema version lives in the `config` tree under
+//! `schema_version`. On open, every registered migration newer than the stored
+//! version runs in order, each one bumping the version once it has been
+//! applied. Migrations work on the decoded JSON of each record, so they can
+//! backfill fields that older builds never wrote.
+//!
+//! Backups carry the schema version of the store they were taken from; a
+//! snapshot from an older version is brought up to date with the same
+//! migrations before it is restored (see [`upgrade_snapshot`]).
+//!
+//! To change a persisted model: bump [`SCHEMA_VERSION`], then append a
+//! [`Migration`] with that version to [`MIGRATIONS`].
+
+use crate::core::entity::{Entity, EntityType};
+use crate::core::normalize::canonicalize;
+use crate::storage::backup::StoreSnapshot;
+use crate::storage::{SledStore, StorageError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// Schema version written by this build
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// A single ordered schema migration
+pub struct Migration {
+    /// Version the store is at once this migration has been applied
+    pub version: u32,
+    /// Short human-readable summary
+    pub description: &'static str,
+    /// Transformation to apply
+    pub apply: fn(&mut MigrationContext) -> Result<(), StorageError>,
+    /// The same transformation over a backup snapshot. Snapshots hold
+    /// decoded records only, so migrations of indexes or on-disk layout
+    /// leave them alone.
+    pub upgrade_snapshot: fn(&mut StoreSnapshot),
+}
+
+/// All migrations, in ascending version order
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Adopt versioned schema (baseline, no record changes)",
+        apply: |_| Ok(()),
+        upgrade_snapshot: |_| {},
+    },
+    Migration {
+        version: 2,
+        description: "Build entity secondary indexes",
+        apply: |ctx| ctx.rebuild_entity_indexes(),
+        upgrade_snapshot: |_| {},
+    },
+    Migration {
+        version: 3,
+        description: "Canonicalise entity values and merge formatting variants",
+        apply: |ctx| ctx.canonicalise_entities(),
+        upgrade_snapshot: canonicalise_snapshot,
+    },
+    Migration {
+        version: 4,
+        description: "Key entity records and indexes with the store's keyed hash",
+        apply: |ctx| ctx.rekey_entities(),
+        upgrade_snapshot: |_| {},
+    },
+];
+
+/// Bring a snapshot taken at an older schema version up to
+/// [`SCHEMA_VERSION`]
+pub fn upgrade_snapshot(snapshot: &mut StoreSnapshot) {
+    for migration in MIGRATIONS {
+        if migration.version > snapshot.schema_version {
+            (migration.upgrade_snapshot)(snapshot);
+            snapshot.schema_version = migration.version;
+        }
+    }
+}
+
+/// Entities re-keyed by canonical value
+pub(crate) struct Canonicalised {
+    /// Every entity by hash, those that collapsed to one value merged
+    pub entities: BTreeMap<String, Entity>,
+    /// Number of entities whose value or hash changed
+    pub changed: usize,
+    /// Old → new hash of each re-keyed entity
+    pub rehashed: HashMap<String, String>,
+}
+
+/// Re-key `entities` by canonical value, merging those that collapse to the
+/// same one
+pub(crate) fn canonicalise(entities: impl IntoIterator<Item = Entity>) -> Canonicalised {
+    let mut merged: BTreeMap<String, Entity> = BTreeMap::new();
+    let mut rehashed = HashMap::new();
+    let mut changed = 0;
+
+    for entity in entities {
+        // Custom values were canonicalised by their rule when extracted,
+        // and the rule may since have changed or been deleted
+        let value = match &entity.entity_type {
+            EntityType::Custom(_) => entity.value.clone(),
+            entity_type => canonicalize(entity_type, &entity.value),
+        };
+        let hash = Entity::compute_hash(&entity.entity_type, &value);
+        if hash == entity.hash && value == entity.value {
+            merged.entry(entity.hash.clone()).or_insert(entity);
+            continue;
+        }
+
+        changed += 1;
+        if hash != entity.hash {
+            rehashed.insert(entity.hash.clone(), hash.clone());
+        }
+        let mut rekeyed = entity.clone();
+        rekeyed.hash = hash;
+        rekeyed.value = value;
+        rekeyed.add_alias(&entity.value);
+        match merged.get_mut(&rekeyed.hash) {
+            Some(existing) => existing.absorb(rekeyed),
+            None => {
+                merged.insert(rekeyed.hash.clone(), rekeyed);
+            }
+        }
+    }
+
+    Canonicalised {
+        entities: merged,
+        changed,
+        rehashed,
+    }
+}
+
+/// Migration v3 over a snapshot: canonicalise its entities and point
+/// timeline events and graph nodes and edges at the new hashes
+fn canonicalise_snapshot(snapshot: &mut StoreSnapshot) {
+    let Canonicalised {
+        entities, rehashed, ..
+    } = canonicalise(std::mem::take(&mut snapshot.entities));
+    snapshot.entities = entities.into_values().collect();
+
+    let remap = |hash: &mut String| {
+        if let Some(new_hash) = rehashed.get(hash.as_str()) {
+            *hash = new_hash.clone();
+        }
+    };
+    for investigation in &mut snapshot.investigations {
+        for event in &mut investigation.timeline {
+            if let Some(hash) = &mut event.entity_hash {
+                remap(hash);
+            }
+        }
+        for node in &mut investigation.graph.nodes {
+            remap(&mut node.id);
+        }
+        for edge in &mut investigation.graph.edges {
+            remap(&mut edge.source);
+            remap(&mut edge.target);
+        }
+    }
+}
+
+/// How migrations should be run when a store is opened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationOptions {
+    /// Plan the migrations and count affected records without writing
+    pub dry_run: bool,
+    /// Copy the database aside before the first migration is applied
+    pub backup: bool,
+}
+
+impl Default for MigrationOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            backup: true,
+        }
+    }
+}
+
+/// Outcome of a single migration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub description: String,
+    pub records_changed: usize,
+}
+
+/// Outcome of a migration run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub dry_run: bool,
+    pub backup_path: Option<PathBuf>,
+    pub migrations: Vec<AppliedMigration>,
+    pub completed_at: DateTime<Utc>,
+}
+
+impl MigrationReport {
+    /// Whether any migration was (or would be) applied
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty()
+    }
+}
+
+/// Handle given to a migration for reading and rewriting records
+pub struct MigrationContext<'a> {
+    store: &'a SledStore,
+    dry_run: bool,
+    records_changed: usize,
+}
+
+impl<'a> MigrationContext<'a> {
+    pub(super) fn new(store: &'a SledStore, dry_run: bool) -> Self {
+        Self {
+            store,
+            dry_run,
+            records_changed: 0,
+        }
+    }
+
+    /// Number of records changed (or that would change, in a dry run)
+    pub fn records_changed(&self) -> usize {
+        self.records_changed
+    }
+
+    /// Rebuild the entity secondary indexes (counts entities in a dry run)
+    pub fn rebuild_entity_indexes(&mut self) -> Result<(), StorageError> {
+        self.records_changed += if self.dry_run {
+            self.store.entity_count()
+        } else {
+            self.store.rebuild_entity_indexes()?
+        };
+        Ok(())
+    }
+
+    /// Re-key entities by canonical value, then point timeline events and
+    /// graph nodes and edges at the new hashes and rebuild the indexes
+    pub fn canonicalise_entities(&mut self) -> Result<(), StorageError> {
+        let (changed, rehashed) = self.store.canonicalise_entities(self.dry_run)?;
+        self.records_changed += changed;
+        if rehashed.is_empty() {
+            return Ok(());
+        }
+
+        let remap = |value: &mut serde_json::Value, field: &str| -> bool {
+            let Some(new_hash) = value
+                .get(field)
+                .and_then(|old| old.as_str())
+                .and_then(|old| rehashed.get(old))
+            else {
+                return false;
+            };
+            value[field] = serde_json::Value::String(new_hash.clone());
+            true
+        };
+        self.map_records("timeline_events", |event| remap(event, "entity_hash"))?;
+        self.map_records("graph_nodes", |node| remap(node, "id"))?;
+        self.map_records("graph_edges", |edge| {
+            let source = remap(edge, "source");
+            remap(edge, "target") || source
+        })?;
+
+        if !self.dry_run {
+            self.store.rebuild_entity_indexes()?;
+        }
+        Ok(())
+    }
+
+    /// Move entities to their keyed entity keys and rebuild the indexes under
+    /// keyed prefixes
+    pub fn rekey_entities(&mut self) -> Result<(), StorageError> {
+        self.records_changed += self.store.rekey_entities(self.dry_run)?;
+        if !self.dry_run {
+            self.store.rebuild_entity_indexes()?;
+        }
+        Ok(())
+    }
+
+    /// Run `f` over every record in `tree`, decoded as JSON.
+    ///
+    /// `f` returns `true` when it modified the value; only those records are
+    /// re-sealed and written back (or just counted, in a dry run).
+    pub fn map_records<F>(&mut self, tree: &str, mut f: F) -> Result<(), StorageError>
+    where
+        F: FnMut(&mut serde_json::Value) -> bool,
+    {
+        let tree = self.store.tree_by_name(tree)?;
+        let _writes = self.store.write_guard()?;
+        let mut batch = sled::Batch::default();
+
+        for result in tree.iter() {
+            let (key, sealed) = result?;
+            let mut value: serde_json::Value =
+                serde_json::from_slice(&self.store.open_bytes(&sealed)?)?;
+            if f(&mut value) {
+                self.records_changed += 1;
+                if !self.dry_run {
+                    batch.insert(key, self.store.seal_bytes(&serde_json::to_vec(&value)?)?);
+                }
+            }
+        }
+
+        if !self.dry_run {
+            tree.apply_batch(batch)?;
+        }
+        Ok(())
+    }
+}
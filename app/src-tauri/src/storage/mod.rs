@@ -1,17 +1,40 @@
 //! Storage Module
 //!
-//! Persistent storage using sled embedded database.
+//! Persistent storage using sled embedded database, encrypted at rest with a
+//! passphrase-derived key, or an in-memory store for ephemeral sessions. Both
+//! implement [`Store`]; callers only ever see `Arc<dyn Store>`.
 
+pub mod audit;
+pub mod backup;
+mod crypto;
+mod entity_index;
+mod memory_store;
+pub mod migrations;
+mod search;
 mod sled_store;
+mod store;
 
+pub use crypto::KdfParams;
+pub use entity_index::{EntityFilter, EntityPage};
+pub use memory_store::MemoryStore;
+pub use migrations::{MigrationOptions, MigrationReport};
+pub use search::{CapturedPage, SearchDoc, SearchField, SearchHit};
 pub use sled_store::SledStore;
+pub use store::Store;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Environment variable that supplies the store passphrase without a prompt
+pub const PASSPHRASE_ENV: &str = "SPIN_PASSPHRASE";
 
 /// Global store instance
-static STORE: RwLock<Option<Arc<SledStore>>> = RwLock::new(None);
+static STORE: RwLock<Option<Arc<dyn Store>>> = RwLock::new(None);
+
+/// Application data directory the store was opened from
+static DATA_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
 
 /// Storage error types
 #[derive(Debug, Error)]
@@ -27,29 +50,187 @@ pub enum StorageError {
 
     #[error("Entity not found: {0}")]
     NotFound(String),
+
+    #[error("Wrong passphrase")]
+    WrongPassphrase,
+
+    #[error("Encryption error: {0}")]
+    Crypto(String),
+
+    #[error("Store schema v{found} is newer than this build supports (v{supported})")]
+    SchemaTooNew { found: u32, supported: u32 },
+
+    #[error("Unknown tree: {0}")]
+    UnknownTree(String),
+
+    #[error("Search error: {0}")]
+    Search(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Backup error: {0}")]
+    Backup(String),
+
+    #[error("Store lock poisoned")]
+    LockPoisoned,
+
+    #[error("Not supported: {0}")]
+    Unsupported(String),
 }
 
 /// Initialize the storage system.
 ///
-/// `data_dir` is the application-level data directory (e.g. `~/.local/share/spin`).
-/// The sled database is stored at `<data_dir>/spin.db`.
-pub fn init(data_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+/// `data_dir` is the open vault's data directory (e.g. `~/.local/share/spin`).
+/// The sled database is stored at `<data_dir>/spin.db` and unlocked with
+/// `passphrase`; a wrong passphrase fails initialization. Pending schema
+/// migrations run according to `options`.
+pub fn init(
+    data_dir: &Path,
+    passphrase: &str,
+    options: &MigrationOptions,
+) -> Result<MigrationReport, Box<dyn std::error::Error>> {
     let db_path = data_dir.join("spin.db");
 
     tracing::info!("Initializing sled database at {:?}", db_path);
 
-    let store = SledStore::new(&db_path)?;
+    let (store, report) = SledStore::open(&db_path, passphrase, options)?;
+    if !report.is_empty() {
+        tracing::info!(
+            "Store schema v{} -> v{} ({} migrations, backup: {:?})",
+            report.from_version,
+            report.to_version,
+            report.migrations.len(),
+            report.backup_path
+        );
+    }
 
+    set_global(Arc::new(store), data_dir)?;
+
+    Ok(report)
+}
+
+/// Initialize the storage system with an empty in-memory store.
+///
+/// Nothing is written under `data_dir`; it is only recorded for the modules
+/// (CEF, backups) that keep their own files there. Everything in the store is
+/// gone once it is closed.
+pub fn init_in_memory(data_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    tracing::info!("Initializing in-memory store");
+    set_global(Arc::new(MemoryStore::new()), data_dir)
+}
+
+fn set_global(store: Arc<dyn Store>, data_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let mut global_store = STORE
         .write()
         .map_err(|e| format!("Storage lock poisoned: {}", e))?;
-    *global_store = Some(Arc::new(store));
+    *global_store = Some(store);
+
+    let mut global_data_dir = DATA_DIR
+        .write()
+        .map_err(|e| format!("Storage lock poisoned: {}", e))?;
+    *global_data_dir = Some(data_dir.to_path_buf());
 
     Ok(())
 }
 
+/// Flush and release the global store.
+///
+/// Handles already obtained from [`get_store`] stay usable until dropped;
+/// the database file is unlocked once the last one goes.
+pub fn close() -> Result<(), StorageError> {
+    let store = STORE
+        .write()
+        .map_err(|_| StorageError::NotInitialized)?
+        .take();
+    DATA_DIR
+        .write()
+        .map_err(|_| StorageError::NotInitialized)?
+        .take();
+
+    if let Some(store) = store {
+        store.flush()?;
+    }
+    Ok(())
+}
+
+/// Plan pending schema migrations without applying them.
+///
+/// Must be called before [`init`]: sled allows one open handle per database.
+/// Nothing is written; a vault without a store yet is not created.
+pub fn plan_migrations(data_dir: &Path, passphrase: &str) -> Result<MigrationReport, StorageError> {
+    let options = MigrationOptions {
+        dry_run: true,
+        backup: false,
+    };
+    let db_path = data_dir.join("spin.db");
+    if !db_path.exists() {
+        return Ok(MigrationReport {
+            from_version: migrations::SCHEMA_VERSION,
+            to_version: migrations::SCHEMA_VERSION,
+            dry_run: true,
+            backup_path: None,
+            migrations: Vec::new(),
+            completed_at: chrono::Utc::now(),
+        });
+    }
+    let (_, report) = SledStore::open(&db_path, passphrase, &options)?;
+    Ok(report)
+}
+
 /// Get the global store instance (no AppHandle required).
-pub fn get_store() -> Result<Arc<SledStore>, StorageError> {
+pub fn get_store() -> Result<Arc<dyn Store>, StorageError> {
     let store = STORE.read().map_err(|_| StorageError::NotInitialized)?;
     store.clone().ok_or(StorageError::NotInitialized)
 }
+
+/// Get the application data directory passed to [`init`]
+pub fn data_dir() -> Result<PathBuf, StorageError> {
+    let data_dir = DATA_DIR.read().map_err(|_| StorageError::NotInitialized)?;
+    data_dir.clone().ok_or(StorageError::NotInitialized)
+}
+
+/// The store passphrase from `$SPIN_PASSPHRASE`, if set.
+///
+/// Scripted and headless runs use it to skip the unlock screen.
+pub fn env_passphrase() -> Result<Option<Zeroizing<String>>, String> {
+    match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) if passphrase.is_empty() => {
+            Err(format!("{} is set but empty", PASSPHRASE_ENV))
+        }
+        Ok(passphrase) => Ok(Some(Zeroizing::new(passphrase))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Obtain the store passphrase for command-line runs such as
+/// `--migrate-dry-run`; the GUI asks on its unlock screen instead.
+///
+/// Uses `$SPIN_PASSPHRASE` when set, otherwise prompts on the terminal without
+/// echo. When no store exists yet the passphrase is asked for twice.
+pub fn read_passphrase(data_dir: &Path) -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    if let Some(passphrase) = env_passphrase()? {
+        return Ok(passphrase);
+    }
+
+    let is_new = !data_dir.join("spin.db").exists();
+    let prompt = if is_new {
+        "Choose a passphrase for the Spin store: "
+    } else {
+        "Spin store passphrase: "
+    };
+
+    let passphrase = Zeroizing::new(rpassword::prompt_password(prompt)?);
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".into());
+    }
+
+    if is_new {
+        let confirm = Zeroizing::new(rpassword::prompt_password("Confirm passphrase: ")?);
+        if *confirm != *passphrase {
+            return Err("Passphrases do not match".into());
+        }
+    }
+
+    Ok(passphrase)
+}
@@ -0,0 +1,566 @@
+//! Full-Text Search
+//!
+//! An inverted index over entity values, notes and source contexts, timeline
+//! event titles and descriptions, and captured page text. The store is sealed
+//! at rest, so the index is never written to disk: it is built from the
+//! decrypted records when the store is opened and updated on every write.
+//!
+//! Query syntax (all clauses must match):
+//!
+//! - `wire` — a token anywhere
+//! - `wire*` — any token starting with `wire`
+//! - `"wire transfer"` — consecutive tokens within one field
+//! - `notes:"wire transfer"`, `value:alice*` — scoped to one field
+//! - `type:email` — an entity type, or `timeline` / `page`
+
+use crate::core::entity::{Entity, EntityType};
+use crate::investigation::TimelineEvent;
+use crate::storage::StorageError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+
+/// Searchable fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    /// `Entity.value`
+    Value,
+    /// `Entity.notes`
+    Notes,
+    /// `EntitySource.context`
+    Context,
+    /// Timeline event or page title
+    Title,
+    /// Timeline event description
+    Description,
+    /// Captured page text
+    Text,
+}
+
+impl SearchField {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "value" => Some(Self::Value),
+            "notes" => Some(Self::Notes),
+            "context" => Some(Self::Context),
+            "title" => Some(Self::Title),
+            "description" => Some(Self::Description),
+            "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// A record the index points at
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchDoc {
+    Entity {
+        hash: String,
+    },
+    TimelineEvent {
+        investigation_id: String,
+        event_id: String,
+    },
+    Page {
+        id: String,
+    },
+}
+
+/// One search result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub doc: SearchDoc,
+    /// Number of matching occurrences across all clauses
+    pub score: usize,
+    /// Fields that matched
+    pub fields: Vec<SearchField>,
+}
+
+/// Page text captured from the browser so it can be searched later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedPage {
+    /// Stable per identity and URL, so recapturing a page replaces it
+    pub id: String,
+    pub identity_id: String,
+    pub url: String,
+    pub title: String,
+    pub text: String,
+    pub captured_at: DateTime<Utc>,
+}
+
+impl CapturedPage {
+    pub fn new(identity_id: String, url: String, title: String, text: String) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(identity_id.as_bytes());
+        hasher.update([0]);
+        hasher.update(url.as_bytes());
+        Self {
+            id: format!("{:x}", hasher.finalize()),
+            identity_id,
+            url,
+            title,
+            text,
+            captured_at: Utc::now(),
+        }
+    }
+}
+
+/// Where a token occurs within a document
+#[derive(Debug, Clone, Copy)]
+struct Posting {
+    field: SearchField,
+    position: u32,
+}
+
+/// What the index remembers about a document, for `type:` and removal
+#[derive(Debug)]
+struct IndexedDoc {
+    kind: String,
+    tokens: Vec<String>,
+}
+
+/// In-memory inverted index
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /// token -> document -> occurrences; ordered for prefix queries
+    terms: BTreeMap<String, HashMap<SearchDoc, Vec<Posting>>>,
+    docs: HashMap<SearchDoc, IndexedDoc>,
+}
+
+impl SearchIndex {
+    /// Index (or re-index) an entity
+    pub fn index_entity(&mut self, entity: &Entity) {
+        let mut fields = vec![(SearchField::Value, entity.value.as_str())];
+        for alias in &entity.aliases {
+            fields.push((SearchField::Value, alias.as_str()));
+        }
+        if let Some(notes) = &entity.notes {
+            fields.push((SearchField::Notes, notes.as_str()));
+        }
+        for source in &entity.sources {
+            if let Some(context) = &source.context {
+                fields.push((SearchField::Context, context.as_str()));
+            }
+        }
+        self.insert(
+            SearchDoc::Entity {
+                hash: entity.hash.clone(),
+            },
+            entity_kind(&entity.entity_type),
+            &fields,
+        );
+    }
+
+    /// Index (or re-index) a timeline event
+    pub fn index_timeline_event(&mut self, event: &TimelineEvent) {
+        self.insert(
+            SearchDoc::TimelineEvent {
+                investigation_id: event.investigation_id.clone(),
+                event_id: event.id.clone(),
+            },
+            "timeline".to_string(),
+            &[
+                (SearchField::Title, event.title.as_str()),
+                (SearchField::Description, event.description.as_str()),
+            ],
+        );
+    }
+
+    /// Index (or re-index) a captured page
+    pub fn index_page(&mut self, page: &CapturedPage) {
+        self.insert(
+            SearchDoc::Page {
+                id: page.id.clone(),
+            },
+            "page".to_string(),
+            &[
+                (SearchField::Title, page.title.as_str()),
+                (SearchField::Text, page.text.as_str()),
+            ],
+        );
+    }
+
+    /// Drop every document matching `predicate`
+    pub fn remove_where<F: Fn(&SearchDoc) -> bool>(&mut self, predicate: F) {
+        let matching: Vec<SearchDoc> = self
+            .docs
+            .keys()
+            .filter(|doc| predicate(doc))
+            .cloned()
+            .collect();
+        for doc in matching {
+            self.remove(&doc);
+        }
+    }
+
+    /// Drop every timeline event document of an investigation
+    pub fn remove_timeline(&mut self, investigation_id: &str) {
+        self.remove_where(|doc| {
+            matches!(doc, SearchDoc::TimelineEvent { investigation_id: id, .. } if id == investigation_id)
+        });
+    }
+
+    /// Drop a document
+    pub fn remove(&mut self, doc: &SearchDoc) {
+        let Some(indexed) = self.docs.remove(doc) else {
+            return;
+        };
+        for token in indexed.tokens {
+            if let Some(postings) = self.terms.get_mut(&token) {
+                postings.remove(doc);
+                if postings.is_empty() {
+                    self.terms.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// Run a query, best matches first
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, StorageError> {
+        let clauses = parse_query(query)?;
+        if clauses.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut matched: Option<HashMap<&SearchDoc, (usize, Vec<SearchField>)>> = None;
+        for clause in &clauses {
+            let hits = self.evaluate(clause);
+            matched = Some(match matched {
+                None => hits,
+                Some(mut acc) => {
+                    acc.retain(|doc, _| hits.contains_key(doc));
+                    for (doc, (score, fields)) in acc.iter_mut() {
+                        let (more, more_fields) = &hits[doc];
+                        *score += more;
+                        fields.extend(more_fields);
+                    }
+                    acc
+                }
+            });
+        }
+
+        let mut hits: Vec<SearchHit> = matched
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(doc, (score, mut fields))| {
+                fields.sort_by_key(|f| *f as u8);
+                fields.dedup();
+                SearchHit {
+                    doc: doc.clone(),
+                    score,
+                    fields,
+                }
+            })
+            .collect();
+        hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    fn insert(&mut self, doc: SearchDoc, kind: String, fields: &[(SearchField, &str)]) {
+        self.remove(&doc);
+
+        let mut tokens = Vec::new();
+        let mut positions: HashMap<SearchField, u32> = HashMap::new();
+        for (field, value) in fields {
+            let next = positions.entry(*field).or_default();
+            for token in tokenize(value) {
+                self.terms
+                    .entry(token.clone())
+                    .or_default()
+                    .entry(doc.clone())
+                    .or_default()
+                    .push(Posting {
+                        field: *field,
+                        position: *next,
+                    });
+                tokens.push(token);
+                *next += 1;
+            }
+            // Leave a gap so phrases never span two separate values
+            *next += 1;
+        }
+
+        tokens.sort();
+        tokens.dedup();
+        self.docs.insert(doc, IndexedDoc { kind, tokens });
+    }
+
+    /// Documents matching one clause, with occurrence counts and fields
+    fn evaluate(&self, clause: &Clause) -> HashMap<&SearchDoc, (usize, Vec<SearchField>)> {
+        let mut hits: HashMap<&SearchDoc, (usize, Vec<SearchField>)> = HashMap::new();
+
+        let (field, tokens, prefix) = match clause {
+            Clause::Kind(kind) => {
+                for (doc, indexed) in &self.docs {
+                    if indexed.kind == *kind {
+                        hits.insert(doc, (0, Vec::new()));
+                    }
+                }
+                return hits;
+            }
+            Clause::Terms {
+                field,
+                tokens,
+                prefix,
+            } => (field, tokens, *prefix),
+        };
+
+        let postings: Vec<HashMap<&SearchDoc, Vec<Posting>>> = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| self.postings(token, prefix && i == tokens.len() - 1))
+            .collect();
+
+        for (doc, starts) in &postings[0] {
+            for start in starts {
+                if field.is_some_and(|f| f != start.field) {
+                    continue;
+                }
+                let is_phrase = postings[1..].iter().enumerate().all(|(offset, next)| {
+                    next.get(doc).is_some_and(|ps| {
+                        ps.iter().any(|p| {
+                            p.field == start.field
+                                && p.position == start.position + offset as u32 + 1
+                        })
+                    })
+                });
+                if is_phrase {
+                    let entry = hits.entry(doc).or_default();
+                    entry.0 += 1;
+                    entry.1.push(start.field);
+                }
+            }
+        }
+        hits
+    }
+
+    /// Occurrences of a token, or of every token starting with it
+    fn postings(&self, token: &str, prefix: bool) -> HashMap<&SearchDoc, Vec<Posting>> {
+        let mut out: HashMap<&SearchDoc, Vec<Posting>> = HashMap::new();
+        if prefix {
+            let matching = self
+                .terms
+                .range::<str, _>((Bound::Included(token), Bound::Unbounded))
+                .take_while(|(term, _)| term.starts_with(token));
+            for (_, docs) in matching {
+                for (doc, postings) in docs {
+                    out.entry(doc).or_default().extend(postings);
+                }
+            }
+        } else if let Some(docs) = self.terms.get(token) {
+            for (doc, postings) in docs {
+                out.insert(doc, postings.clone());
+            }
+        }
+        out
+    }
+}
+
+/// A parsed query clause
+#[derive(Debug, PartialEq)]
+enum Clause {
+    /// `type:<kind>`
+    Kind(String),
+    /// A token sequence, optionally field-scoped, last token optionally a prefix
+    Terms {
+        field: Option<SearchField>,
+        tokens: Vec<String>,
+        prefix: bool,
+    },
+}
+
+/// Lowercased alphanumeric runs
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The `type:` value for an entity
+fn entity_kind(entity_type: &EntityType) -> String {
+    match entity_type {
+        EntityType::Custom(_) => "custom".to_string(),
+        other => serde_json::to_value(other)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default(),
+    }
+}
+
+fn parse_query(query: &str) -> Result<Vec<Clause>, StorageError> {
+    let mut clauses = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut word = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '"') {
+            word.push(c);
+        }
+
+        let quoted = if chars.next_if_eq(&'"').is_some() {
+            let mut phrase = String::new();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                phrase.push(c);
+            }
+            Some(phrase)
+        } else {
+            None
+        };
+
+        let (scope, body) = match (&quoted, word.split_once(':')) {
+            (Some(phrase), _) => (word.strip_suffix(':').map(str::to_string), phrase.clone()),
+            (None, Some((scope, rest)))
+                if scope == "type" || SearchField::from_name(scope).is_some() =>
+            {
+                (Some(scope.to_string()), rest.to_string())
+            }
+            (None, _) => (None, word),
+        };
+
+        if scope.as_deref() == Some("type") {
+            let kind = body.trim().to_lowercase();
+            if kind.is_empty() {
+                return Err(StorageError::Search("Empty type: filter".to_string()));
+            }
+            clauses.push(Clause::Kind(kind));
+            continue;
+        }
+
+        let field = match scope {
+            Some(name) => Some(
+                SearchField::from_name(&name)
+                    .ok_or_else(|| StorageError::Search(format!("Unknown field: {}", name)))?,
+            ),
+            None => None,
+        };
+        let prefix = quoted.is_none() && body.ends_with('*');
+        let tokens: Vec<String> = tokenize(&body).collect();
+        if !tokens.is_empty() {
+            clauses.push(Clause::Terms {
+                field,
+                tokens,
+                prefix,
+            });
+        }
+    }
+
+    Ok(clauses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entity::EntitySource;
+
+    fn entity(entity_type: EntityType, value: &str, notes: Option<&str>) -> Entity {
+        let mut entity = Entity::new(
+            entity_type,
+            value.to_string(),
+            EntitySource {
+                context: Some("seen in a forum signature".to_string()),
+                ..EntitySource::new("prime")
+            },
+        );
+        entity.notes = notes.map(str::to_string);
+        entity
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::default();
+        index.index_entity(&entity(
+            EntityType::Email,
+            "alice@example.com",
+            Some("Requested a wire transfer on Friday"),
+        ));
+        index.index_entity(&entity(
+            EntityType::Domain,
+            "example.com",
+            Some("Transfer wire unclear"),
+        ));
+        index.index_page(&CapturedPage::new(
+            "prime".to_string(),
+            "https://example.com/about".to_string(),
+            "About".to_string(),
+            "Contact alice for wire transfers".to_string(),
+        ));
+        index
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let clauses = parse_query(r#"type:email notes:"wire transfer" ali* http://x"#).unwrap();
+        assert_eq!(clauses[0], Clause::Kind("email".to_string()));
+        assert_eq!(
+            clauses[1],
+            Clause::Terms {
+                field: Some(SearchField::Notes),
+                tokens: vec!["wire".to_string(), "transfer".to_string()],
+                prefix: false,
+            }
+        );
+        assert_eq!(
+            clauses[2],
+            Clause::Terms {
+                field: None,
+                tokens: vec!["ali".to_string()],
+                prefix: true,
+            }
+        );
+        assert_eq!(
+            clauses[3],
+            Clause::Terms {
+                field: None,
+                tokens: vec!["http".to_string(), "x".to_string()],
+                prefix: false,
+            }
+        );
+        assert!(parse_query(r#"bogus:"x""#).is_err());
+    }
+
+    #[test]
+    fn test_phrase_and_field_scope() {
+        let index = index();
+        assert_eq!(index.search("wire transfer", 10).unwrap().len(), 2);
+        assert_eq!(index.search(r#""wire transfer""#, 10).unwrap().len(), 1);
+        assert_eq!(index.search("wire transf*", 10).unwrap().len(), 3);
+        assert_eq!(
+            index.search(r#"title:"wire transfer""#, 10).unwrap().len(),
+            0
+        );
+        assert_eq!(
+            index
+                .search(r#"type:email notes:"wire transfer""#, 10)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(index.search("type:page transf*", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reindex_and_remove() {
+        let mut index = index();
+        index.index_entity(&entity(EntityType::Email, "alice@example.com", None));
+        assert_eq!(index.search("notes:friday", 10).unwrap().len(), 0);
+        assert_eq!(index.search("value:alice", 10).unwrap().len(), 1);
+
+        index.remove_where(|doc| matches!(doc, SearchDoc::Entity { .. }));
+        assert_eq!(index.docs.len(), 1);
+        let hits = index.search("alice", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(matches!(hits[0].doc, SearchDoc::Page { .. }));
+    }
+}
@@ -1,40 +1,221 @@
 //! Sled Database Store
 //!
 //! High-performance embedded database for Spin data.
+//!
+//! Every value is sealed with AES-256-GCM before it reaches disk (see
+//! [`crate::storage::crypto`]). Keys stay in plaintext so lookups and prefix
+//! scans keep working; they only ever hold identity IDs, generated record IDs
+//! and, for entities and their indexes, keyed hashes (see
+//! [`entity_index`](crate::storage::entity_index)).
 
 use crate::core::entity::Entity;
 use crate::core::identity::Identity;
+use crate::investigation::{Investigation, InvestigationGraph};
+use crate::storage::audit::{AuditAction, AuditRecord};
+use crate::storage::backup::{SessionRecord, StoreSnapshot};
+use crate::storage::crypto::{KdfParams, StoreCipher, TreeKeys};
+use crate::storage::entity_index::{self, EntityFilter, EntityIndexKeys, EntityPage};
+use crate::storage::migrations::{
+    canonicalise, AppliedMigration, Canonicalised, MigrationContext, MigrationOptions,
+    MigrationReport, MIGRATIONS, SCHEMA_VERSION,
+};
+use crate::storage::search::{CapturedPage, SearchDoc, SearchHit, SearchIndex};
+use crate::storage::store::Store;
 use crate::storage::StorageError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sled::transaction::{ConflictableTransactionError, TransactionError};
+use sled::Transactional;
 use sled::{Db, Tree};
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// `meta` key holding the serialized [`KdfParams`]
+const META_KDF: &str = "kdf";
+
+/// `meta` key holding the sealed passphrase verifier
+const META_VERIFIER: &str = "verifier";
+
+/// `meta` key holding the sealed [`TreeKeys`] key
+const META_TREE_KEY: &str = "tree_key";
+
+/// `config` key holding the schema version
+const CONFIG_SCHEMA_VERSION: &str = "schema_version";
+
+/// `config` key prefix for named settings
+const CONFIG_SETTING_PREFIX: &str = "setting/";
+
+/// `config` key holding the hash of the newest audit record
+const CONFIG_AUDIT_HEAD: &str = "audit_head";
 
 /// Sled-based storage
 pub struct SledStore {
     db: Db,
+    meta: Tree,
+    cipher: RwLock<StoreCipher>,
+    /// Keyed hash for entity and index tree keys
+    tree_keys: TreeKeys,
+    /// False only for a dry run over a store written before encryption: its
+    /// values are read as plaintext and every write is refused
+    sealed: bool,
+    /// Write barrier. Every write holds it shared from sealing its values
+    /// until sled has committed them; re-keying takes it exclusively.
+    writes: RwLock<()>,
     identities: Tree,
     entities: Tree,
     sessions: Tree,
     config: Tree,
+    investigations: Tree,
+    timeline_events: Tree,
+    graph_nodes: Tree,
+    graph_edges: Tree,
+    pages: Tree,
+    audit: Tree,
+    idx_entity_type: Tree,
+    idx_entity_tag: Tree,
+    idx_entity_identity: Tree,
+    idx_entity_last_seen: Tree,
+    search: RwLock<SearchIndex>,
+    /// Serialises audit appends so each links to the true previous record
+    audit_lock: Mutex<()>,
+    /// What was last written for each investigation, so saves only rewrite
+    /// the records that changed
+    persisted_cases: Mutex<HashMap<String, PersistedCase>>,
+}
+
+/// SHA-256 of the plaintext of each record stored for one investigation
+#[derive(Debug, Default)]
+struct PersistedCase {
+    header: Option<[u8; 32]>,
+    timeline: Vec<[u8; 32]>,
+    nodes: Vec<[u8; 32]>,
+    edges: Vec<[u8; 32]>,
+}
+
+/// Child records of one investigation that a save has to write or remove
+struct ChildChanges {
+    digests: Vec<[u8; 32]>,
+    writes: Vec<(String, Vec<u8>)>,
+    removals: Vec<String>,
+}
+
+impl ChildChanges {
+    fn apply(
+        &self,
+        tree: &sled::transaction::TransactionalTree,
+    ) -> sled::transaction::ConflictableTransactionResult<(), StorageError> {
+        for key in &self.removals {
+            tree.remove(key.as_bytes())?;
+        }
+        for (key, sealed) in &self.writes {
+            tree.insert(key.as_bytes(), sealed.as_slice())?;
+        }
+        Ok(())
+    }
 }
 
 impl SledStore {
-    /// Create a new store
-    pub fn new(path: &Path) -> Result<Self, StorageError> {
-        let db = sled::open(path)?;
+    /// Open (or create) a store, unlock it with `passphrase` and bring its
+    /// schema up to date with the default [`MigrationOptions`].
+    ///
+    /// Fails closed with [`StorageError::WrongPassphrase`] if the passphrase
+    /// does not match the one the store was sealed with. A store written by a
+    /// build without encryption is sealed in place on first unlock.
+    pub fn new(path: &Path, passphrase: &str) -> Result<Self, StorageError> {
+        let (store, report) = Self::open(path, passphrase, &MigrationOptions::default())?;
+        if !report.is_empty() {
+            tracing::info!(
+                "Store migrated from schema v{} to v{}",
+                report.from_version,
+                report.to_version
+            );
+        }
+        Ok(store)
+    }
+
+    /// Open a store like [`SledStore::new`] with explicit migration options.
+    ///
+    /// With `dry_run` set, pending migrations are only planned and nothing is
+    /// written: a plaintext store is not sealed, and the returned store is
+    /// left at its old schema version and should be dropped after inspecting
+    /// the report.
+    pub fn open(
+        path: &Path,
+        passphrase: &str,
+        options: &MigrationOptions,
+    ) -> Result<(Self, MigrationReport), StorageError> {
+        let db = open_db(path)?;
+        let meta = db.open_tree("meta")?;
 
         let identities = db.open_tree("identities")?;
         let entities = db.open_tree("entities")?;
         let sessions = db.open_tree("sessions")?;
         let config = db.open_tree("config")?;
+        let investigations = db.open_tree("investigations")?;
+        let timeline_events = db.open_tree("timeline_events")?;
+        let graph_nodes = db.open_tree("graph_nodes")?;
+        let graph_edges = db.open_tree("graph_edges")?;
+        let pages = db.open_tree("pages")?;
+        let audit = db.open_tree("audit")?;
+        let idx_entity_type = db.open_tree("idx_entity_type")?;
+        let idx_entity_tag = db.open_tree("idx_entity_tag")?;
+        let idx_entity_identity = db.open_tree("idx_entity_identity")?;
+        let idx_entity_last_seen = db.open_tree("idx_entity_last_seen")?;
+
+        let (cipher, tree_keys, sealed) = Self::unlock(
+            &meta,
+            &[
+                &identities,
+                &entities,
+                &sessions,
+                &config,
+                &investigations,
+                &timeline_events,
+                &graph_nodes,
+                &graph_edges,
+                &pages,
+                &audit,
+            ],
+            passphrase,
+            options.dry_run,
+        )?;
 
         let store = Self {
             db,
+            meta,
+            cipher: RwLock::new(cipher),
+            tree_keys,
+            sealed,
+            writes: RwLock::new(()),
             identities,
             entities,
             sessions,
             config,
+            investigations,
+            timeline_events,
+            graph_nodes,
+            graph_edges,
+            pages,
+            audit,
+            idx_entity_type,
+            idx_entity_tag,
+            idx_entity_identity,
+            idx_entity_last_seen,
+            search: RwLock::new(SearchIndex::default()),
+            audit_lock: Mutex::new(()),
+            persisted_cases: Mutex::new(HashMap::new()),
         };
 
+        let report = store.migrate(path, options)?;
+        if options.dry_run {
+            return Ok((store, report));
+        }
+
+        store.rebuild_search_index()?;
+
         // Ensure Prime identity exists
         if store.get_identity("prime")?.is_none() {
             let prime = Identity::prime();
@@ -42,24 +223,595 @@ impl SledStore {
             store.set_active_identity("prime")?;
         }
 
-        Ok(store)
+        Ok((store, report))
+    }
+
+    // ============ Schema Versioning ============
+
+    /// Record the schema version
+    fn set_schema_version(&self, version: u32) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.config
+            .insert(CONFIG_SCHEMA_VERSION, self.encode(&version)?)?;
+        self.config.flush()?;
+        Ok(())
+    }
+
+    /// Run every registered migration newer than the stored schema version
+    fn migrate(
+        &self,
+        path: &Path,
+        options: &MigrationOptions,
+    ) -> Result<MigrationReport, StorageError> {
+        let from_version = match self.schema_version()? {
+            Some(version) => version,
+            // A brand-new store starts at the current schema
+            None if self.sealed_trees().iter().all(|tree| tree.is_empty()) => {
+                if !options.dry_run {
+                    self.set_schema_version(SCHEMA_VERSION)?;
+                }
+                SCHEMA_VERSION
+            }
+            None => 0,
+        };
+
+        if from_version > SCHEMA_VERSION {
+            return Err(StorageError::SchemaTooNew {
+                found: from_version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        let mut report = MigrationReport {
+            from_version,
+            to_version: from_version,
+            dry_run: options.dry_run,
+            backup_path: None,
+            migrations: Vec::new(),
+            completed_at: chrono::Utc::now(),
+        };
+
+        let pending: Vec<_> = MIGRATIONS
+            .iter()
+            .filter(|m| m.version > from_version)
+            .collect();
+        if pending.is_empty() {
+            return Ok(report);
+        }
+
+        if options.backup && !options.dry_run {
+            report.backup_path = Some(self.backup_before_migration(path, from_version)?);
+        }
+
+        for migration in pending {
+            let mut ctx = MigrationContext::new(self, options.dry_run);
+            (migration.apply)(&mut ctx)?;
+            if !options.dry_run {
+                self.set_schema_version(migration.version)?;
+            }
+
+            tracing::info!(
+                "{} schema migration v{}: {} ({} records)",
+                if options.dry_run {
+                    "Planned"
+                } else {
+                    "Applied"
+                },
+                migration.version,
+                migration.description,
+                ctx.records_changed()
+            );
+
+            report.to_version = migration.version;
+            report.migrations.push(AppliedMigration {
+                version: migration.version,
+                description: migration.description.to_string(),
+                records_changed: ctx.records_changed(),
+            });
+        }
+
+        report.completed_at = chrono::Utc::now();
+        Ok(report)
+    }
+
+    /// Copy the whole database (still sealed) next to `path` before migrating
+    fn backup_before_migration(
+        &self,
+        path: &Path,
+        from_version: u32,
+    ) -> Result<PathBuf, StorageError> {
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "spin.db".to_string());
+        let backup_path = path.with_file_name(format!(
+            "{}.pre-v{}-{}",
+            file_name,
+            from_version,
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+        ));
+
+        tracing::info!("Backing up store to {:?} before migrating", backup_path);
+
+        let backup = sled::open(&backup_path)?;
+        backup.import(self.db.export());
+        backup.flush()?;
+        Ok(backup_path)
+    }
+
+    /// Look up a sealed tree by its sled name
+    pub(super) fn tree_by_name(&self, name: &str) -> Result<&Tree, StorageError> {
+        match name {
+            "identities" => Ok(&self.identities),
+            "entities" => Ok(&self.entities),
+            "sessions" => Ok(&self.sessions),
+            "config" => Ok(&self.config),
+            "investigations" => Ok(&self.investigations),
+            "timeline_events" => Ok(&self.timeline_events),
+            "graph_nodes" => Ok(&self.graph_nodes),
+            "graph_edges" => Ok(&self.graph_edges),
+            "pages" => Ok(&self.pages),
+            _ => Err(StorageError::UnknownTree(name.to_string())),
+        }
+    }
+
+    // ============ Encryption ============
+
+    /// Derive the store key and check it against the sealed verifier, or
+    /// initialise encryption (sealing any existing plaintext) on first use.
+    ///
+    /// Returns the cipher, the tree key and whether the store is sealed,
+    /// which is only false when a dry run leaves a plaintext store untouched.
+    /// A store sealed before tree keys were keyed gets a fresh tree key here;
+    /// migration v4 re-keys its entities.
+    fn unlock(
+        meta: &Tree,
+        trees: &[&Tree],
+        passphrase: &str,
+        dry_run: bool,
+    ) -> Result<(StoreCipher, TreeKeys, bool), StorageError> {
+        if let Some(bytes) = meta.get(META_KDF)? {
+            let params: KdfParams = serde_json::from_slice(&bytes)?;
+            let cipher = StoreCipher::derive(passphrase, &params)?;
+            let verifier = meta
+                .get(META_VERIFIER)?
+                .ok_or_else(|| StorageError::Crypto("Missing passphrase verifier".to_string()))?;
+            cipher.check_verifier(&verifier)?;
+            let tree_keys = match meta.get(META_TREE_KEY)? {
+                Some(sealed) => TreeKeys::open(&cipher, &sealed)?,
+                None => {
+                    let tree_keys = TreeKeys::generate();
+                    if !dry_run {
+                        meta.insert(META_TREE_KEY, tree_keys.seal(&cipher)?)?;
+                        meta.flush()?;
+                    }
+                    tree_keys
+                }
+            };
+            return Ok((cipher, tree_keys, true));
+        }
+
+        let params = KdfParams::generate();
+        let cipher = StoreCipher::derive(passphrase, &params)?;
+        let tree_keys = TreeKeys::generate();
+        if dry_run {
+            return Ok((cipher, tree_keys, false));
+        }
+
+        tracing::info!("Initialising encryption at rest for the store");
+        Self::reseal(
+            meta,
+            trees,
+            |value| cipher.seal(value),
+            &params,
+            &cipher,
+            &tree_keys,
+        )?;
+        Ok((cipher, tree_keys, true))
+    }
+
+    /// Rewrite every value in `trees` through `transform` and record the KDF
+    /// parameters, verifier and tree key for `cipher`, all in one transaction
+    fn reseal<F>(
+        meta: &Tree,
+        trees: &[&Tree],
+        transform: F,
+        params: &KdfParams,
+        cipher: &StoreCipher,
+        tree_keys: &TreeKeys,
+    ) -> Result<(), StorageError>
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>, StorageError>,
+    {
+        let mut rewritten: Vec<Vec<(sled::IVec, Vec<u8>)>> = Vec::with_capacity(trees.len());
+        for tree in trees {
+            let mut values = Vec::new();
+            for result in tree.iter() {
+                let (key, value) = result?;
+                values.push((key, transform(&value)?));
+            }
+            rewritten.push(values);
+        }
+
+        let params_json = serde_json::to_vec(params)?;
+        let verifier = cipher.make_verifier()?;
+        let tree_key = tree_keys.seal(cipher)?;
+
+        let mut all: Vec<&Tree> = trees.to_vec();
+        all.push(meta);
+        all[..]
+            .transaction(|txs| {
+                for (tx, values) in txs.iter().zip(&rewritten) {
+                    for (key, value) in values {
+                        tx.insert(key, value.as_slice())?;
+                    }
+                }
+                let meta_tx = &txs[txs.len() - 1];
+                meta_tx.insert(META_KDF, params_json.as_slice())?;
+                meta_tx.insert(META_VERIFIER, verifier.as_slice())?;
+                meta_tx.insert(META_TREE_KEY, tree_key.as_slice())?;
+                Ok(())
+            })
+            .map_err(from_transaction_error)?;
+
+        meta.flush()?;
+        Ok(())
+    }
+
+    /// Every tree whose values are sealed
+    fn sealed_trees(&self) -> Vec<&Tree> {
+        vec![
+            &self.identities,
+            &self.entities,
+            &self.sessions,
+            &self.config,
+            &self.investigations,
+            &self.timeline_events,
+            &self.graph_nodes,
+            &self.graph_edges,
+            &self.pages,
+            &self.audit,
+        ]
+    }
+
+    /// Hold off re-keying (and anything else that needs the store quiet)
+    /// until the returned guard is dropped
+    pub(super) fn write_guard(&self) -> Result<RwLockReadGuard<'_, ()>, StorageError> {
+        self.writes.read().map_err(|_| StorageError::LockPoisoned)
+    }
+
+    /// Wait for in-flight writes to commit and block new ones until the
+    /// returned guard is dropped
+    fn exclusive_writes(&self) -> Result<RwLockWriteGuard<'_, ()>, StorageError> {
+        self.writes.write().map_err(|_| StorageError::LockPoisoned)
+    }
+
+    /// Seal raw bytes with the current store key
+    pub(super) fn seal_bytes(&self, plaintext: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if !self.sealed {
+            return Err(StorageError::Unsupported(
+                "Store is open read-only for a dry run".to_string(),
+            ));
+        }
+        self.cipher
+            .read()
+            .map_err(|_| StorageError::Crypto("Cipher lock poisoned".to_string()))?
+            .seal(plaintext)
+    }
+
+    /// Open raw bytes sealed with the current store key
+    pub(super) fn open_bytes(&self, sealed: &[u8]) -> Result<Vec<u8>, StorageError> {
+        if !self.sealed {
+            return Ok(sealed.to_vec());
+        }
+        self.cipher
+            .read()
+            .map_err(|_| StorageError::Crypto("Cipher lock poisoned".to_string()))?
+            .open(sealed)
+    }
+
+    /// Serialize and seal a record
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        self.seal_bytes(&serde_json::to_vec(value)?)
+    }
+
+    /// Open and deserialize a record
+    fn decode<T: DeserializeOwned>(&self, sealed: &[u8]) -> Result<T, StorageError> {
+        Ok(serde_json::from_slice(&self.open_bytes(sealed)?)?)
     }
 
+    // ============ Entity Index Maintenance ============
+
+    /// Rebuild every entity index from the `entities` tree.
+    ///
+    /// Returns the number of entities indexed.
+    pub fn rebuild_entity_indexes(&self) -> Result<usize, StorageError> {
+        let _writes = self.write_guard()?;
+        for index in self.entity_index_trees() {
+            index.clear()?;
+        }
+
+        let mut count = 0;
+        for result in self.entities.iter() {
+            let (_, value) = result?;
+            let entity: Entity = self.decode(&value)?;
+            let keys = EntityIndexKeys::for_entity(&entity, &self.tree_keys);
+            self.idx_entity_type.insert(keys.by_type, &[])?;
+            for key in keys.by_tag {
+                self.idx_entity_tag.insert(key, &[])?;
+            }
+            for key in keys.by_identity {
+                self.idx_entity_identity.insert(key, &[])?;
+            }
+            self.idx_entity_last_seen.insert(keys.by_last_seen, &[])?;
+            count += 1;
+        }
+
+        self.flush()?;
+        Ok(count)
+    }
+
+    /// Re-key every entity by its canonical value, merging records that
+    /// collapse to the same one. Returns the number of entities changed and
+    /// the old → new hash of each re-keyed entity.
+    ///
+    /// In a dry run nothing is written. Secondary indexes must be rebuilt
+    /// afterwards.
+    pub fn canonicalise_entities(
+        &self,
+        dry_run: bool,
+    ) -> Result<(usize, HashMap<String, String>), StorageError> {
+        let _writes = self.write_guard()?;
+        let entities = self
+            .entities
+            .iter()
+            .map(|result| self.decode::<Entity>(&result?.1))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Canonicalised {
+            entities: merged,
+            changed,
+            rehashed,
+        } = canonicalise(entities);
+
+        if !dry_run && changed > 0 {
+            let mut batch = sled::Batch::default();
+            for key in self.entities.iter().keys() {
+                batch.remove(key?);
+            }
+            for entity in merged.values() {
+                batch.insert(
+                    entity_index::entity_key(&self.tree_keys, &entity.hash),
+                    self.encode(entity)?,
+                );
+            }
+            self.entities.apply_batch(batch)?;
+            self.entities.flush()?;
+        }
+        Ok((changed, rehashed))
+    }
+
+    /// Move every entity whose record is not at its keyed entity key there.
+    /// Returns the number of entities moved (or that would move, in a dry
+    /// run). Secondary indexes must be rebuilt afterwards.
+    pub fn rekey_entities(&self, dry_run: bool) -> Result<usize, StorageError> {
+        let _writes = self.write_guard()?;
+        let mut batch = sled::Batch::default();
+        let mut moved = 0;
+
+        for result in self.entities.iter() {
+            let (key, value) = result?;
+            let entity: Entity = self.decode(&value)?;
+            let entity_key = entity_index::entity_key(&self.tree_keys, &entity.hash);
+            if key.as_ref() != entity_key.as_slice() {
+                moved += 1;
+                batch.remove(key);
+                batch.insert(entity_key, value);
+            }
+        }
+
+        if !dry_run && moved > 0 {
+            self.entities.apply_batch(batch)?;
+            self.entities.flush()?;
+        }
+        Ok(moved)
+    }
+
+    /// Number of stored entities
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Load up to `limit` entities named by a stream of index keys
+    fn collect_page<I>(
+        &self,
+        keys: I,
+        prefix_len: usize,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError>
+    where
+        I: Iterator<Item = sled::Result<(sled::IVec, sled::IVec)>>,
+    {
+        let mut entities = Vec::new();
+        let mut last_key = None;
+        let mut has_more = false;
+
+        for result in keys {
+            let (key, _) = result?;
+            if entities.len() == limit {
+                has_more = true;
+                break;
+            }
+            let entity_key = entity_index::entity_key_from(&key, prefix_len);
+            if let Some(bytes) = self.entities.get(entity_key)? {
+                entities.push(self.decode(&bytes)?);
+            }
+            last_key = Some(key);
+        }
+
+        Ok(EntityPage {
+            entities,
+            next_cursor: if has_more {
+                last_key.map(|k| entity_index::encode_cursor(&k))
+            } else {
+                None
+            },
+        })
+    }
+
+    /// The secondary index trees over `entities`
+    fn entity_index_trees(&self) -> [&Tree; 4] {
+        [
+            &self.idx_entity_type,
+            &self.idx_entity_tag,
+            &self.idx_entity_identity,
+            &self.idx_entity_last_seen,
+        ]
+    }
+
+    // ============ Investigation Records ============
+
+    /// Seal the children of an investigation whose plaintext differs from
+    /// `previous`, and list the keys of any that no longer exist
+    fn diff_children<T: Serialize>(
+        &self,
+        investigation_id: &str,
+        previous: &[[u8; 32]],
+        items: &[T],
+    ) -> Result<ChildChanges, StorageError> {
+        let mut changes = ChildChanges {
+            digests: Vec::with_capacity(items.len()),
+            writes: Vec::new(),
+            removals: Vec::new(),
+        };
+        for (position, item) in items.iter().enumerate() {
+            let plaintext = serde_json::to_vec(item)?;
+            let digest: [u8; 32] = Sha256::digest(&plaintext).into();
+            if previous.get(position) != Some(&digest) {
+                changes.writes.push((
+                    child_key(investigation_id, position),
+                    self.seal_bytes(&plaintext)?,
+                ));
+            }
+            changes.digests.push(digest);
+        }
+        changes.removals = (items.len()..previous.len())
+            .map(|position| child_key(investigation_id, position))
+            .collect();
+        Ok(changes)
+    }
+
+    /// Load the child records stored under `<investigation_id>/` in key
+    /// order, with the digest of each one's plaintext
+    fn load_children<T: DeserializeOwned>(
+        &self,
+        tree: &Tree,
+        investigation_id: &str,
+    ) -> Result<(Vec<T>, Vec<[u8; 32]>), StorageError> {
+        let mut items = Vec::new();
+        let mut digests = Vec::new();
+        for result in tree.scan_prefix(format!("{}/", investigation_id)) {
+            let (_, value) = result?;
+            let plaintext = self.open_bytes(&value)?;
+            digests.push(Sha256::digest(&plaintext).into());
+            items.push(serde_json::from_slice(&plaintext)?);
+        }
+        Ok((items, digests))
+    }
+
+    /// Load an investigation together with the digests of its stored records
+    fn load_investigation(
+        &self,
+        id: &str,
+    ) -> Result<Option<(Investigation, PersistedCase)>, StorageError> {
+        let Some(bytes) = self.investigations.get(id)? else {
+            return Ok(None);
+        };
+        let header = self.open_bytes(&bytes)?;
+        let mut investigation: Investigation = serde_json::from_slice(&header)?;
+        let (timeline, timeline_digests) = self.load_children(&self.timeline_events, id)?;
+        let (nodes, node_digests) = self.load_children(&self.graph_nodes, id)?;
+        let (edges, edge_digests) = self.load_children(&self.graph_edges, id)?;
+        investigation.timeline = timeline;
+        investigation.graph.nodes = nodes;
+        investigation.graph.edges = edges;
+
+        let persisted = PersistedCase {
+            header: Some(Sha256::digest(&header).into()),
+            timeline: timeline_digests,
+            nodes: node_digests,
+            edges: edge_digests,
+        };
+        Ok(Some((investigation, persisted)))
+    }
+
+    fn cases(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, HashMap<String, PersistedCase>>, StorageError> {
+        self.persisted_cases
+            .lock()
+            .map_err(|_| StorageError::LockPoisoned)
+    }
+
+    // ============ Search Index ============
+
+    /// Rebuild the full-text index from the decrypted records
+    pub fn rebuild_search_index(&self) -> Result<(), StorageError> {
+        let mut index = SearchIndex::default();
+        for result in self.entities.iter() {
+            let (_, value) = result?;
+            index.index_entity(&self.decode(&value)?);
+        }
+        for result in self.timeline_events.iter() {
+            let (_, value) = result?;
+            index.index_timeline_event(&self.decode(&value)?);
+        }
+        for result in self.pages.iter() {
+            let (_, value) = result?;
+            index.index_page(&self.decode(&value)?);
+        }
+        *self.search_index_mut()? = index;
+        Ok(())
+    }
+
+    fn search_index_mut(
+        &self,
+    ) -> Result<std::sync::RwLockWriteGuard<'_, SearchIndex>, StorageError> {
+        self.search
+            .write()
+            .map_err(|_| StorageError::Search("Search index lock poisoned".to_string()))
+    }
+
+    // ============ Database Operations ============
+
+    /// Get database statistics
+    pub fn stats(&self) -> DatabaseStats {
+        DatabaseStats {
+            identity_count: self.identities.len(),
+            entity_count: self.entities.len(),
+            session_count: self.sessions.len(),
+            investigation_count: self.investigations.len(),
+            size_on_disk: self.db.size_on_disk().unwrap_or(0),
+        }
+    }
+}
+
+impl Store for SledStore {
     // ============ Identity Operations ============
 
     /// Save an identity
-    pub fn save_identity(&self, identity: &Identity) -> Result<(), StorageError> {
-        let json = serde_json::to_vec(identity)?;
-        self.identities.insert(&identity.id, json)?;
+    fn save_identity(&self, identity: &Identity) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.identities
+            .insert(&identity.id, self.encode(identity)?)?;
         self.identities.flush()?;
         Ok(())
     }
 
     /// Get an identity by ID
-    pub fn get_identity(&self, id: &str) -> Result<Option<Identity>, StorageError> {
+    fn get_identity(&self, id: &str) -> Result<Option<Identity>, StorageError> {
         match self.identities.get(id)? {
             Some(bytes) => {
-                let identity: Identity = serde_json::from_slice(&bytes)?;
+                let identity: Identity = self.decode(&bytes)?;
                 Ok(Some(identity))
             }
             None => Ok(None),
@@ -67,34 +819,39 @@ impl SledStore {
     }
 
     /// Get all identities
-    pub fn get_all_identities(&self) -> Result<Vec<Identity>, StorageError> {
+    fn get_all_identities(&self) -> Result<Vec<Identity>, StorageError> {
         let mut identities = Vec::new();
         for result in self.identities.iter() {
             let (_, value) = result?;
-            let identity: Identity = serde_json::from_slice(&value)?;
+            let identity: Identity = self.decode(&value)?;
             identities.push(identity);
         }
         Ok(identities)
     }
 
     /// Delete an identity
-    pub fn delete_identity(&self, id: &str) -> Result<(), StorageError> {
+    fn delete_identity(&self, id: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
         self.identities.remove(id)?;
         self.identities.flush()?;
         Ok(())
     }
 
+    // ============ Config Operations ============
+
     /// Set the active identity
-    pub fn set_active_identity(&self, id: &str) -> Result<(), StorageError> {
-        self.config.insert("active_identity", id.as_bytes())?;
+    fn set_active_identity(&self, id: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.config
+            .insert("active_identity", self.seal_bytes(id.as_bytes())?)?;
         self.config.flush()?;
         Ok(())
     }
 
     /// Get the active identity
-    pub fn get_active_identity(&self) -> Result<Identity, StorageError> {
+    fn get_active_identity(&self) -> Result<Identity, StorageError> {
         let active_id = match self.config.get("active_identity")? {
-            Some(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+            Some(bytes) => String::from_utf8_lossy(&self.open_bytes(&bytes)?).to_string(),
             None => "prime".to_string(),
         };
 
@@ -102,21 +859,139 @@ impl SledStore {
             .ok_or_else(|| StorageError::NotFound(active_id))
     }
 
+    /// Read a setting stored under `setting/<key>` in the `config` tree
+    fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>, StorageError> {
+        match self
+            .config
+            .get(format!("{}{}", CONFIG_SETTING_PREFIX, key))?
+        {
+            Some(bytes) => Ok(Some(self.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Write a setting under `setting/<key>` in the `config` tree
+    fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.config.insert(
+            format!("{}{}", CONFIG_SETTING_PREFIX, key),
+            self.encode(value)?,
+        )?;
+        self.config.flush()?;
+        Ok(())
+    }
+
+    /// Remove `setting/<key>` from the `config` tree
+    fn delete_setting(&self, key: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.config
+            .remove(format!("{}{}", CONFIG_SETTING_PREFIX, key))?;
+        self.config.flush()?;
+        Ok(())
+    }
+
+    /// Get the stored schema version (`None` for stores that predate versioning)
+    fn schema_version(&self) -> Result<Option<u32>, StorageError> {
+        match self.config.get(CONFIG_SCHEMA_VERSION)? {
+            Some(bytes) => Ok(Some(self.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Change the store passphrase, re-sealing every value under a fresh key.
+    ///
+    /// Writers are blocked for the duration; the rewrite and the new KDF
+    /// parameters are committed in a single transaction.
+    fn change_passphrase(&self, current: &str, new: &str) -> Result<(), StorageError> {
+        let _quiesced = self.exclusive_writes()?;
+        let mut cipher = self
+            .cipher
+            .write()
+            .map_err(|_| StorageError::Crypto("Cipher lock poisoned".to_string()))?;
+
+        let params: KdfParams = match self.meta.get(META_KDF)? {
+            Some(bytes) => serde_json::from_slice(&bytes)?,
+            None => return Err(StorageError::Crypto("Store is not encrypted".to_string())),
+        };
+        let verifier = self
+            .meta
+            .get(META_VERIFIER)?
+            .ok_or_else(|| StorageError::Crypto("Missing passphrase verifier".to_string()))?;
+        StoreCipher::derive(current, &params)?.check_verifier(&verifier)?;
+
+        let new_params = KdfParams::generate();
+        let new_cipher = StoreCipher::derive(new, &new_params)?;
+        Self::reseal(
+            &self.meta,
+            &self.sealed_trees(),
+            |value| new_cipher.seal(&cipher.open(value)?),
+            &new_params,
+            &new_cipher,
+            &self.tree_keys,
+        )?;
+
+        *cipher = new_cipher;
+        tracing::info!("Store passphrase changed");
+        Ok(())
+    }
+
     // ============ Entity Operations ============
 
-    /// Save an entity
-    pub fn save_entity(&self, entity: &Entity) -> Result<(), StorageError> {
-        let json = serde_json::to_vec(entity)?;
-        self.entities.insert(&entity.hash, json)?;
+    /// Save an entity and update its secondary index entries atomically
+    fn save_entity(&self, entity: &Entity) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        let sealed = self.encode(entity)?;
+        let key = entity_index::entity_key(&self.tree_keys, &entity.hash);
+        let new_keys = EntityIndexKeys::for_entity(entity, &self.tree_keys);
+
+        (
+            &self.entities,
+            &self.idx_entity_type,
+            &self.idx_entity_tag,
+            &self.idx_entity_identity,
+            &self.idx_entity_last_seen,
+        )
+            .transaction(|(entities, by_type, by_tag, by_identity, by_last_seen)| {
+                if let Some(previous) = entities.insert(key.as_slice(), sealed.as_slice())? {
+                    let previous: Entity = self
+                        .decode(&previous)
+                        .map_err(ConflictableTransactionError::Abort)?;
+                    let old_keys = EntityIndexKeys::for_entity(&previous, &self.tree_keys);
+                    by_type.remove(old_keys.by_type)?;
+                    for key in old_keys.by_tag {
+                        by_tag.remove(key)?;
+                    }
+                    for key in old_keys.by_identity {
+                        by_identity.remove(key)?;
+                    }
+                    by_last_seen.remove(old_keys.by_last_seen)?;
+                }
+
+                by_type.insert(new_keys.by_type.as_slice(), &[])?;
+                for key in &new_keys.by_tag {
+                    by_tag.insert(key.as_slice(), &[])?;
+                }
+                for key in &new_keys.by_identity {
+                    by_identity.insert(key.as_slice(), &[])?;
+                }
+                by_last_seen.insert(new_keys.by_last_seen.as_slice(), &[])?;
+                Ok(())
+            })
+            .map_err(from_transaction_error)?;
+
+        self.search_index_mut()?.index_entity(entity);
         self.entities.flush()?;
         Ok(())
     }
 
     /// Get an entity by hash
-    pub fn get_entity(&self, hash: &str) -> Result<Option<Entity>, StorageError> {
-        match self.entities.get(hash)? {
+    fn get_entity(&self, hash: &str) -> Result<Option<Entity>, StorageError> {
+        match self
+            .entities
+            .get(entity_index::entity_key(&self.tree_keys, hash))?
+        {
             Some(bytes) => {
-                let entity: Entity = serde_json::from_slice(&bytes)?;
+                let entity: Entity = self.decode(&bytes)?;
                 Ok(Some(entity))
             }
             None => Ok(None),
@@ -124,83 +999,733 @@ impl SledStore {
     }
 
     /// Get all entities
-    pub fn get_all_entities(&self) -> Result<Vec<Entity>, StorageError> {
+    fn get_all_entities(&self) -> Result<Vec<Entity>, StorageError> {
         let mut entities = Vec::new();
         for result in self.entities.iter() {
             let (_, value) = result?;
-            let entity: Entity = serde_json::from_slice(&value)?;
+            let entity: Entity = self.decode(&value)?;
             entities.push(entity);
         }
         Ok(entities)
     }
 
-    /// Clear all entities
-    pub fn clear_entities(&self) -> Result<(), StorageError> {
+    /// Delete an entity and its index entries atomically
+    fn delete_entity(&self, hash: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        let key = entity_index::entity_key(&self.tree_keys, hash);
+        (
+            &self.entities,
+            &self.idx_entity_type,
+            &self.idx_entity_tag,
+            &self.idx_entity_identity,
+            &self.idx_entity_last_seen,
+        )
+            .transaction(|(entities, by_type, by_tag, by_identity, by_last_seen)| {
+                if let Some(previous) = entities.remove(key.as_slice())? {
+                    let previous: Entity = self
+                        .decode(&previous)
+                        .map_err(ConflictableTransactionError::Abort)?;
+                    let old_keys = EntityIndexKeys::for_entity(&previous, &self.tree_keys);
+                    by_type.remove(old_keys.by_type)?;
+                    for key in old_keys.by_tag {
+                        by_tag.remove(key)?;
+                    }
+                    for key in old_keys.by_identity {
+                        by_identity.remove(key)?;
+                    }
+                    by_last_seen.remove(old_keys.by_last_seen)?;
+                }
+                Ok(())
+            })
+            .map_err(from_transaction_error)?;
+
+        self.search_index_mut()?.remove(&SearchDoc::Entity {
+            hash: hash.to_string(),
+        });
+        self.entities.flush()?;
+        Ok(())
+    }
+
+    /// Clear all entities and their index entries
+    fn clear_entities(&self) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
         self.entities.clear()?;
+        for index in self.entity_index_trees() {
+            index.clear()?;
+        }
+        self.search_index_mut()?
+            .remove_where(|doc| matches!(doc, SearchDoc::Entity { .. }));
         self.entities.flush()?;
         Ok(())
     }
 
-    /// Get entities by type
-    pub fn get_entities_by_type(
+    /// Page through the entities matching `filter`, in a stable order.
+    ///
+    /// Pass the previous page's `next_cursor` to continue.
+    fn query_entities(
+        &self,
+        filter: &EntityFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError> {
+        let index = match filter {
+            EntityFilter::Type(_) => &self.idx_entity_type,
+            EntityFilter::Tag(_) => &self.idx_entity_tag,
+            EntityFilter::Identity(_) => &self.idx_entity_identity,
+        };
+        let prefix = entity_index::prefix_for(filter, &self.tree_keys);
+
+        let start = match cursor.and_then(entity_index::decode_cursor) {
+            Some(key) if key.starts_with(&prefix) => Bound::Excluded(key),
+            _ => Bound::Included(prefix.clone()),
+        };
+
+        let keys = index
+            .range((start, Bound::Unbounded))
+            .take_while(|r| r.as_ref().map_or(true, |(k, _)| k.starts_with(&prefix)));
+        self.collect_page(keys, prefix.len(), limit)
+    }
+
+    /// Page through entities by `last_seen`, newest first.
+    ///
+    /// Only entities last seen strictly before `before` are returned, if given.
+    fn entities_by_last_seen(
         &self,
-        entity_type: &crate::core::entity::EntityType,
-    ) -> Result<Vec<Entity>, StorageError> {
-        let all = self.get_all_entities()?;
-        Ok(all
-            .into_iter()
-            .filter(|e| &e.entity_type == entity_type)
-            .collect())
+        before: Option<chrono::DateTime<chrono::Utc>>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError> {
+        let end = match cursor.and_then(entity_index::decode_cursor) {
+            Some(key) => Bound::Excluded(key),
+            None => match before {
+                Some(before) => Bound::Excluded(entity_index::time_prefix(before)),
+                None => Bound::Unbounded,
+            },
+        };
+
+        let keys = self
+            .idx_entity_last_seen
+            .range((Bound::Unbounded, end))
+            .rev();
+        self.collect_page(keys, 8, limit)
+    }
+
+    /// Hashes of entities attributed to more than one identity, found through
+    /// the identity index so only those entities are decoded
+    fn cross_reference_hashes(&self) -> Result<Vec<String>, StorageError> {
+        let mut identity_counts: HashMap<Vec<u8>, usize> = HashMap::new();
+        for result in self.idx_entity_identity.iter() {
+            let (key, _) = result?;
+            let entity_key = entity_index::entity_key_from(&key, entity_index::PREFIX_LEN);
+            *identity_counts.entry(entity_key.to_vec()).or_default() += 1;
+        }
+
+        let mut hashes = Vec::new();
+        for (entity_key, count) in identity_counts {
+            if count < 2 {
+                continue;
+            }
+            if let Some(bytes) = self.entities.get(entity_key)? {
+                let entity: Entity = self.decode(&bytes)?;
+                hashes.push(entity.hash);
+            }
+        }
+        Ok(hashes)
     }
 
     // ============ Session Operations ============
 
     /// Save session data for an identity
-    pub fn save_session(&self, identity_id: &str, data: &[u8]) -> Result<(), StorageError> {
-        self.sessions.insert(identity_id, data)?;
+    fn save_session(&self, identity_id: &str, data: &[u8]) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.sessions.insert(identity_id, self.seal_bytes(data)?)?;
         self.sessions.flush()?;
         Ok(())
     }
 
     /// Get session data for an identity
-    pub fn get_session(&self, identity_id: &str) -> Result<Option<Vec<u8>>, StorageError> {
+    fn get_session(&self, identity_id: &str) -> Result<Option<Vec<u8>>, StorageError> {
         match self.sessions.get(identity_id)? {
-            Some(bytes) => Ok(Some(bytes.to_vec())),
+            Some(bytes) => Ok(Some(self.open_bytes(&bytes)?)),
             None => Ok(None),
         }
     }
 
     /// Clear session for an identity
-    pub fn clear_session(&self, identity_id: &str) -> Result<(), StorageError> {
+    fn clear_session(&self, identity_id: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
         self.sessions.remove(identity_id)?;
         self.sessions.flush()?;
         Ok(())
     }
 
+    // ============ Investigation Operations ============
+
+    /// Save an investigation together with its timeline and graph.
+    ///
+    /// The case header lives in the `investigations` tree; timeline events,
+    /// graph nodes and graph edges each get their own tree, keyed by
+    /// `<investigation_id>/<position>` so load order matches insertion order.
+    /// Only records that differ from what was last stored are rewritten, in
+    /// one transaction across the four trees.
+    fn save_investigation(&self, investigation: &Investigation) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        let id = &investigation.id;
+        let header = case_header(investigation);
+
+        // Held until the cache is updated, so saves of a case cannot interleave
+        let mut cases = self.cases()?;
+        let previous = match cases.remove(id) {
+            Some(previous) => previous,
+            None => self
+                .load_investigation(id)?
+                .map(|(_, persisted)| persisted)
+                .unwrap_or_default(),
+        };
+
+        let header_plaintext = serde_json::to_vec(&header)?;
+        let header_digest: [u8; 32] = Sha256::digest(&header_plaintext).into();
+        let header_sealed = if previous.header == Some(header_digest) {
+            None
+        } else {
+            Some(self.seal_bytes(&header_plaintext)?)
+        };
+        let timeline = self.diff_children(id, &previous.timeline, &investigation.timeline)?;
+        let nodes = self.diff_children(id, &previous.nodes, &investigation.graph.nodes)?;
+        let edges = self.diff_children(id, &previous.edges, &investigation.graph.edges)?;
+
+        (
+            &self.investigations,
+            &self.timeline_events,
+            &self.graph_nodes,
+            &self.graph_edges,
+        )
+            .transaction(
+                |(investigations, timeline_events, graph_nodes, graph_edges)| {
+                    if let Some(sealed) = &header_sealed {
+                        investigations.insert(id.as_bytes(), sealed.as_slice())?;
+                    }
+                    timeline.apply(timeline_events)?;
+                    nodes.apply(graph_nodes)?;
+                    edges.apply(graph_edges)?;
+                    Ok(())
+                },
+            )
+            .map_err(from_transaction_error)?;
+
+        let timeline_changed = !timeline.writes.is_empty() || !timeline.removals.is_empty();
+        cases.insert(
+            id.clone(),
+            PersistedCase {
+                header: Some(header_digest),
+                timeline: timeline.digests,
+                nodes: nodes.digests,
+                edges: edges.digests,
+            },
+        );
+        drop(cases);
+
+        if timeline_changed {
+            let mut search = self.search_index_mut()?;
+            search.remove_timeline(id);
+            for event in &investigation.timeline {
+                search.index_timeline_event(event);
+            }
+        }
+
+        self.flush()
+    }
+
+    /// Get an investigation by ID, including its timeline and graph
+    fn get_investigation(&self, id: &str) -> Result<Option<Investigation>, StorageError> {
+        let Some((investigation, persisted)) = self.load_investigation(id)? else {
+            return Ok(None);
+        };
+        // A save that ran meanwhile already recorded the newer state
+        self.cases()?.entry(id.to_string()).or_insert(persisted);
+        Ok(Some(investigation))
+    }
+
+    /// Get all investigations with their timelines and graphs
+    fn get_all_investigations(&self) -> Result<Vec<Investigation>, StorageError> {
+        let mut investigations = Vec::new();
+        for result in self.investigations.iter() {
+            let (key, _) = result?;
+            let id = String::from_utf8_lossy(&key).to_string();
+            if let Some(investigation) = self.get_investigation(&id)? {
+                investigations.push(investigation);
+            }
+        }
+        Ok(investigations)
+    }
+
+    /// Delete an investigation and all of its timeline and graph records
+    fn delete_investigation(&self, id: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        let mut cases = self.cases()?;
+        let prefix = format!("{}/", id);
+        let child_keys = |tree: &Tree| {
+            tree.scan_prefix(&prefix)
+                .keys()
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let timeline_keys = child_keys(&self.timeline_events)?;
+        let node_keys = child_keys(&self.graph_nodes)?;
+        let edge_keys = child_keys(&self.graph_edges)?;
+
+        (
+            &self.investigations,
+            &self.timeline_events,
+            &self.graph_nodes,
+            &self.graph_edges,
+        )
+            .transaction(
+                |(investigations, timeline_events, graph_nodes, graph_edges)| {
+                    investigations.remove(id.as_bytes())?;
+                    for key in &timeline_keys {
+                        timeline_events.remove(key)?;
+                    }
+                    for key in &node_keys {
+                        graph_nodes.remove(key)?;
+                    }
+                    for key in &edge_keys {
+                        graph_edges.remove(key)?;
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(from_transaction_error)?;
+        cases.remove(id);
+        drop(cases);
+
+        self.search_index_mut()?.remove_timeline(id);
+        self.flush()
+    }
+
+    // ============ Page Capture Operations ============
+
+    /// Save captured page text, replacing any earlier capture of the same page
+    fn save_page(&self, page: &CapturedPage) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.pages.insert(&page.id, self.encode(page)?)?;
+        self.search_index_mut()?.index_page(page);
+        self.pages.flush()?;
+        Ok(())
+    }
+
+    /// Get a captured page by ID
+    fn get_page(&self, id: &str) -> Result<Option<CapturedPage>, StorageError> {
+        match self.pages.get(id)? {
+            Some(bytes) => Ok(Some(self.decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get every captured page
+    fn get_all_pages(&self) -> Result<Vec<CapturedPage>, StorageError> {
+        let mut pages = Vec::new();
+        for result in self.pages.iter() {
+            let (_, value) = result?;
+            pages.push(self.decode(&value)?);
+        }
+        Ok(pages)
+    }
+
+    /// Delete a captured page and drop it from the search index
+    fn delete_page(&self, id: &str) -> Result<(), StorageError> {
+        let _writes = self.write_guard()?;
+        self.pages.remove(id)?;
+        self.search_index_mut()?
+            .remove(&SearchDoc::Page { id: id.to_string() });
+        self.pages.flush()?;
+        Ok(())
+    }
+
+    /// Delete every page captured by an identity
+    fn delete_pages_by_identity(&self, identity_id: &str) -> Result<usize, StorageError> {
+        let _writes = self.write_guard()?;
+        let mut batch = sled::Batch::default();
+        let mut removed = Vec::new();
+        for result in self.pages.iter() {
+            let (key, value) = result?;
+            let page: CapturedPage = self.decode(&value)?;
+            if page.identity_id == identity_id {
+                batch.remove(key);
+                removed.push(page.id);
+            }
+        }
+        self.pages.apply_batch(batch)?;
+
+        let mut search = self.search_index_mut()?;
+        for id in &removed {
+            search.remove(&SearchDoc::Page { id: id.clone() });
+        }
+        drop(search);
+
+        self.pages.flush()?;
+        Ok(removed.len())
+    }
+
+    // ============ Search Operations ============
+
+    /// Run a full-text query across entities, timeline events and pages
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, StorageError> {
+        self.search
+            .read()
+            .map_err(|_| StorageError::Search("Search index lock poisoned".to_string()))?
+            .search(query, limit)
+    }
+
+    // ============ Audit Operations ============
+
+    /// Append a record keyed by its big-endian sequence number, moving the
+    /// head in the same transaction
+    fn append_audit(&self, actor: &str, action: AuditAction) -> Result<AuditRecord, StorageError> {
+        let _guard = self
+            .audit_lock
+            .lock()
+            .map_err(|_| StorageError::LockPoisoned)?;
+        let _writes = self.write_guard()?;
+
+        let prev: Option<AuditRecord> = match self.audit.last()? {
+            Some((_, value)) => Some(self.decode(&value)?),
+            None => None,
+        };
+        let record = AuditRecord::next(prev.as_ref(), actor, action);
+        let sealed = self.encode(&record)?;
+        let head = self.seal_bytes(record.hash.as_bytes())?;
+
+        (&self.audit, &self.config)
+            .transaction(|(audit, config)| {
+                audit.insert(&record.seq.to_be_bytes(), sealed.as_slice())?;
+                config.insert(CONFIG_AUDIT_HEAD, head.as_slice())?;
+                Ok(())
+            })
+            .map_err(from_transaction_error)?;
+
+        self.audit.flush()?;
+        Ok(record)
+    }
+
+    fn audit_log(&self) -> Result<Vec<AuditRecord>, StorageError> {
+        let mut records = Vec::new();
+        for result in self.audit.iter() {
+            let (_, value) = result?;
+            records.push(self.decode(&value)?);
+        }
+        Ok(records)
+    }
+
+    fn audit_head(&self) -> Result<Option<String>, StorageError> {
+        match self.config.get(CONFIG_AUDIT_HEAD)? {
+            Some(bytes) => Ok(Some(
+                String::from_utf8_lossy(&self.open_bytes(&bytes)?).to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    // ============ Snapshot Operations ============
+
+    /// Take a consistent, decrypted copy of every record.
+    ///
+    /// Writes are held off by the write barrier until every record has been
+    /// copied out and decrypted, so the copy cannot straddle a write or a
+    /// re-key.
+    fn snapshot(&self) -> Result<StoreSnapshot, StorageError> {
+        let _quiesced = self.exclusive_writes()?;
+        self.flush()?;
+        let dump = |tree: &Tree| tree.iter().collect::<Result<Vec<_>, _>>();
+        let identities = dump(&self.identities)?;
+        let entities = dump(&self.entities)?;
+        let sessions = dump(&self.sessions)?;
+        let config = dump(&self.config)?;
+        let pages = dump(&self.pages)?;
+        let investigations = dump(&self.investigations)?;
+        let timeline_events = dump(&self.timeline_events)?;
+        let graph_nodes = dump(&self.graph_nodes)?;
+        let graph_edges = dump(&self.graph_edges)?;
+
+        let mut snapshot = StoreSnapshot {
+            schema_version: self.schema_version()?.unwrap_or(SCHEMA_VERSION),
+            active_identity: None,
+            identities: Vec::new(),
+            entities: Vec::new(),
+            sessions: Vec::new(),
+            investigations: Vec::new(),
+            pages: Vec::new(),
+            settings: BTreeMap::new(),
+        };
+
+        for (_, value) in &identities {
+            snapshot.identities.push(self.decode(value)?);
+        }
+        for (_, value) in &entities {
+            snapshot.entities.push(self.decode(value)?);
+        }
+        for (key, value) in &sessions {
+            snapshot.sessions.push(SessionRecord {
+                identity_id: String::from_utf8_lossy(key).to_string(),
+                data: self.open_bytes(value)?,
+            });
+        }
+        for (key, value) in &config {
+            if key.as_ref() == b"active_identity" {
+                let id = self.open_bytes(value)?;
+                snapshot.active_identity = Some(String::from_utf8_lossy(&id).to_string());
+            } else if let Some(setting) = key.strip_prefix(CONFIG_SETTING_PREFIX.as_bytes()) {
+                snapshot.settings.insert(
+                    String::from_utf8_lossy(setting).to_string(),
+                    self.decode(value)?,
+                );
+            }
+        }
+        for (_, value) in &pages {
+            snapshot.pages.push(self.decode(value)?);
+        }
+
+        let mut by_id: HashMap<String, Investigation> = HashMap::new();
+        for (key, value) in &investigations {
+            by_id.insert(
+                String::from_utf8_lossy(key).to_string(),
+                self.decode(value)?,
+            );
+        }
+        for (key, value) in &timeline_events {
+            if let Some(investigation) = by_id.get_mut(&parent_id(key)) {
+                investigation.timeline.push(self.decode(value)?);
+            }
+        }
+        for (key, value) in &graph_nodes {
+            if let Some(investigation) = by_id.get_mut(&parent_id(key)) {
+                investigation.graph.nodes.push(self.decode(value)?);
+            }
+        }
+        for (key, value) in &graph_edges {
+            if let Some(investigation) = by_id.get_mut(&parent_id(key)) {
+                investigation.graph.edges.push(self.decode(value)?);
+            }
+        }
+        snapshot.investigations = by_id.into_values().collect();
+        snapshot.investigations.sort_by_key(|i| i.created_at);
+
+        Ok(snapshot)
+    }
+
+    /// Replace every record with the contents of a snapshot.
+    ///
+    /// Every value is sealed up front and swapped in by one transaction across
+    /// the record and index trees, so a failure leaves the store as it was.
+    /// Settings missing from the snapshot are removed; the schema version and
+    /// audit chain are kept.
+    fn restore_snapshot(&self, snapshot: &StoreSnapshot) -> Result<(), StorageError> {
+        let _quiesced = self.exclusive_writes()?;
+        let trees = [
+            &self.identities,
+            &self.entities,
+            &self.sessions,
+            &self.config,
+            &self.pages,
+            &self.investigations,
+            &self.timeline_events,
+            &self.graph_nodes,
+            &self.graph_edges,
+            &self.idx_entity_type,
+            &self.idx_entity_tag,
+            &self.idx_entity_identity,
+            &self.idx_entity_last_seen,
+        ];
+
+        let mut identities = Vec::new();
+        let mut entities = Vec::new();
+        let mut sessions = Vec::new();
+        let mut config = Vec::new();
+        let mut pages = Vec::new();
+        let mut investigations = Vec::new();
+        let mut timeline_events = Vec::new();
+        let mut graph_nodes = Vec::new();
+        let mut graph_edges = Vec::new();
+        let mut by_type = Vec::new();
+        let mut by_tag = Vec::new();
+        let mut by_identity = Vec::new();
+        let mut by_last_seen = Vec::new();
+
+        for identity in &snapshot.identities {
+            identities.push((identity.id.clone().into_bytes(), self.encode(identity)?));
+        }
+        for entity in &snapshot.entities {
+            entities.push((
+                entity_index::entity_key(&self.tree_keys, &entity.hash),
+                self.encode(entity)?,
+            ));
+            let keys = EntityIndexKeys::for_entity(entity, &self.tree_keys);
+            by_type.push((keys.by_type, Vec::new()));
+            by_tag.extend(keys.by_tag.into_iter().map(|key| (key, Vec::new())));
+            by_identity.extend(keys.by_identity.into_iter().map(|key| (key, Vec::new())));
+            by_last_seen.push((keys.by_last_seen, Vec::new()));
+        }
+        for session in &snapshot.sessions {
+            sessions.push((
+                session.identity_id.clone().into_bytes(),
+                self.seal_bytes(&session.data)?,
+            ));
+        }
+        if let Some(active) = &snapshot.active_identity {
+            config.push((
+                b"active_identity".to_vec(),
+                self.seal_bytes(active.as_bytes())?,
+            ));
+        }
+        for (key, value) in &snapshot.settings {
+            config.push((
+                format!("{}{}", CONFIG_SETTING_PREFIX, key).into_bytes(),
+                self.encode(value)?,
+            ));
+        }
+        for page in &snapshot.pages {
+            pages.push((page.id.clone().into_bytes(), self.encode(page)?));
+        }
+        for investigation in &snapshot.investigations {
+            let id = &investigation.id;
+            investigations.push((
+                id.clone().into_bytes(),
+                self.encode(&case_header(investigation))?,
+            ));
+            for (position, event) in investigation.timeline.iter().enumerate() {
+                timeline_events.push((child_key(id, position).into_bytes(), self.encode(event)?));
+            }
+            for (position, node) in investigation.graph.nodes.iter().enumerate() {
+                graph_nodes.push((child_key(id, position).into_bytes(), self.encode(node)?));
+            }
+            for (position, edge) in investigation.graph.edges.iter().enumerate() {
+                graph_edges.push((child_key(id, position).into_bytes(), self.encode(edge)?));
+            }
+        }
+
+        let inserts: [Vec<(Vec<u8>, Vec<u8>)>; 13] = [
+            identities,
+            entities,
+            sessions,
+            config,
+            pages,
+            investigations,
+            timeline_events,
+            graph_nodes,
+            graph_edges,
+            by_type,
+            by_tag,
+            by_identity,
+            by_last_seen,
+        ];
+        let mut removals = Vec::with_capacity(trees.len());
+        for tree in trees {
+            let mut keys = tree.iter().keys().collect::<Result<Vec<_>, _>>()?;
+            if std::ptr::eq(tree, &self.config) {
+                // The schema version and audit head stay
+                keys.retain(|key| {
+                    key.as_ref() == b"active_identity"
+                        || key.starts_with(CONFIG_SETTING_PREFIX.as_bytes())
+                });
+            }
+            removals.push(keys);
+        }
+
+        trees[..]
+            .transaction(|txs| {
+                for ((tx, keys), values) in txs.iter().zip(&removals).zip(&inserts) {
+                    for key in keys {
+                        tx.remove(key)?;
+                    }
+                    for (key, value) in values {
+                        tx.insert(key.as_slice(), value.as_slice())?;
+                    }
+                }
+                Ok(())
+            })
+            .map_err(from_transaction_error)?;
+
+        self.cases()?.clear();
+        self.rebuild_search_index()?;
+        self.flush()
+    }
+
     // ============ Database Operations ============
 
     /// Flush all pending writes
-    pub fn flush(&self) -> Result<(), StorageError> {
+    fn flush(&self) -> Result<(), StorageError> {
         self.db.flush()?;
         Ok(())
     }
+}
 
-    /// Get database statistics
-    pub fn stats(&self) -> DatabaseStats {
-        DatabaseStats {
-            identity_count: self.identities.len(),
-            entity_count: self.entities.len(),
-            session_count: self.sessions.len(),
-            size_on_disk: self.db.size_on_disk().unwrap_or(0),
+/// Open a sled database, retrying briefly while a just-closed handle to the
+/// same path still holds its file lock (e.g. when reopening a vault)
+fn open_db(path: &Path) -> Result<Db, StorageError> {
+    let mut attempts = 0;
+    loop {
+        match sled::open(path) {
+            Err(sled::Error::Io(e)) if is_lock_contention(&e) && attempts < 20 => {
+                attempts += 1;
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            result => return Ok(result?),
         }
     }
 }
 
+/// Whether opening failed because another handle still holds the file lock.
+///
+/// sled reports this as `ErrorKind::Other` with the OS error in the message.
+fn is_lock_contention(e: &std::io::Error) -> bool {
+    e.kind() == std::io::ErrorKind::WouldBlock
+        || e.to_string().starts_with("could not acquire lock")
+}
+
+/// An investigation without its timeline and graph, as stored in the
+/// `investigations` tree
+fn case_header(investigation: &Investigation) -> Investigation {
+    Investigation {
+        id: investigation.id.clone(),
+        name: investigation.name.clone(),
+        description: investigation.description.clone(),
+        status: investigation.status.clone(),
+        timeline: Vec::new(),
+        graph: InvestigationGraph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        },
+        created_at: investigation.created_at,
+        updated_at: investigation.updated_at,
+    }
+}
+
+/// Key of the child record at `position` under an investigation
+fn child_key(investigation_id: &str, position: usize) -> String {
+    format!("{}/{:010}", investigation_id, position)
+}
+
+/// Investigation ID of a `<investigation_id>/<position>` child key
+fn parent_id(key: &[u8]) -> String {
+    let key = String::from_utf8_lossy(key);
+    key.rsplit_once('/')
+        .map(|(id, _)| id.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Collapse a sled transaction error into a [`StorageError`]
+fn from_transaction_error(e: TransactionError<StorageError>) -> StorageError {
+    match e {
+        TransactionError::Abort(e) => e,
+        TransactionError::Storage(e) => StorageError::Database(e),
+    }
+}
+
 /// Database statistics
 #[derive(Debug)]
 pub struct DatabaseStats {
     pub identity_count: usize,
     pub entity_count: usize,
     pub session_count: usize,
+    pub investigation_count: usize,
     pub size_on_disk: u64,
 }
@@ -0,0 +1,184 @@
+//! Storage Backend Trait
+//!
+//! Everything the rest of the app needs from a store. [`SledStore`] keeps
+//! records in an encrypted sled database on disk; [`MemoryStore`] keeps them
+//! in process memory only, for ephemeral sessions and tests.
+//!
+//! [`SledStore`]: super::SledStore
+//! [`MemoryStore`]: super::MemoryStore
+
+use crate::core::entity::{Entity, EntityType};
+use crate::core::identity::Identity;
+use crate::investigation::Investigation;
+use crate::storage::audit::{AuditAction, AuditRecord};
+use crate::storage::backup::StoreSnapshot;
+use crate::storage::{CapturedPage, EntityFilter, EntityPage, SearchHit, StorageError};
+use chrono::{DateTime, Utc};
+
+/// A storage backend
+pub trait Store: Send + Sync {
+    // ============ Identity Operations ============
+
+    /// Save an identity
+    fn save_identity(&self, identity: &Identity) -> Result<(), StorageError>;
+
+    /// Get an identity by ID
+    fn get_identity(&self, id: &str) -> Result<Option<Identity>, StorageError>;
+
+    /// Get all identities
+    fn get_all_identities(&self) -> Result<Vec<Identity>, StorageError>;
+
+    /// Delete an identity
+    fn delete_identity(&self, id: &str) -> Result<(), StorageError>;
+
+    // ============ Config Operations ============
+
+    /// Set the active identity
+    fn set_active_identity(&self, id: &str) -> Result<(), StorageError>;
+
+    /// Get the active identity
+    fn get_active_identity(&self) -> Result<Identity, StorageError>;
+
+    /// Read a named setting
+    fn get_setting(&self, key: &str) -> Result<Option<serde_json::Value>, StorageError>;
+
+    /// Write a named setting
+    fn set_setting(&self, key: &str, value: &serde_json::Value) -> Result<(), StorageError>;
+
+    /// Remove a named setting
+    fn delete_setting(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Stored schema version, or `None` for a store that predates versioning
+    fn schema_version(&self) -> Result<Option<u32>, StorageError>;
+
+    /// Change the passphrase protecting the store
+    fn change_passphrase(&self, current: &str, new: &str) -> Result<(), StorageError>;
+
+    // ============ Entity Operations ============
+
+    /// Save an entity, merging into the secondary and search indexes
+    fn save_entity(&self, entity: &Entity) -> Result<(), StorageError>;
+
+    /// Get an entity by hash
+    fn get_entity(&self, hash: &str) -> Result<Option<Entity>, StorageError>;
+
+    /// Get all entities
+    fn get_all_entities(&self) -> Result<Vec<Entity>, StorageError>;
+
+    /// Delete one entity and its index entries
+    fn delete_entity(&self, hash: &str) -> Result<(), StorageError>;
+
+    /// Clear all entities
+    fn clear_entities(&self) -> Result<(), StorageError>;
+
+    /// Page through the entities matching `filter`, in a stable order.
+    ///
+    /// Pass the previous page's `next_cursor` to continue.
+    fn query_entities(
+        &self,
+        filter: &EntityFilter,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError>;
+
+    /// Page through entities by `last_seen`, most recent first.
+    ///
+    /// `before` limits the first page to entities seen strictly earlier.
+    fn entities_by_last_seen(
+        &self,
+        before: Option<DateTime<Utc>>,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<EntityPage, StorageError>;
+
+    /// Hashes of entities discovered by more than one identity
+    fn cross_reference_hashes(&self) -> Result<Vec<String>, StorageError>;
+
+    /// Get entities by type
+    fn get_entities_by_type(&self, entity_type: &EntityType) -> Result<Vec<Entity>, StorageError> {
+        let filter = EntityFilter::Type(entity_type.clone());
+        let mut entities = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self.query_entities(&filter, cursor.as_deref(), 500)?;
+            entities.extend(page.entities);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(entities),
+            }
+        }
+    }
+
+    // ============ Session Operations ============
+
+    /// Save session data for an identity
+    fn save_session(&self, identity_id: &str, data: &[u8]) -> Result<(), StorageError>;
+
+    /// Get session data for an identity
+    fn get_session(&self, identity_id: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Clear session for an identity
+    fn clear_session(&self, identity_id: &str) -> Result<(), StorageError>;
+
+    // ============ Investigation Operations ============
+
+    /// Save an investigation with its timeline and graph
+    fn save_investigation(&self, investigation: &Investigation) -> Result<(), StorageError>;
+
+    /// Get an investigation by ID
+    fn get_investigation(&self, id: &str) -> Result<Option<Investigation>, StorageError>;
+
+    /// Get all investigations
+    fn get_all_investigations(&self) -> Result<Vec<Investigation>, StorageError>;
+
+    /// Delete an investigation with its timeline and graph
+    fn delete_investigation(&self, id: &str) -> Result<(), StorageError>;
+
+    // ============ Page Capture Operations ============
+
+    /// Save captured page text, replacing any earlier capture of the URL
+    fn save_page(&self, page: &CapturedPage) -> Result<(), StorageError>;
+
+    /// Get a captured page by ID
+    fn get_page(&self, id: &str) -> Result<Option<CapturedPage>, StorageError>;
+
+    /// Get every captured page
+    fn get_all_pages(&self) -> Result<Vec<CapturedPage>, StorageError>;
+
+    /// Delete a captured page
+    fn delete_page(&self, id: &str) -> Result<(), StorageError>;
+
+    /// Delete every page captured by an identity, returning how many
+    fn delete_pages_by_identity(&self, identity_id: &str) -> Result<usize, StorageError>;
+
+    // ============ Search Operations ============
+
+    /// Full-text search over entities, timelines and captured pages
+    fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>, StorageError>;
+
+    // ============ Audit Operations ============
+
+    /// Append a record to the audit chain
+    fn append_audit(&self, actor: &str, action: AuditAction) -> Result<AuditRecord, StorageError>;
+
+    /// Every audit record, oldest first
+    fn audit_log(&self) -> Result<Vec<AuditRecord>, StorageError>;
+
+    /// Hash of the newest audit record, kept apart from the records
+    fn audit_head(&self) -> Result<Option<String>, StorageError>;
+
+    // ============ Snapshot Operations ============
+
+    /// Take a consistent, decrypted copy of every record
+    fn snapshot(&self) -> Result<StoreSnapshot, StorageError>;
+
+    /// Replace every record with the contents of a snapshot.
+    ///
+    /// The audit log is left untouched: it only ever grows.
+    fn restore_snapshot(&self, snapshot: &StoreSnapshot) -> Result<(), StorageError>;
+
+    // ============ Database Operations ============
+
+    /// Flush all pending writes
+    fn flush(&self) -> Result<(), StorageError>;
+}
@@ -4,11 +4,12 @@
 //! Every UI event flows through `update()` as a typed `Message`.
 
 use iced::{Element, Subscription, Task, Theme};
+use zeroize::Zeroizing;
 
 use crate::commands;
 use crate::mcp::Agent;
 use crate::ui::messages::Message;
-use crate::ui::state::{AppState, ActivePanel, ChatEntry, ChatRole, OpsecLevel, OsintDisplay, Tab};
+use crate::ui::state::{AppState, ActivePanel, ChatEntry, ChatRole, OpsecLevel, OsintDisplay, Tab, UnlockState};
 use crate::ui::theme;
 use crate::ui::views;
 
@@ -19,11 +20,24 @@ pub struct SpinApp {
 impl SpinApp {
     // ── Constructor ────────────────────────────────────────────────────────
 
-    pub fn new() -> (Self, Task<Message>) {
-        let app = Self {
+    /// `unlock` is set when the vault still has to be unlocked; startup data
+    /// is only loaded once it is.
+    pub fn new(unlock: Option<UnlockState>) -> (Self, Task<Message>) {
+        let mut app = Self {
             state: AppState::default(),
         };
 
+        if let Some(unlock) = unlock {
+            app.state.status = "Locked.".to_string();
+            app.state.unlock = Some(unlock);
+            return (app, Task::none());
+        }
+
+        (app, Self::bootstrap())
+    }
+
+    /// Load what the main layout shows from the open vault
+    fn bootstrap() -> Task<Message> {
         // Load startup data in parallel via Task::perform chains.
         // Maps to what the React app did on mount with initializeApp thunks.
         let bootstrap = Task::perform(
@@ -44,7 +58,13 @@ impl SpinApp {
             },
         );
 
-        (app, bootstrap)
+        // Persisted cases are reloaded by investigation::init(); surface them.
+        let investigations = Task::perform(
+            commands::investigation::get_all_investigations(),
+            |res| Message::InvestigationsLoaded(res.unwrap_or_default()),
+        );
+
+        Task::batch([bootstrap, investigations])
     }
 
     // ── Title ──────────────────────────────────────────────────────────────
@@ -111,6 +131,67 @@ impl SpinApp {
                 Task::none()
             }
 
+            // ── Unlock ────────────────────────────────────────────────────
+            Message::UnlockPassphraseChanged(passphrase) => {
+                if let Some(unlock) = s.unlock.as_mut() {
+                    unlock.passphrase = Zeroizing::new(passphrase);
+                }
+                Task::none()
+            }
+
+            Message::UnlockConfirmChanged(confirm) => {
+                if let Some(unlock) = s.unlock.as_mut() {
+                    unlock.confirm = Zeroizing::new(confirm);
+                }
+                Task::none()
+            }
+
+            Message::Unlock => {
+                let Some(unlock) = s.unlock.as_mut() else {
+                    return Task::none();
+                };
+                if unlock.passphrase.is_empty() {
+                    unlock.error = Some("Passphrase cannot be empty".to_string());
+                    return Task::none();
+                }
+                if unlock.is_new && *unlock.confirm != *unlock.passphrase {
+                    unlock.error = Some("Passphrases do not match".to_string());
+                    return Task::none();
+                }
+                unlock.error = None;
+                unlock.unlocking = true;
+                s.status = "Unlocking…".to_string();
+
+                Task::perform(
+                    commands::vault::open_vault(
+                        unlock.vault.clone(),
+                        unlock.passphrase.to_string(),
+                        Some(unlock.migration_options.clone()),
+                    ),
+                    |res| match res {
+                        Ok(_) => Message::Unlocked,
+                        Err(e) => Message::UnlockFailed(e),
+                    },
+                )
+            }
+
+            Message::Unlocked => {
+                s.unlock = None;
+                s.status = "Loading…".to_string();
+                Self::bootstrap()
+            }
+
+            Message::UnlockFailed(e) => {
+                if let Some(unlock) = s.unlock.as_mut() {
+                    unlock.passphrase = Zeroizing::new(String::new());
+                    unlock.confirm = Zeroizing::new(String::new());
+                    unlock.unlocking = false;
+                    unlock.error = Some(e);
+                }
+                s.status = "Locked.".to_string();
+                Task::none()
+            }
+
             // ── Navigation ────────────────────────────────────────────────
             Message::UrlBarChanged(url) => {
                 s.url_bar = url;
@@ -310,6 +391,8 @@ impl SpinApp {
                         format!("Page: {}", url),
                         identity_id,
                         Some(url),
+                        None,
+                        None,
                     ),
                     |res| match res {
                         Ok(entities) => Message::EntitiesLoaded(entities),
@@ -509,7 +592,10 @@ impl SpinApp {
     // ── View ───────────────────────────────────────────────────────────────
 
     pub fn view(&self) -> Element<Message> {
-        views::layout::main_layout(&self.state)
+        match &self.state.unlock {
+            Some(unlock) => views::unlock::unlock_screen(&self.state, unlock),
+            None => views::layout::main_layout(&self.state),
+        }
     }
 }
 
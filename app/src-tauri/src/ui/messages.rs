@@ -20,6 +20,13 @@ pub enum Message {
     },
     StartupError(String),
 
+    // ── Unlock ─────────────────────────────────────────────────────────────
+    UnlockPassphraseChanged(String),
+    UnlockConfirmChanged(String),
+    Unlock,
+    Unlocked,
+    UnlockFailed(String),
+
     // ── Navigation ─────────────────────────────────────────────────────────
     UrlBarChanged(String),
     Navigate,
@@ -5,6 +5,8 @@
 use crate::core::identity::Identity;
 use crate::investigation::InvestigationSummary;
 use crate::mcp::Agent;
+use crate::storage::MigrationOptions;
+use zeroize::Zeroizing;
 
 // ─── Panel routing ─────────────────────────────────────────────────────────
 
@@ -97,6 +99,36 @@ pub struct ChatEntry {
     pub agent_name: Option<String>,
 }
 
+// ─── Unlock ────────────────────────────────────────────────────────────────
+
+/// Passphrase prompt shown before the vault's store is opened
+#[derive(Debug, Clone)]
+pub struct UnlockState {
+    pub vault: String,
+    /// No store exists yet, so the passphrase is chosen (and confirmed) now
+    pub is_new: bool,
+    pub passphrase: Zeroizing<String>,
+    pub confirm: Zeroizing<String>,
+    /// How pending schema migrations run once the store is unlocked
+    pub migration_options: MigrationOptions,
+    pub error: Option<String>,
+    pub unlocking: bool,
+}
+
+impl UnlockState {
+    pub fn new(vault: impl Into<String>, is_new: bool, migration_options: MigrationOptions) -> Self {
+        Self {
+            vault: vault.into(),
+            is_new,
+            migration_options,
+            passphrase: Zeroizing::new(String::new()),
+            confirm: Zeroizing::new(String::new()),
+            error: None,
+            unlocking: false,
+        }
+    }
+}
+
 // ─── Privacy ───────────────────────────────────────────────────────────────
 
 // Re-export OpsecLevel for convenience
@@ -106,6 +138,10 @@ pub use crate::core::privacy_engine::OpsecLevel;
 
 /// The complete application state (replaces 9 Redux slices)
 pub struct AppState {
+    // ── Unlock ─────────────────────────────────────────────────────────────
+    /// Set until the vault is unlocked; the main layout is hidden meanwhile
+    pub unlock: Option<UnlockState>,
+
     // ── Tabs ───────────────────────────────────────────────────────────────
     pub tabs: Vec<Tab>,
     pub active_tab: usize,
@@ -157,6 +193,8 @@ pub struct AppState {
 impl Default for AppState {
     fn default() -> Self {
         Self {
+            unlock: None,
+
             tabs: vec![Tab::new_tab()],
             active_tab: 0,
             url_bar: String::new(),
@@ -126,8 +126,26 @@ fn entity_type_label(et: &EntityType) -> &'static str {
         EntityType::MacAddress => "MAC",
         EntityType::Uuid => "UUID",
         EntityType::Hashtag => "#TAG",
+        EntityType::Iban => "IBAN",
+        EntityType::MoneroAddress => "XMR",
+        EntityType::LitecoinAddress => "LTC",
+        EntityType::TronAddress => "TRX",
+        EntityType::SolanaAddress => "SOL",
+        EntityType::OnionAddress => "ONION",
+        EntityType::Asn => "ASN",
+        EntityType::Cidr => "CIDR",
+        EntityType::Cve => "CVE",
+        EntityType::Md5Hash => "MD5",
+        EntityType::Sha1Hash => "SHA1",
+        EntityType::Sha256Hash => "SHA256",
+        EntityType::Jwt => "JWT",
+        EntityType::AwsAccessKey => "AWS",
+        EntityType::GithubToken => "GH",
+        EntityType::SlackToken => "SLACK",
+        EntityType::PgpFingerprint => "PGP",
+        EntityType::TelegramHandle => "TG",
+        EntityType::DiscordInvite => "DISCORD",
         EntityType::Custom(_) => "CUSTOM",
-        _ => "OTHER",
     }
 }
 
@@ -137,9 +155,12 @@ fn entity_type_color(et: &EntityType) -> iced::Color {
         EntityType::Phone => iced::Color::from_rgb(0.063, 0.725, 0.506),
         EntityType::IpV4 | EntityType::IpV6 => iced::Color::from_rgb(0.961, 0.620, 0.044),
         EntityType::Domain | EntityType::Url => iced::Color::from_rgb(0.267, 0.565, 0.961),
-        EntityType::BitcoinAddress | EntityType::EthereumAddress => {
-            iced::Color::from_rgb(0.937, 0.780, 0.267)
-        }
+        EntityType::BitcoinAddress
+        | EntityType::EthereumAddress
+        | EntityType::MoneroAddress
+        | EntityType::LitecoinAddress
+        | EntityType::TronAddress
+        | EntityType::SolanaAddress => iced::Color::from_rgb(0.937, 0.780, 0.267),
         _ => colors::TEXT_MUTED,
     }
 }
@@ -16,3 +16,4 @@ pub mod settings;
 pub mod side_panel;
 pub mod tab_bar;
 pub mod title_bar;
+pub mod unlock;
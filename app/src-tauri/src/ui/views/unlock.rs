@@ -0,0 +1,104 @@
+//! Unlock screen — asks for the vault passphrase before the store is opened.
+//!
+//! Shown at startup instead of the main layout when no passphrase was
+//! supplied through `$SPIN_PASSPHRASE`. A vault without a store yet asks for
+//! the new passphrase twice.
+
+use iced::{
+    widget::{button, column, container, text, text_input},
+    Alignment, Element, Fill, Padding,
+};
+
+use crate::ui::messages::Message;
+use crate::ui::state::{AppState, UnlockState};
+use crate::ui::theme::colors;
+use crate::ui::views::identity::{active_btn_style, input_style};
+use crate::ui::views::title_bar;
+
+pub fn unlock_screen<'a>(state: &'a AppState, unlock: &'a UnlockState) -> Element<'a, Message> {
+    let heading = text(if unlock.is_new {
+        format!("Choose a passphrase for vault '{}'", unlock.vault)
+    } else {
+        format!("Unlock vault '{}'", unlock.vault)
+    })
+    .size(16)
+    .color(colors::TEXT);
+
+    let hint = text(if unlock.is_new {
+        "The store is encrypted with this passphrase. It cannot be recovered if lost."
+    } else {
+        "Enter the passphrase the store was sealed with."
+    })
+    .size(11)
+    .color(colors::TEXT_MUTED);
+
+    let submit = (!unlock.unlocking).then_some(Message::Unlock);
+
+    let mut passphrase = text_input("Passphrase", &unlock.passphrase)
+        .secure(true)
+        .size(13)
+        .padding(Padding::new(8.0))
+        .style(input_style);
+    if !unlock.unlocking {
+        passphrase = passphrase.on_input(Message::UnlockPassphraseChanged);
+        if !unlock.is_new {
+            passphrase = passphrase.on_submit(Message::Unlock);
+        }
+    }
+
+    let mut form = column![heading, hint, passphrase].spacing(10);
+
+    if unlock.is_new {
+        let mut confirm = text_input("Confirm passphrase", &unlock.confirm)
+            .secure(true)
+            .size(13)
+            .padding(Padding::new(8.0))
+            .style(input_style);
+        if !unlock.unlocking {
+            confirm = confirm
+                .on_input(Message::UnlockConfirmChanged)
+                .on_submit(Message::Unlock);
+        }
+        form = form.push(confirm);
+    }
+
+    if let Some(error) = &unlock.error {
+        form = form.push(text(error.as_str()).size(11).color(colors::DANGER));
+    }
+
+    let label = if unlock.unlocking {
+        "Unlocking…"
+    } else {
+        "Unlock"
+    };
+    form = form.push(
+        button(text(label).size(12))
+            .on_press_maybe(submit)
+            .padding(Padding::new(8.0).left(16.0).right(16.0))
+            .style(active_btn_style),
+    );
+
+    let card = container(form.align_x(Alignment::Start))
+        .width(420)
+        .padding(Padding::new(24.0))
+        .style(|_theme| iced::widget::container::Style {
+            background: Some(iced::Background::Color(colors::BG_PANEL)),
+            border: iced::Border {
+                color: colors::BORDER,
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            ..Default::default()
+        });
+
+    let body = container(card).center(Fill);
+
+    container(column![title_bar::title_bar(state), body])
+        .width(Fill)
+        .height(Fill)
+        .style(|_theme| iced::widget::container::Style {
+            background: Some(iced::Background::Color(colors::BG)),
+            ..Default::default()
+        })
+        .into()
+}
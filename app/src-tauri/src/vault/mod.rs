@@ -0,0 +1,359 @@
+//! Case Vaults
+//!
+//! A vault is a self-contained data directory with its own encrypted store,
+//! browser contexts and investigations, so material from different clients
+//! and cases never shares a database or a Hivemind. Exactly one vault is
+//! open at a time: opening another closes the current store and CEF manager
+//! before the new vault's are initialised.
+//!
+//! Layout under the application directory:
+//!
+//! - `vaults.json` — registry of vault names and timestamps (no case data)
+//! - `spin.db`, `cef/` — the `default` vault, i.e. the pre-vault layout
+//! - `vaults/<name>/spin.db`, `vaults/<name>/cef/` — every other vault
+//!
+//! The [`EPHEMERAL_VAULT`] is never registered: its store lives in memory and
+//! its browser contexts in a scratch directory shredded when it is closed.
+//! The scratch directory goes on a RAM-backed filesystem (`/dev/shm`) where
+//! one exists, so profiles only reach disk if the system swaps. Elsewhere it
+//! falls back to the OS temporary directory on disk, where shredding carries
+//! the usual caveats (journaling, copy-on-write, SSD wear levelling). A crash
+//! or kill before [`close`] leaves the directory behind in either place.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::cef::browser_context::shred_dir;
+use crate::storage::{MigrationOptions, MigrationReport};
+
+/// Vault that maps onto the application directory itself
+pub const DEFAULT_VAULT: &str = "default";
+
+/// Unregistered in-memory vault opened by [`open_ephemeral`]
+pub const EPHEMERAL_VAULT: &str = "ephemeral";
+
+/// Registry file name
+const REGISTRY_FILE: &str = "vaults.json";
+
+/// Global vault state
+static VAULTS: RwLock<Option<VaultState>> = RwLock::new(None);
+
+/// A registered vault
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultInfo {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_opened: Option<DateTime<Utc>>,
+    /// Whether this is the currently open vault (not persisted)
+    #[serde(default, skip_deserializing)]
+    pub is_open: bool,
+}
+
+/// Contents of `vaults.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VaultRegistry {
+    vaults: Vec<VaultInfo>,
+    last_opened: Option<String>,
+}
+
+#[derive(Debug)]
+struct VaultState {
+    app_dir: PathBuf,
+    registry: VaultRegistry,
+    active: Option<String>,
+    /// Scratch directory of the open ephemeral vault
+    ephemeral_dir: Option<PathBuf>,
+}
+
+impl VaultState {
+    fn dir_of(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_VAULT {
+            self.app_dir.clone()
+        } else {
+            self.app_dir.join("vaults").join(name)
+        }
+    }
+
+    fn save_registry(&self) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(&self.registry)
+            .map_err(|e| format!("Failed to serialize vault registry: {}", e))?;
+        let path = self.app_dir.join(REGISTRY_FILE);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| format!("Failed to write vault registry: {}", e))
+    }
+}
+
+/// Initialize the vault registry under the application directory.
+///
+/// Does not open a vault; call [`open`] once the passphrase is known.
+pub fn init(app_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let path = app_dir.join(REGISTRY_FILE);
+    let mut registry: VaultRegistry = if path.exists() {
+        serde_json::from_slice(&std::fs::read(&path)?)?
+    } else {
+        VaultRegistry::default()
+    };
+
+    if !registry.vaults.iter().any(|v| v.name == DEFAULT_VAULT) {
+        registry.vaults.insert(
+            0,
+            VaultInfo {
+                name: DEFAULT_VAULT.to_string(),
+                created_at: Utc::now(),
+                last_opened: None,
+                is_open: false,
+            },
+        );
+    }
+
+    let state = VaultState {
+        app_dir: app_dir.to_path_buf(),
+        registry,
+        active: None,
+        ephemeral_dir: None,
+    };
+    state.save_registry()?;
+
+    tracing::info!(
+        "Vault registry loaded ({} vaults)",
+        state.registry.vaults.len()
+    );
+
+    let mut global = VAULTS
+        .write()
+        .map_err(|e| format!("Vault lock poisoned: {}", e))?;
+    *global = Some(state);
+    Ok(())
+}
+
+/// List every registered vault
+pub fn list() -> Result<Vec<VaultInfo>, String> {
+    with_state(|state| {
+        Ok(state
+            .registry
+            .vaults
+            .iter()
+            .map(|v| VaultInfo {
+                is_open: state.active.as_deref() == Some(v.name.as_str()),
+                ..v.clone()
+            })
+            .collect())
+    })
+}
+
+/// Name of the open vault, if any
+pub fn active() -> Result<Option<String>, String> {
+    with_state(|state| Ok(state.active.clone()))
+}
+
+/// Vault to open at startup: the last one opened, else `default`
+pub fn last_opened() -> Result<String, String> {
+    with_state(|state| {
+        Ok(state
+            .registry
+            .last_opened
+            .clone()
+            .filter(|name| state.registry.vaults.iter().any(|v| &v.name == name))
+            .unwrap_or_else(|| DEFAULT_VAULT.to_string()))
+    })
+}
+
+/// Data directory of a registered vault
+pub fn vault_dir(name: &str) -> Result<PathBuf, String> {
+    with_state(|state| {
+        if !state.registry.vaults.iter().any(|v| v.name == name) {
+            return Err(format!("Unknown vault '{}'", name));
+        }
+        Ok(state.dir_of(name))
+    })
+}
+
+/// Register a new, empty vault. Its store is created on first [`open`].
+pub fn create(name: &str) -> Result<VaultInfo, String> {
+    validate_name(name)?;
+    with_state_mut(|state| {
+        if state.registry.vaults.iter().any(|v| v.name == name) {
+            return Err(format!("Vault '{}' already exists", name));
+        }
+        let dir = state.dir_of(name);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create vault dir {:?}: {}", dir, e))?;
+
+        let info = VaultInfo {
+            name: name.to_string(),
+            created_at: Utc::now(),
+            last_opened: None,
+            is_open: false,
+        };
+        state.registry.vaults.push(info.clone());
+        state.save_registry()?;
+
+        tracing::info!("Created vault '{}' at {:?}", name, dir);
+        Ok(info)
+    })
+}
+
+/// Open a vault, closing the current one first.
+///
+/// Unlocks the vault's store (running migrations per `options`), then
+/// re-initialises CEF and the investigation cache against its directory.
+pub fn open(
+    name: &str,
+    passphrase: &str,
+    options: &MigrationOptions,
+) -> Result<MigrationReport, String> {
+    let dir = vault_dir(name)?;
+    close()?;
+
+    tracing::info!("Opening vault '{}' at {:?}", name, dir);
+
+    let report = crate::storage::init(&dir, passphrase, options)
+        .map_err(|e| format!("Failed to unlock vault '{}': {}", name, e))?;
+    let opened = crate::cef::init(&dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| crate::investigation::init().map_err(|e| e.to_string()))
+        .and_then(|_| crate::commands::extractors::load())
+        .and_then(|_| crate::commands::suppression::load())
+        .and_then(|_| crate::commands::brands::load());
+    if let Err(e) = opened {
+        let _ = close_modules();
+        return Err(format!("Failed to open vault '{}': {}", name, e));
+    }
+
+    with_state_mut(|state| {
+        let now = Utc::now();
+        if let Some(info) = state.registry.vaults.iter_mut().find(|v| v.name == name) {
+            info.last_opened = Some(now);
+        }
+        state.registry.last_opened = Some(name.to_string());
+        state.active = Some(name.to_string());
+        state.save_registry()
+    })?;
+
+    Ok(report)
+}
+
+/// Open a burn-after-use vault, closing the current one first.
+///
+/// The store is in memory only and CEF profiles go to a fresh scratch
+/// directory, RAM-backed where possible; both are discarded by [`close`].
+/// Nothing is recorded in the registry.
+pub fn open_ephemeral() -> Result<(), String> {
+    close()?;
+
+    let dir = create_ephemeral_dir()?;
+    tracing::info!("Opening ephemeral vault at {:?}", dir);
+
+    let opened = crate::storage::init_in_memory(&dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| crate::cef::init(&dir).map_err(|e| e.to_string()))
+        .and_then(|_| crate::investigation::init().map_err(|e| e.to_string()))
+        .and_then(|_| crate::commands::extractors::load())
+        .and_then(|_| crate::commands::suppression::load())
+        .and_then(|_| crate::commands::brands::load());
+    if let Err(e) = opened {
+        let _ = close_modules();
+        let _ = shred_dir(&dir);
+        return Err(format!("Failed to open ephemeral vault: {}", e));
+    }
+
+    with_state_mut(|state| {
+        state.active = Some(EPHEMERAL_VAULT.to_string());
+        state.ephemeral_dir = Some(dir);
+        Ok(())
+    })
+}
+
+/// Close the open vault, if any: drop cached investigations, privacy
+/// profile and custom extractors, tear down browser contexts and flush and
+/// release the store
+pub fn close() -> Result<(), String> {
+    let Some(name) = active()? else {
+        return Ok(());
+    };
+    close_modules()?;
+    let ephemeral_dir = with_state_mut(|state| {
+        state.active = None;
+        Ok(state.ephemeral_dir.take())
+    })?;
+    if let Some(dir) = ephemeral_dir {
+        let shredded = shred_dir(&dir)
+            .map_err(|e| format!("Failed to shred ephemeral dir {:?}: {}", dir, e))?;
+        tracing::info!("Shredded ephemeral vault ({} files)", shredded.files);
+    }
+    tracing::info!("Closed vault '{}'", name);
+    Ok(())
+}
+
+/// Fresh scratch directory for an ephemeral vault, on `/dev/shm` when it is
+/// available and writable, otherwise under the OS temporary directory
+fn create_ephemeral_dir() -> Result<PathBuf, String> {
+    let name = format!("spin-ephemeral-{}", uuid::Uuid::new_v4());
+    let ram = Path::new("/dev/shm");
+    if ram.is_dir() {
+        let dir = ram.join(&name);
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) => tracing::warn!("Cannot use {:?} for the ephemeral vault: {}", ram, e),
+        }
+    }
+
+    let dir = std::env::temp_dir().join(name);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create ephemeral dir {:?}: {}", dir, e))?;
+    tracing::warn!("No RAM-backed directory; ephemeral browser profiles go to disk");
+    Ok(dir)
+}
+
+fn close_modules() -> Result<(), String> {
+    crate::commands::privacy::unload()?;
+    crate::core::custom_extractors::clear();
+    crate::core::suppression::clear();
+    crate::core::idn::clear();
+    crate::investigation::close()?;
+    crate::cef::shutdown()?;
+    crate::storage::close().map_err(|e| format!("Failed to close store: {}", e))
+}
+
+/// Vault names become directory names, so keep them to a safe alphabet
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("Vault name must be 1-64 characters".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Vault name may only contain letters, digits, '-' and '_'".to_string());
+    }
+    if name == EPHEMERAL_VAULT {
+        return Err(format!("'{}' is reserved", EPHEMERAL_VAULT));
+    }
+    Ok(())
+}
+
+fn with_state<F, R>(f: F) -> Result<R, String>
+where
+    F: FnOnce(&VaultState) -> Result<R, String>,
+{
+    let guard = VAULTS
+        .read()
+        .map_err(|e| format!("Vault lock poisoned: {}", e))?;
+    let state = guard.as_ref().ok_or("Vaults not initialized")?;
+    f(state)
+}
+
+fn with_state_mut<F, R>(f: F) -> Result<R, String>
+where
+    F: FnOnce(&mut VaultState) -> Result<R, String>,
+{
+    let mut guard = VAULTS
+        .write()
+        .map_err(|e| format!("Vault lock poisoned: {}", e))?;
+    let state = guard.as_mut().ok_or("Vaults not initialized")?;
+    f(state)
+}